@@ -64,9 +64,12 @@ lazy_static! {
         // 1. Default values
         settings.set_default("BE_RUSTC", false).unwrap();
         settings.set_default("VIPER_BACKEND", "Silicon").unwrap();
+        settings.set_default("VIPER_BACKEND_MODE", "single").unwrap();
+        settings.set_default("VERIFICATION_BACKEND", "viper").unwrap();
         settings.set_default("CHECK_FOLDUNFOLD_STATE", false).unwrap();
         settings.set_default("CHECK_OVERFLOWS", false).unwrap();
         settings.set_default("CHECK_PANICS", true).unwrap();
+        settings.set_default::<Vec<String>>("DISABLED_PANIC_CHECKS", vec![]).unwrap();
         settings.set_default("ENCODE_UNSIGNED_NUM_CONSTRAINT", false).unwrap();
         settings.set_default("SIMPLIFY_ENCODING", true).unwrap();
         settings.set_default("LOG_DIR", "./log/").unwrap();
@@ -85,11 +88,19 @@ lazy_static! {
         settings.set_default("ASSERT_TIMEOUT", 10_000).unwrap();
         settings.set_default("USE_MORE_COMPLETE_EXHALE", true).unwrap();
         settings.set_default("SKIP_UNSUPPORTED_FEATURES", false).unwrap();
+        settings.set_default("REPORT_SUPPORT_STATUS", false).unwrap();
+        settings.set_default::<Vec<String>>("VERIFY_ONLY", vec![]).unwrap();
         settings.set_default("ALLOW_UNREACHABLE_UNSUPPORTED_CODE", false).unwrap();
         settings.set_default("NO_VERIFY", false).unwrap();
         settings.set_default("FULL_COMPILATION", false).unwrap();
+        settings.set_default("CROSS_CRATE_SPECS", false).unwrap();
         settings.set_default("JSON_COMMUNICATION", false).unwrap();
         settings.set_default("JSON_COMMUNICATION", false).unwrap();
+        settings.set_default("ENABLE_CACHE", false).unwrap();
+        settings.set_default("SERVER_WORKER_PROCESSES", true).unwrap();
+        settings.set_default("SERVER_CACHE_SIZE", 1000).unwrap();
+        settings.set_default("PRODUCE_COUNTEREXAMPLE", false).unwrap();
+        settings.set_default("EXPLAIN_VERIFICATION_ERRORS", false).unwrap();
         settings.set_default("OPTIMIZATIONS","all").unwrap();
         settings.set_default("INTERN_NAMES", true).unwrap();
         settings.set_default("ENABLE_PURIFICATION_OPTIMIZATION", false).unwrap();
@@ -175,11 +186,53 @@ pub fn viper_backend() -> String {
         .to_string()
 }
 
+/// How the Viper programs are verified: `single` uses the Viper backend selected by
+/// `VIPER_BACKEND`, `portfolio` runs both Silicon and Carbon and takes the first definitive
+/// result, and `cross-check` runs both and warns when their results differ. Without a server,
+/// the backends verify a program one after the other, and in `portfolio` mode the other backend
+/// only verifies it if the backend selected by `VIPER_BACKEND` gives no definitive result.
+pub fn viper_backend_mode() -> String {
+    read_setting::<String>("VIPER_BACKEND_MODE")
+        .to_lowercase()
+        .trim()
+        .to_string()
+}
+
+/// The backend that verifies the Viper programs: `viper` runs the Viper backend selected by
+/// `VIPER_BACKEND` on a JVM, `mock` answers with the results of `MOCK_BACKEND_RESULTS` without
+/// starting a JVM. Other backends can be registered with `prusti_server::register_backend`.
+pub fn verification_backend() -> String {
+    read_setting::<String>("VERIFICATION_BACKEND")
+        .to_lowercase()
+        .trim()
+        .to_string()
+}
+
+/// The file of the results of the `mock` verification backend: a JSON object that maps the names
+/// of programs to their result. The programs that are not in the file verify successfully.
+pub fn mock_backend_results() -> Option<String> {
+    read_optional_setting("MOCK_BACKEND_RESULTS")
+}
+
 /// Should we check absence of panics?
 pub fn check_panics() -> bool {
     read_setting("CHECK_PANICS")
 }
 
+/// The categories of panics whose absence should not be checked, e.g. `todo` or `overflow`.
+/// The categories are `panic`, `assert`, `debug_assert`, `unreachable`, `unimplemented`, `todo`,
+/// `unwrap`, `index_out_of_bounds`, `division_by_zero`, `overflow` and `other`. When given as a
+/// string (e.g. in an environment variable), categories are separated by commas.
+pub fn disabled_panic_checks() -> Vec<String> {
+    read_optional_setting::<Vec<String>>("DISABLED_PANIC_CHECKS").unwrap_or_else(|| {
+        read_setting::<String>("DISABLED_PANIC_CHECKS")
+            .split(',')
+            .map(|category| category.trim().to_string())
+            .filter(|category| !category.is_empty())
+            .collect()
+    })
+}
+
 /// Should we simplify the encoding before passing it to Viper?
 pub fn simplify_encoding() -> bool {
     read_setting("SIMPLIFY_ENCODING")
@@ -304,12 +357,42 @@ pub fn server_max_stored_verifiers() -> Option<usize> {
 }
 
 /// The maximum amount of verification requests the server will work on concurrently.
+/// Requests are only verified concurrently if `SERVER_WORKER_PROCESSES` is enabled, as it is by
+/// default.
 ///
 /// If not set, this defaults to the number of (logical) cores on the system
 pub fn server_max_concurrency() -> Option<usize> {
     read_optional_setting("SERVER_MAX_CONCURRENCY")
 }
 
+/// When enabled (the default), the server verifies the requests on a pool of up to
+/// `SERVER_MAX_CONCURRENCY` child processes, each with its own JVM. The child processes are only
+/// started when all the others are busy with a request, and crashed ones are replaced. When
+/// disabled, the requests are verified one after the other on verifier threads
+/// of the JVM of the server. This only affects the `prusti-server` executable.
+pub fn server_worker_processes() -> bool {
+    read_setting("SERVER_WORKER_PROCESSES")
+}
+
+/// The maximum number of verification results that the server keeps in its cache, which is
+/// shared by all its clients. Set to 0 to disable the cache of the server.
+pub fn server_cache_size() -> usize {
+    read_setting("SERVER_CACHE_SIZE")
+}
+
+/// When set, the cache of the server is loaded from this file and stored in it, so that it
+/// survives restarts of the server. The changes of the cache are written about once per second.
+pub fn server_cache_path() -> Option<String> {
+    read_optional_setting("SERVER_CACHE_PATH")
+}
+
+/// When set, each verification request is written, together with its result, to a new file in
+/// this directory, by the process that verifies it with Viper. Results taken from a cache are not
+/// recorded. The request can then be verified again with `prusti-server replay <file>`.
+pub fn record_requests() -> Option<String> {
+    read_optional_setting("RECORD_REQUESTS")
+}
+
 /// When set, Prusti will connect to this server and use it for its verification backend (i.e. the things using the JVM/Viper).
 /// Set to "MOCK" to run the server off-thread, effectively mocking connecting to a server without having to start it up separately.
 /// e.g. "127.0.0.1:2468"
@@ -317,6 +400,69 @@ pub fn server_address() -> Option<String> {
     read_optional_setting("SERVER_ADDRESS")
 }
 
+/// The maximum amount of procedures that are verified concurrently as separate requests to the
+/// server. Without a server, the procedures are verified one after the other, because Viper does
+/// not support parallel verifiers in one JVM.
+///
+/// If not set, this defaults to the number of (logical) cores on the system
+pub fn num_parallel_verifiers() -> Option<usize> {
+    read_optional_setting("NUM_PARALLEL_VERIFIERS")
+}
+
+/// Whether the results of verifying each procedure should be stored in a persistent cache, so
+/// that unchanged procedures are not verified again. Edits elsewhere in the crate do not change
+/// whether a procedure is considered unchanged.
+///
+/// This is disabled by default, but `cargo-prusti` enables it unless it is set explicitly.
+pub fn enable_cache() -> bool {
+    read_setting("ENABLE_CACHE")
+}
+
+/// The file in which the verification cache is stored.
+///
+/// If not set, this defaults to `prusti-verification-cache.bin` in the output directory of the
+/// compiled crate (e.g. `target/debug/deps` under Cargo).
+pub fn cache_path() -> Option<String> {
+    read_optional_setting("CACHE_PATH")
+}
+
+/// Whether the verifier should produce counterexamples for failing assertions, which are
+/// reported as a note on the verification error.
+pub fn produce_counterexample() -> bool {
+    read_setting("PRODUCE_COUNTEREXAMPLE")
+}
+
+/// Whether verification errors should be explained by the branches of the procedure that lead to
+/// them, e.g. "took the `else` branch at line 12". The explanation is computed from the
+/// counterexample of the error, which is requested from the verifier in this mode.
+pub fn explain_verification_errors() -> bool {
+    read_setting("EXPLAIN_VERIFICATION_ERRORS")
+}
+
+/// The maximum wall-clock time, in seconds, that the verification of a procedure may take before
+/// it is reported as timed out. It can be overridden for a procedure with `#[timeout(seconds)]`.
+/// Silicon stops the verification when the timeout elapses. The verification of Carbon cannot be
+/// stopped in-process, so it is only abandoned, and the next procedures are verified once it has
+/// finished, unless the server runs it in a worker process, which is killed.
+///
+/// If not set, the verification of a procedure has no time limit.
+pub fn procedure_timeout() -> Option<u64> {
+    read_optional_setting("PROCEDURE_TIMEOUT")
+}
+
+/// The file to which a JSON report of the verification of each procedure (status, errors and
+/// timings) is written. If several crates are verified, the file contains the report of each
+/// crate.
+pub fn json_report() -> Option<String> {
+    read_optional_setting("JSON_REPORT")
+}
+
+/// The file to which the verification errors are written in the SARIF format, to be consumed by
+/// code scanning tools. If several crates are verified, the file contains a run for each crate.
+pub fn sarif_report() -> Option<String> {
+    read_optional_setting("SARIF_REPORT")
+}
+
 /// If true, communication with the server will be encoded as json and not the default of bincode.
 pub fn json_communication() -> bool {
     read_setting("JSON_COMMUNICATION")
@@ -354,6 +500,21 @@ pub fn verify_only_basic_block_path() -> Vec<String> {
     read_setting("VERIFY_ONLY_BASIC_BLOCK_PATH")
 }
 
+/// Verify only the procedures whose path matches one of the given patterns, e.g.
+/// `my_crate::module::*` or `Stack::push`. In a pattern, `*` matches any sequence of characters.
+/// A pattern matches a path if it matches the whole path or a suffix of it that starts after
+/// a `::`. When given as a string (e.g. in an environment variable), patterns are separated by
+/// commas.
+pub fn verify_only() -> Vec<String> {
+    read_optional_setting::<Vec<String>>("VERIFY_ONLY").unwrap_or_else(|| {
+        read_setting::<String>("VERIFY_ONLY")
+            .split(',')
+            .map(|pattern| pattern.trim().to_string())
+            .filter(|pattern| !pattern.is_empty())
+            .collect()
+    })
+}
+
 /// Which optimizations should be enabled
 pub fn optimizations() -> Optimizations {
     let optimizations_string = read_setting::<String>("OPTIMIZATIONS");
@@ -394,6 +555,16 @@ pub fn skip_unsupported_features() -> bool {
     read_setting("SKIP_UNSUPPORTED_FEATURES")
 }
 
+/// Report the features of each procedure that are not fully supported, before encoding it
+pub fn report_support_status() -> bool {
+    read_setting("REPORT_SUPPORT_STATUS")
+}
+
+/// The path of a JSON file to which the support status of all procedures is written
+pub fn support_status_report() -> Option<String> {
+    read_optional_setting("SUPPORT_STATUS_REPORT")
+}
+
 /// Encode unsupported code as `assert false`, so that we report error messages
 /// only for unsupported code that is actually reachable.
 pub fn allow_unreachable_unsupported_code() -> bool {
@@ -410,6 +581,12 @@ pub fn full_compilation() -> bool {
     read_setting("FULL_COMPILATION")
 }
 
+/// Export the specifications of the crate next to its metadata, and import
+/// the specifications exported by its dependencies.
+pub fn cross_crate_specs() -> bool {
+    read_setting("CROSS_CRATE_SPECS")
+}
+
 /// Intern Viper identifiers to shorten them when possible.
 pub fn intern_names() -> bool {
     read_setting("INTERN_NAMES")
@@ -12,6 +12,7 @@
 
 #[macro_use]
 extern crate log;
+extern crate atty;
 extern crate config as config_crate;
 extern crate itertools;
 #[macro_use]
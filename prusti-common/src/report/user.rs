@@ -6,6 +6,7 @@
 
 //! This module defines functions for user output: the nice, clean, readable, polished output
 
+use atty;
 use config;
 
 /// Print to stderr a message that is only meant to be read by the user.
@@ -16,3 +17,18 @@ pub fn message<S: ToString>(msg: S) {
         eprintln!("{}", msg.to_string())
     }
 }
+
+/// Print to stderr a message that replaces the previous progress message, e.g. the procedures
+/// that are being verified. This is only done if stderr is a terminal.
+/// Note: this function does nothing if `config::quiet()` is true.
+pub fn progress<S: ToString>(msg: S) {
+    if !config::quiet() && atty::is(atty::Stream::Stderr) {
+        // Return to the start of the line and clear it
+        eprint!("\r\x1b[K{}", msg.to_string())
+    }
+}
+
+/// Remove the last progress message from stderr.
+pub fn clear_progress() {
+    progress("")
+}
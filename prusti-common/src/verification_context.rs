@@ -21,7 +21,9 @@ pub struct VerifierBuilder {
 
 impl VerifierBuilder {
     pub fn new() -> Self {
-        Self::new_with_backend(VerificationBackend::from_str(&config::viper_backend()))
+        Self::new_with_backend(config::viper_backend().parse().unwrap_or_else(|error| {
+            panic!("{}", error)
+        }))
     }
 
     pub fn new_with_backend(backend: VerificationBackend) -> Self {
@@ -1,9 +1,32 @@
 use config;
-use viper::{self, VerificationBackend};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use viper::VerificationBackend;
 use vir::Program;
 
-pub trait VerificationService {
-    fn verify(&self, request: VerificationRequest) -> viper::VerificationResult;
+/// The sources of the types that are sent to a server in a `VerificationRequest`.
+const REQUEST_SCHEMA_SOURCES: &[&str] = &[
+    include_str!("verification_service.rs"),
+    include_str!("vir/program.rs"),
+    include_str!("vir/borrows.rs"),
+    include_str!("vir/cfg/method.rs"),
+    include_str!("vir/ast/bodyless_method.rs"),
+    include_str!("vir/ast/common.rs"),
+    include_str!("vir/ast/domain.rs"),
+    include_str!("vir/ast/expr.rs"),
+    include_str!("vir/ast/function.rs"),
+    include_str!("vir/ast/predicate.rs"),
+    include_str!("vir/ast/stmt.rs"),
+    include_str!("vir/ast/trigger.rs"),
+];
+
+/// A fingerprint of the definition of `VerificationRequest` and of the VIR types that it
+/// contains. A client and a server can exchange requests only if their fingerprints are equal.
+/// Any change to these files changes the fingerprint, even if it does not change the encoding.
+pub fn request_schema_fingerprint() -> u64 {
+    let mut hasher = DefaultHasher::new();
+    REQUEST_SCHEMA_SOURCES.hash(&mut hasher);
+    hasher.finish()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +34,8 @@ pub struct VerificationRequest {
     pub program: Program,
     pub program_name: String,
     pub backend_config: ViperBackendConfig,
+    /// The maximum wall-clock time, in seconds, that the verification may take.
+    pub timeout: Option<u64>,
 }
 
 /**
@@ -25,7 +50,16 @@ pub struct ViperBackendConfig {
 
 impl Default for ViperBackendConfig {
     fn default() -> Self {
-        let backend = VerificationBackend::from_str(&config::viper_backend());
+        Self::new(config::viper_backend().parse().unwrap_or_else(|error| {
+            panic!("{}", error)
+        }))
+    }
+}
+
+impl ViperBackendConfig {
+    /// The configuration of the given backend, with the verifier arguments given by the
+    /// configuration flags.
+    pub fn new(backend: VerificationBackend) -> Self {
         let mut verifier_args = config::extra_verifier_args();
         match backend {
             VerificationBackend::Silicon => {
@@ -38,11 +72,23 @@ impl Default for ViperBackendConfig {
                     "--logLevel".to_string(),
                     "ERROR".to_string(),
                 ]);
+                if config::produce_counterexample() || config::explain_verification_errors() {
+                    verifier_args.extend(vec![
+                        "--counterexample".to_string(),
+                        "mapped".to_string(),
+                    ]);
+                }
             }
             VerificationBackend::Carbon => {
                 verifier_args.extend(vec![
                     "--disableAllocEncoding".to_string(),
                 ]);
+                if config::produce_counterexample() || config::explain_verification_errors() {
+                    verifier_args.extend(vec![
+                        "--counterexample".to_string(),
+                        "variables".to_string(),
+                    ]);
+                }
             }
         }
         Self {
@@ -1,4 +1,7 @@
+use std::collections::HashSet;
 use vir::{ast::*, cfg::CfgMethod};
+use vir::optimizations::predicates::delete_unused_predicates;
+use vir::utils::{walk_functions, walk_methods};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Program {
@@ -9,3 +12,127 @@ pub struct Program {
     pub functions: Vec<Function>,
     pub viper_predicates: Vec<Predicate>,
 }
+
+impl Program {
+    /// Split the program into one program per method, so that the methods can be verified
+    /// independently. Each program contains only the functions and predicates that its method
+    /// transitively uses. Functions that are not used by any method are put in an additional
+    /// program without methods, so that they are still verified.
+    ///
+    /// Returns the name of the method (or `functions`) together with the program.
+    pub fn split_by_method(self) -> Vec<(String, Program)> {
+        let mut programs = vec![];
+        let mut functions_used_by_methods = HashSet::new();
+        for method in &self.methods {
+            let program = self.extract(vec![method.clone()], vec![]);
+            functions_used_by_methods.extend(
+                program.functions.iter().map(|function| function.get_identifier())
+            );
+            programs.push((method.name(), program));
+        }
+        let remaining_functions: Vec<_> = self.functions
+            .iter()
+            .filter(|function| !functions_used_by_methods.contains(&function.get_identifier()))
+            .cloned()
+            .collect();
+        if !remaining_functions.is_empty() {
+            programs.push(("functions".to_string(), self.extract(vec![], remaining_functions)));
+        }
+        programs
+    }
+
+    /// Build a program with the given methods and functions, together with the functions and
+    /// predicates that they transitively use.
+    fn extract(&self, methods: Vec<CfgMethod>, root_functions: Vec<Function>) -> Program {
+        let mut collector = UsedFunctionCollector {
+            used_functions: root_functions.iter().map(|f| f.get_identifier()).collect(),
+        };
+        walk_methods(&methods, &mut collector);
+        let mut used_functions = HashSet::new();
+        let (functions, viper_predicates) = loop {
+            // Functions can use predicates, whose bodies can use other functions.
+            let mut to_visit: Vec<String> = collector.used_functions.drain().collect();
+            while let Some(identifier) = to_visit.pop() {
+                if !used_functions.insert(identifier.clone()) {
+                    continue;
+                }
+                let function = self.functions.iter().find(|f| f.get_identifier() == identifier);
+                if let Some(function) = function {
+                    walk_functions(std::slice::from_ref(function), &mut collector);
+                    to_visit.extend(collector.used_functions.drain());
+                }
+            }
+            let functions: Vec<Function> = self.functions
+                .iter()
+                .filter(|function| used_functions.contains(&function.get_identifier()))
+                .cloned()
+                .collect();
+            let viper_predicates = delete_unused_predicates(
+                &methods,
+                &functions,
+                self.viper_predicates.clone(),
+            );
+            walk_predicates(&viper_predicates, &mut collector);
+            if collector.used_functions.is_subset(&used_functions) {
+                break (functions, viper_predicates);
+            }
+        };
+        Program {
+            domains: self.domains.clone(),
+            fields: self.fields.clone(),
+            builtin_methods: self.builtin_methods.clone(),
+            methods,
+            functions,
+            viper_predicates,
+        }
+    }
+}
+
+fn walk_predicates(predicates: &[Predicate], walker: &mut impl ExprWalker) {
+    for predicate in predicates {
+        match predicate {
+            Predicate::Struct(StructPredicate { body: Some(body), .. }) => {
+                ExprWalker::walk(walker, body);
+            }
+            Predicate::Enum(enum_predicate) => {
+                ExprWalker::walk(walker, &enum_predicate.discriminant);
+                ExprWalker::walk(walker, &enum_predicate.discriminant_bounds);
+                for (guard, _, variant) in &enum_predicate.variants {
+                    ExprWalker::walk(walker, guard);
+                    if let Some(body) = &variant.body {
+                        ExprWalker::walk(walker, body);
+                    }
+                }
+            }
+            Predicate::Struct(StructPredicate { body: None, .. }) |
+            Predicate::Bodyless(..) => {}
+        }
+    }
+}
+
+struct UsedFunctionCollector {
+    /// Identifiers of the used functions.
+    used_functions: HashSet<String>,
+}
+
+impl ExprWalker for UsedFunctionCollector {
+    fn walk_func_app(
+        &mut self,
+        name: &str,
+        args: &Vec<Expr>,
+        formal_args: &Vec<LocalVar>,
+        return_type: &Type,
+        _pos: &Position
+    ) {
+        self.used_functions.insert(compute_identifier(name, formal_args, return_type));
+        for arg in args {
+            ExprWalker::walk(self, arg);
+        }
+    }
+}
+
+impl StmtWalker for UsedFunctionCollector {
+    fn walk_expr(&mut self, expr: &Expr) {
+        ExprWalker::walk(self, expr);
+    }
+}
@@ -20,6 +20,9 @@ mod private {
     /// A macro for marking a function as trusted.
     pub use prusti_contracts_impl::trusted;
 
+    /// A macro for setting the verification timeout of a function, in seconds.
+    pub use prusti_contracts_impl::timeout;
+
     /// A macro for writing a loop body invariant.
     pub use prusti_contracts_impl::body_invariant;
 
@@ -31,6 +34,9 @@ mod private {
 
     /// A macro for specifying external functions.
     pub use prusti_contracts_impl::extern_spec;
+
+    /// A macro for writing an invariant on a trait.
+    pub use prusti_contracts_impl::invariant;
 }
 
 #[cfg(feature = "prusti")]
@@ -53,6 +59,9 @@ mod private {
     /// A macro for marking a function as trusted.
     pub use prusti_contracts_internal::trusted;
 
+    /// A macro for setting the verification timeout of a function, in seconds.
+    pub use prusti_contracts_internal::timeout;
+
     /// A macro for writing a loop body invariant.
     pub use prusti_contracts_internal::body_invariant;
 
@@ -64,6 +73,9 @@ mod private {
 
     /// A macro for specifying external functions.
     pub use prusti_contracts_internal::extern_spec;
+
+    /// A macro for writing an invariant on a trait.
+    pub use prusti_contracts_internal::invariant;
 }
 
 
@@ -316,24 +316,31 @@ impl ProcedureLoops {
         // They are those blocks in the loop that:
         // 1. have a SwitchInt terminator
         // 2. have an out-edge that exists from the loop
+        // 3. are not in a nested loop (e.g. a labelled `break` out of an inner loop)
+        //
+        // We also identify the nonconditional blocks of each loop, which are those that are
+        // executed in every iteration that reaches a back edge, i.e. those that dominate all
+        // the sources of the back edges of the loop. A `continue` is a back edge as well.
         let mut loop_exit_blocks = HashMap::new();
         let mut nonconditional_loop_blocks = HashMap::new();
         for &loop_head in loop_heads.iter() {
             let loop_head_depth = loop_head_depths[&loop_head];
-            let loop_body = &loop_bodies[&loop_head];
             let ordered_loop_body = &ordered_loop_bodies[&loop_head];
+            let back_edge_sources: Vec<_> = back_edges.iter()
+                .filter(|&&(_, target)| target == loop_head)
+                .map(|&(source, _)| source)
+                .collect();
 
             let mut exit_blocks = vec![];
-            let mut nonconditional_blocks = vec![];
-            let mut reached_back_edge = false;
-            let mut border = HashSet::new();
-            border.insert(loop_head);
+            let mut nonconditional_blocks = HashSet::new();
 
             for &curr_bb in ordered_loop_body {
-                debug_assert!(border.contains(&curr_bb));
-
-                if !reached_back_edge {
-                    nonconditional_blocks.push(curr_bb);
+                // Decide if this block is executed in every iteration
+                let is_nonconditional = back_edge_sources.iter().all(
+                    |&source| dominators.is_dominated_by(source, curr_bb)
+                );
+                if is_nonconditional {
+                    nonconditional_blocks.insert(curr_bb);
                 }
 
                 // Decide if this block has an exit edge
@@ -345,29 +352,14 @@ impl ProcedureLoops {
                 let has_exit_edge = term.successors().any(
                     |&bb| get_loop_depth(bb) < loop_head_depth
                 );
-                if is_switch_int && has_exit_edge {
+                let is_in_nested_loop = get_loop_depth(curr_bb) > loop_head_depth;
+                if is_switch_int && has_exit_edge && !is_in_nested_loop {
                     exit_blocks.push(curr_bb);
                 }
-
-                border.remove(&curr_bb);
-
-                for &succ_bb in mir[curr_bb].terminator().successors() {
-                    if back_edges.contains(&(curr_bb, succ_bb)) {
-                        if succ_bb == loop_head || !loop_body.contains(&succ_bb) {
-                            // From this point, we consider all blocks to be conditional
-                            reached_back_edge = true;
-                        }
-                    } else {
-                        // Consider only forward in-loop edges
-                        if loop_body.contains(&succ_bb) {
-                            border.insert(succ_bb);
-                        }
-                    }
-                }
             }
 
             loop_exit_blocks.insert(loop_head, exit_blocks);
-            nonconditional_loop_blocks.insert(loop_head, nonconditional_blocks.into_iter().collect());
+            nonconditional_loop_blocks.insert(loop_head, nonconditional_blocks);
         }
         debug!("loop_exit_blocks: {:?}", loop_exit_blocks);
         debug!("nonconditional_loop_blocks: {:?}", nonconditional_loop_blocks);
@@ -459,6 +451,23 @@ impl ProcedureLoops {
         }
     }
 
+    /// Get the blocks from which a back edge leads to the given loop head, i.e. the ends of the
+    /// loop body and the `continue` statements of the loop.
+    pub fn get_back_edge_sources(&self, loop_head: BasicBlockIndex) -> Vec<BasicBlockIndex> {
+        debug_assert!(self.is_loop_head(loop_head));
+        self.back_edges
+            .iter()
+            .filter(|&&(_, target)| target == loop_head)
+            .map(|&(source, _)| source)
+            .collect()
+    }
+
+    /// Check if every path from the start of the procedure to ``block`` goes through
+    /// ``dominator``.
+    pub fn is_dominated_by(&self, block: BasicBlockIndex, dominator: BasicBlockIndex) -> bool {
+        self.dominators.is_dominated_by(block, dominator)
+    }
+
     /// Check if ``block`` is inside a given loop.
     pub fn is_block_in_loop(&self, loop_head: BasicBlockIndex, block: BasicBlockIndex) -> bool {
         self.dominators.is_dominated_by(block, loop_head)
@@ -0,0 +1,47 @@
+// © 2021, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use rustc_hir::def_id::DefId;
+use rustc_middle::mir;
+use rustc_middle::ty::{self, TyCtxt};
+use std::cell::Ref;
+use std::ops::Deref;
+
+/// The MIR body of a procedure, which is either defined in the current crate or
+/// loaded from the metadata of another crate.
+pub enum MirBody<'a, 'tcx> {
+    /// The MIR of a local procedure, before optimizations.
+    Local(Ref<'a, mir::Body<'tcx>>),
+    /// The optimized MIR of an external procedure. It's available because
+    /// dependencies are compiled with `-Zalways-encode-mir`, and it keeps the
+    /// shape of the unoptimized MIR when they are compiled with
+    /// `-Zmir-opt-level=0` (see `config::cross_crate_specs`).
+    External(&'a mir::Body<'tcx>),
+}
+
+impl<'tcx> MirBody<'tcx, 'tcx> {
+    pub fn new(tcx: TyCtxt<'tcx>, def_id: DefId) -> Self {
+        if let Some(local_def_id) = def_id.as_local() {
+            let (mir, _) = tcx.mir_promoted(ty::WithOptConstParam::unknown(local_def_id));
+            MirBody::Local(mir.borrow())
+        } else {
+            MirBody::External(
+                tcx.optimized_mir_or_const_arg_mir(ty::WithOptConstParam::unknown(def_id))
+            )
+        }
+    }
+}
+
+impl<'a, 'tcx> Deref for MirBody<'a, 'tcx> {
+    type Target = mir::Body<'tcx>;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            MirBody::Local(mir) => &**mir,
+            MirBody::External(mir) => *mir,
+        }
+    }
+}
@@ -17,6 +17,7 @@ use std::cell::Ref;
 use rustc_span::{Span, MultiSpan, symbol::Symbol};
 use std::collections::HashSet;
 use log::debug;
+use regex::Regex;
 
 pub mod borrowck;
 mod collect_prusti_spec_visitor;
@@ -24,6 +25,7 @@ mod collect_closure_defs_visitor;
 mod dump_borrowck_info;
 mod loops;
 mod loops_utils;
+mod mir_body;
 pub mod mir_analyses;
 pub mod mir_utils;
 pub mod place_set;
@@ -36,6 +38,7 @@ use rustc_hir::intravisit::Visitor;
 pub use self::loops::{PlaceAccess, PlaceAccessKind, ProcedureLoops};
 pub use self::loops_utils::*;
 pub use self::procedure::{BasicBlockIndex, Procedure};
+pub use self::mir_body::MirBody;
 // use config;
 use crate::data::ProcedureDefId;
 // use syntax::codemap::CodeMap;
@@ -115,15 +118,19 @@ impl<'tcx> Environment<'tcx> {
         sp: S,
         msg: &str,
         help: &Option<String>,
-        note: &Option<(String, S)>
+        notes: &[(String, Option<S>)]
     ) {
         let mut diagnostic = self.tcx.sess.struct_err(msg);
         diagnostic.set_span(sp);
         if let Some(help_msg) = help {
             diagnostic.help(help_msg);
         }
-        if let Some((note_msg, note_sp)) = note {
-            diagnostic.span_note(note_sp.clone(), note_msg);
+        for (note_msg, opt_note_sp) in notes {
+            if let Some(note_sp) = opt_note_sp {
+                diagnostic.span_note(note_sp.clone(), note_msg);
+            } else {
+                diagnostic.note(note_msg);
+            }
         }
         diagnostic.emit();
     }
@@ -134,15 +141,19 @@ impl<'tcx> Environment<'tcx> {
         sp: S,
         msg: &str,
         help: &Option<String>,
-        note: &Option<(String, S)>
+        notes: &[(String, Option<S>)]
     ) {
         let mut diagnostic = self.tcx.sess.struct_warn(msg);
         diagnostic.set_span(sp);
         if let Some(help_msg) = help {
             diagnostic.help(help_msg);
         }
-        if let Some((note_msg, note_sp)) = note {
-            diagnostic.span_note(note_sp.clone(), note_msg);
+        for (note_msg, opt_note_sp) in notes {
+            if let Some(note_sp) = opt_note_sp {
+                diagnostic.span_note(note_sp.clone(), note_msg);
+            } else {
+                diagnostic.note(note_msg);
+            }
         }
         diagnostic.emit();
     }
@@ -166,12 +177,61 @@ impl<'tcx> Environment<'tcx> {
         result
     }
 
+    /// Select the procedures that should be verified. Procedures with a `#[prusti::skip]`
+    /// attribute are never verified. If some procedures have a `#[prusti::verify]` attribute or
+    /// match a pattern of the `VERIFY_ONLY` configuration flag, only those are verified.
+    /// Closures are selected together with the procedure that defines them.
+    ///
+    /// The attributes can be written as e.g. `#[cfg_attr(prusti, prusti::skip)]`, so that the
+    /// crate still compiles without Prusti.
+    pub fn select_procedures_to_verify(
+        &self,
+        procedures: Vec<ProcedureDefId>,
+    ) -> Vec<ProcedureDefId> {
+        let patterns: Vec<Regex> = prusti_common::config::verify_only()
+            .iter()
+            .map(|pattern| {
+                // A glob, in which `*` matches any sequence of characters, that matches the
+                // whole path or a suffix of it that starts after a `::`.
+                let pattern = regex::escape(pattern).replace("\\*", ".*");
+                Regex::new(&format!("(^|::){}$", pattern)).unwrap()
+            })
+            .collect();
+        let is_selected = |def_id: ProcedureDefId| {
+            let base_def_id = self.tcx.closure_base_def_id(def_id);
+            let item_names = [
+                self.get_item_def_path(base_def_id),
+                self.get_absolute_item_name(base_def_id),
+            ];
+            self.has_prusti_attribute(base_def_id, "verify") || patterns.iter().any(|pattern| {
+                item_names.iter().any(|item_name| pattern.is_match(item_name))
+            })
+        };
+        let procedures: Vec<_> = procedures
+            .into_iter()
+            .filter(|&def_id| {
+                !self.has_prusti_attribute(self.tcx.closure_base_def_id(def_id), "skip")
+            })
+            .collect();
+        if procedures.iter().any(|&def_id| is_selected(def_id)) || !patterns.is_empty() {
+            procedures.into_iter().filter(|&def_id| is_selected(def_id)).collect()
+        } else {
+            procedures
+        }
+    }
+
     /// Find whether the procedure has a particular `prusti::<name>` attribute.
     pub fn has_prusti_attribute(&self, def_id: ProcedureDefId, name: &str) -> bool {
         let tcx = self.tcx();
         crate::utils::has_prusti_attr(tcx.get_attrs(def_id), name)
     }
 
+    /// Get the value of a `prusti::<name> = "<value>"` attribute of the procedure, if any.
+    pub fn get_prusti_attribute(&self, def_id: ProcedureDefId, name: &str) -> Option<String> {
+        let tcx = self.tcx();
+        crate::utils::read_prusti_attr(name, tcx.get_attrs(def_id))
+    }
+
     /// Dump various information from the borrow checker.
     ///
     /// Mostly used for experiments and debugging.
@@ -190,9 +250,8 @@ impl<'tcx> Environment<'tcx> {
     }
 
     /// Get the span of a definition
-    /// Note: panics on non-local `def_id`
     pub fn get_item_span(&self, def_id: DefId) -> Span {
-        self.tcx.hir().span_if_local(def_id).unwrap()
+        self.tcx.hir().span_if_local(def_id).unwrap_or_else(|| self.tcx.def_span(def_id))
     }
 
     pub fn get_absolute_item_name(&self, def_id: DefId) -> String {
@@ -216,6 +275,11 @@ impl<'tcx> Environment<'tcx> {
         ).0.borrow()
     }
 
+    /// Get the MIR body of a local or external procedure.
+    pub fn mir_body<'a>(&self, def_id: DefId) -> MirBody<'a, 'tcx> {
+        MirBody::new(self.tcx(), def_id)
+    }
+
     /// Get the MIR body of an external procedure.
     pub fn external_mir<'a>(&self, def_id: DefId) -> &'a mir::Body<'tcx> {
         self.tcx().optimized_mir_or_const_arg_mir(WithOptConstParam::unknown(def_id))
@@ -255,6 +319,29 @@ impl<'tcx> Environment<'tcx> {
         result
     }
 
+    /// Resolve the call of the trait method `called_def_id` with substitutions `call_substs`
+    /// from within `caller_def_id` to the method of the implementation that gets called,
+    /// together with the substitutions of the implementation method.
+    /// Returns `None` if the implementation is not statically known, for example because
+    /// the receiver is a type parameter of the caller.
+    pub fn resolve_trait_method_call(
+        &self,
+        caller_def_id: ProcedureDefId,
+        called_def_id: ProcedureDefId,
+        call_substs: ty::subst::SubstsRef<'tcx>,
+    ) -> Option<(ProcedureDefId, ty::subst::SubstsRef<'tcx>)> {
+        self.tcx().trait_of_item(called_def_id)?;
+        let param_env = self.tcx().param_env(caller_def_id);
+        let instance = ty::Instance::resolve(self.tcx(), param_env, called_def_id, call_substs)
+            .ok()??;
+        match instance.def {
+            ty::InstanceDef::Item(def) if def.did != called_def_id => {
+                Some((def.did, instance.substs))
+            }
+            _ => None,
+        }
+    }
+
     pub fn type_is_copy(&self, ty: ty::Ty<'tcx>) -> bool {
         let copy_trait = self.tcx.lang_items().copy_trait();
         if let Some(copy_trait_def_id) = copy_trait {
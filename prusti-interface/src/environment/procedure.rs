@@ -7,11 +7,11 @@
 #![feature(box_syntax, box_patterns)]
 
 use super::loops;
+use super::mir_body::MirBody;
 use crate::data::ProcedureDefId;
 use rustc_middle::mir::{self, Body as Mir, Rvalue, AggregateKind};
 use rustc_middle::mir::{BasicBlock, BasicBlockData, Terminator, TerminatorKind};
 use rustc_middle::ty::{self, Ty, TyCtxt};
-use std::cell::Ref;
 use std::collections::{HashSet, HashMap};
 use rustc_span::Span;
 use log::{trace, debug};
@@ -26,7 +26,7 @@ pub type BasicBlockIndex = mir::BasicBlock;
 pub struct Procedure<'a, 'tcx: 'a> {
     tcx: TyCtxt<'tcx>,
     proc_def_id: ProcedureDefId,
-    mir: Ref<'a, Mir<'tcx>>,
+    mir: MirBody<'a, 'tcx>,
     loop_info: loops::ProcedureLoops,
     reachable_basic_blocks: HashSet<BasicBlock>,
     nonspec_basic_blocks: HashSet<BasicBlock>,
@@ -37,8 +37,7 @@ impl<'a, 'tcx> Procedure<'a, 'tcx> {
     /// identifier of a procedure
     pub fn new(tcx: TyCtxt<'tcx>, proc_def_id: ProcedureDefId) -> Self {
         trace!("Encoding procedure {:?}", proc_def_id);
-        let (mir, _) = tcx.mir_promoted(ty::WithOptConstParam::unknown(proc_def_id.expect_local()));
-        let mir = mir.borrow();
+        let mir = MirBody::new(tcx, proc_def_id);
         let reachable_basic_blocks = build_reachable_basic_blocks(&mir);
         let nonspec_basic_blocks = build_nonspec_basic_blocks(&mir, &tcx);
 
@@ -143,6 +142,23 @@ impl<'a, 'tcx> Procedure<'a, 'tcx> {
         !self.nonspec_basic_blocks.contains(&bbi)
     }
 
+    /// Check whether the block is used for typechecking a loop invariant
+    pub fn is_loop_invariant_block(&self, bbi: BasicBlockIndex) -> bool {
+        self.mir.basic_blocks()[bbi].statements.iter().any(|stmt| {
+            if let StatementKind::Assign(box (
+                _,
+                Rvalue::Aggregate(box AggregateKind::Closure(def_id, _), _),
+            )) = &stmt.kind {
+                crate::utils::has_prusti_attr(
+                    self.tcx.get_attrs(*def_id),
+                    "loop_body_invariant_spec",
+                )
+            } else {
+                false
+            }
+        })
+    }
+
     /// Check whether the block is reachable
     pub fn is_reachable_block(&self, bbi: BasicBlockIndex) -> bool {
         self.reachable_basic_blocks.contains(&bbi)
@@ -24,7 +24,7 @@ pub struct PrustiError {
     message: String,
     span: MultiSpan,
     help: Option<String>,
-    note: Option<(String, MultiSpan)>,
+    notes: Vec<(String, Option<MultiSpan>)>,
 }
 
 impl PrustiError {
@@ -35,7 +35,7 @@ impl PrustiError {
             message,
             span,
             help: None,
-            note: None,
+            notes: vec![],
         }
     }
 
@@ -88,11 +88,25 @@ impl PrustiError {
         self.is_error
     }
 
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn span(&self) -> &MultiSpan {
+        &self.span
+    }
+
     pub fn set_help<S: ToString>(mut self, message: S) -> Self {
         self.help = Some(message.to_string());
         self
     }
 
+    /// Add a note, optionally pointing to a span, to the message.
+    pub fn add_note<S: ToString>(mut self, message: S, opt_span: Option<MultiSpan>) -> Self {
+        self.notes.push((message.to_string(), opt_span));
+        self
+    }
+
     /// Report the encoding error using the compiler's interface
     pub fn emit(self, env: &Environment) {
         if self.is_error {
@@ -100,14 +114,14 @@ impl PrustiError {
                 self.span,
                 &self.message,
                 &self.help,
-                &self.note,
+                &self.notes,
             );
         } else {
             env.span_warn_with_help_and_note(
                 self.span,
                 &self.message,
                 &self.help,
-                &self.note,
+                &self.notes,
             );
         }
     }
@@ -117,7 +131,7 @@ impl PrustiError {
     /// Note: this is a noop if `opt_span` is None
     pub fn set_failing_assertion(mut self, opt_span: Option<&MultiSpan>) -> Self {
         if let Some(span) = opt_span {
-            self.note = Some(("the failing assertion is here".to_string(), span.clone()));
+            self.notes.push(("the failing assertion is here".to_string(), Some(span.clone())));
         }
         self
     }
@@ -127,7 +141,7 @@ impl PrustiError {
     /// Note: this is a noop if `opt_span` is None
     pub fn push_primary_span(mut self, opt_span: Option<&MultiSpan>) -> Self {
         if let Some(span) = opt_span {
-            self.note = Some(("the error originates here".to_string(), self.span));
+            self.notes.push(("the error originates here".to_string(), Some(self.span)));
             self.span = span.clone();
         }
         self
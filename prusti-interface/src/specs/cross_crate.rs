@@ -0,0 +1,363 @@
+//! Export and import of specifications across crates.
+//!
+//! The specifications of a crate are written to a `.prusti-specs` file next to
+//! the crate metadata, so that they can be loaded when verifying the crates
+//! that depend on it. Items of the exporting crate are identified by their
+//! `DefIndex`, which is preserved in the crate metadata. The assertions refer
+//! to closures of the exporting crate, whose MIR (as well as the MIR of pure
+//! functions) is available in the metadata because Prusti compiles crates with
+//! `-Zalways-encode-mir`. Crates that export specifications are compiled with
+//! `-Zmir-opt-level=0`, so that their MIR keeps the closures that the
+//! specification encoder looks for; this is checked when importing.
+
+use super::{SpecCollector, reconstruct_typed_assertion, typed};
+use super::typed::SpecIdRef;
+use crate::environment::{Environment, MirBody};
+use crate::PrustiError;
+use crate::utils::read_prusti_attr;
+use log::debug;
+use prusti_specs::specifications::common::{ExpressionId, SpecificationId};
+use prusti_specs::specifications::json::Assertion as JsonAssertion;
+use prusti_specs::specifications::SpecType;
+use rustc_hir::def_id::{CrateNum, DefId, DefIndex, LOCAL_CRATE};
+use rustc_middle::mir;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::MultiSpan;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+/// The specifications exported by a crate.
+#[derive(Serialize, Deserialize)]
+struct CrateSpecs {
+    /// Specifications of procedures.
+    procedures: Vec<ProcedureSpecs>,
+    /// Invariants of traits, keyed by the `DefIndex` of the trait.
+    trait_invariants: Vec<(u32, Vec<String>)>,
+    /// Items that encode the assertions, keyed by their specification id.
+    spec_items: HashMap<String, SpecItemRef>,
+    /// Closures that encode the expressions of the assertions, keyed by
+    /// `<spec_id>_<expr_id>`.
+    expressions: HashMap<String, u32>,
+}
+
+/// The specification of a procedure, referring to its assertions by their
+/// specification id.
+#[derive(Serialize, Deserialize)]
+struct ProcedureSpecs {
+    def_index: u32,
+    pres: Vec<String>,
+    posts: Vec<String>,
+    pledges: Vec<(Option<String>, String)>,
+    pure: bool,
+    trusted: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SpecItemRef {
+    def_index: u32,
+    /// The serialised assertion.
+    assertion: String,
+}
+
+/// The path of the file that contains the specifications of the `krate` crate,
+/// or `None` if the crate was not compiled to a file.
+fn specs_path(tcx: TyCtxt, krate: CrateNum) -> Option<PathBuf> {
+    let directory = if krate == LOCAL_CRATE {
+        tcx.output_filenames(LOCAL_CRATE).out_directory.clone()
+    } else {
+        let source = tcx.used_crate_source(krate);
+        let (path, _) = source.rmeta.as_ref()
+            .or(source.rlib.as_ref())
+            .or(source.dylib.as_ref())?;
+        path.parent()?.to_path_buf()
+    };
+    Some(directory.join(format!(
+        "{}-{}.prusti-specs",
+        tcx.crate_name(krate),
+        tcx.crate_disambiguator(krate),
+    )))
+}
+
+impl<'tcx> SpecCollector<'tcx> {
+    /// Write the specifications of the local crate next to its metadata.
+    pub(super) fn export_specs(&self, env: &Environment<'tcx>) {
+        let specs = self.collect_exported_specs();
+        let path = match specs_path(self.tcx, LOCAL_CRATE) {
+            Some(path) => path,
+            None => return,
+        };
+        debug!("Exporting specifications to {:?}", path);
+        let result = File::create(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|file| {
+                serde_json::to_writer(BufWriter::new(file), &specs)
+                    .map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            PrustiError::internal(
+                format!("failed to export the specifications to {:?}: {}", path, err),
+                MultiSpan::new(),
+            ).emit(env);
+        }
+    }
+
+    fn collect_exported_specs(&self) -> CrateSpecs {
+        let spec_ids_to_strings = |spec_ids: &[SpecificationId]| {
+            spec_ids.iter().map(|spec_id| spec_id.to_string()).collect()
+        };
+        let procedures = self.procedure_specs.iter()
+            .map(|(local_id, refs)| {
+                let mut pres = vec![];
+                let mut posts = vec![];
+                let mut pledges = vec![];
+                for spec_id_ref in &refs.spec_id_refs {
+                    match spec_id_ref {
+                        SpecIdRef::Precondition(spec_id) => pres.push(spec_id.to_string()),
+                        SpecIdRef::Postcondition(spec_id) => posts.push(spec_id.to_string()),
+                        SpecIdRef::Pledge { lhs, rhs } => pledges.push((
+                            lhs.map(|spec_id| spec_id.to_string()),
+                            rhs.to_string(),
+                        )),
+                    }
+                }
+                ProcedureSpecs {
+                    def_index: local_id.local_def_index.as_u32(),
+                    pres,
+                    posts,
+                    pledges,
+                    pure: refs.pure,
+                    trusted: refs.trusted,
+                }
+            })
+            .collect();
+        let trait_invariants = self.trait_invariants.iter()
+            .map(|(local_id, spec_ids)| {
+                (local_id.local_def_index.as_u32(), spec_ids_to_strings(spec_ids))
+            })
+            .collect();
+        let spec_items = self.spec_item_ids.iter()
+            .map(|local_id| {
+                let attrs = self.tcx.get_attrs(local_id.to_def_id());
+                let spec_id: SpecificationId = read_prusti_attr("spec_id", attrs)
+                    .expect("could not find prusti::spec_id")
+                    .try_into()
+                    .expect("failed conversion to SpecificationId");
+                let assertion = read_prusti_attr("assertion", attrs)
+                    .expect("could not find prusti::assertion");
+                (spec_id.to_string(), SpecItemRef {
+                    def_index: local_id.local_def_index.as_u32(),
+                    assertion,
+                })
+            })
+            .collect();
+        let expressions = self.typed_expressions.iter()
+            .map(|(expr_id, def_id)| (expr_id.clone(), def_id.index.as_u32()))
+            .collect();
+        CrateSpecs {
+            procedures,
+            trait_invariants,
+            spec_items,
+            expressions,
+        }
+    }
+
+    /// Load the specifications exported by the dependencies of the local crate.
+    pub(super) fn import_specs(
+        &self,
+        def_spec: &mut typed::DefSpecificationMap<'tcx>,
+        env: &Environment<'tcx>,
+    ) {
+        for &krate in self.tcx.crates() {
+            let path = match specs_path(self.tcx, krate) {
+                Some(path) if path.exists() => path,
+                _ => continue,
+            };
+            debug!("Importing specifications of {} from {:?}", self.tcx.crate_name(krate), path);
+            let result: Result<CrateSpecs, String> = File::open(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|file| {
+                    serde_json::from_reader(BufReader::new(file))
+                        .map_err(|err| err.to_string())
+                });
+            let result = result
+                .and_then(|specs| self.import_crate_specs(krate, specs, def_spec));
+            if let Err(err) = result {
+                PrustiError::internal(
+                    format!(
+                        "failed to import the specifications of {} from {:?}: {}",
+                        self.tcx.crate_name(krate),
+                        path,
+                        err
+                    ),
+                    MultiSpan::new(),
+                ).emit(env);
+            }
+        }
+    }
+
+    /// Import the specifications of the `krate` crate. Nothing is imported if
+    /// the specifications do not match the crate, for example because the file
+    /// is stale or malformed.
+    fn import_crate_specs(
+        &self,
+        krate: CrateNum,
+        specs: CrateSpecs,
+        def_spec: &mut typed::DefSpecificationMap<'tcx>,
+    ) -> Result<(), String> {
+        let to_def_id = |def_index: u32| DefId {
+            krate,
+            index: DefIndex::from_u32(def_index),
+        };
+        let typed_expressions: HashMap<String, DefId> = specs.expressions.into_iter()
+            .map(|(expr_id, def_index)| (expr_id, to_def_id(def_index)))
+            .collect();
+        let mut assertions = vec![];
+        let mut spec_item_ids = vec![];
+        let mut expr_def_ids = vec![];
+        for (spec_id, spec_item) in specs.spec_items {
+            let assertion: JsonAssertion = serde_json::from_str(&spec_item.assertion)
+                .map_err(|err| format!("malformed assertion {}: {}", spec_id, err))?;
+            let mut expr_ids = vec![];
+            collect_expression_ids(&assertion, &mut expr_ids);
+            for expr_id in expr_ids {
+                let def_id = typed_expressions.get(&expr_id).ok_or_else(|| {
+                    format!("unknown expression {} in assertion {}", expr_id, spec_id)
+                })?;
+                expr_def_ids.push(*def_id);
+            }
+            assertions.push((spec_id, assertion));
+            spec_item_ids.push(to_def_id(spec_item.def_index));
+        }
+        check_spec_closures(self.tcx, &spec_item_ids, &expr_def_ids)?;
+        let typed_specs: HashMap<String, typed::Assertion<'tcx>> = assertions.into_iter()
+            .map(|(spec_id, assertion)| {
+                let typed_assertion =
+                    reconstruct_typed_assertion(assertion, &typed_expressions, self.tcx);
+                (spec_id, typed_assertion)
+            })
+            .collect();
+        let get_assertion = |spec_id: &String| {
+            typed_specs.get(spec_id)
+                .cloned()
+                .ok_or_else(|| format!("unknown specification {}", spec_id))
+        };
+
+        let mut imported_specs = vec![];
+        for procedure in specs.procedures {
+            let pledges = procedure.pledges.iter()
+                .map(|(lhs, rhs)| Ok(typed::Pledge {
+                    reference: None,    // FIXME: Currently only `result` is supported.
+                    lhs: lhs.as_ref().map(get_assertion).transpose()?,
+                    rhs: get_assertion(rhs)?,
+                }))
+                .collect::<Result<_, String>>()?;
+            imported_specs.push((
+                to_def_id(procedure.def_index),
+                typed::SpecificationSet::Procedure(typed::ProcedureSpecification {
+                    pres: procedure.pres.iter().map(get_assertion).collect::<Result<_, _>>()?,
+                    posts: procedure.posts.iter().map(get_assertion).collect::<Result<_, _>>()?,
+                    pledges,
+                    pure: procedure.pure,
+                    trusted: procedure.trusted,
+                }),
+            ));
+        }
+        for (def_index, spec_ids) in specs.trait_invariants {
+            let invariants = spec_ids.iter()
+                .map(|spec_id| Ok(typed::Specification {
+                    typ: SpecType::Invariant,
+                    assertion: get_assertion(spec_id)?,
+                }))
+                .collect::<Result<_, String>>()?;
+            imported_specs.push((
+                to_def_id(def_index),
+                typed::SpecificationSet::Struct(invariants),
+            ));
+        }
+        def_spec.imported_specs.extend(imported_specs);
+        def_spec.imported_spec_items.extend(spec_item_ids);
+        Ok(())
+    }
+}
+
+/// Collect the keys (`<spec_id>_<expr_id>`) of the closures that encode the
+/// expressions of the assertion.
+fn collect_expression_ids(assertion: &JsonAssertion, expr_ids: &mut Vec<String>) {
+    use prusti_specs::specifications::json::AssertionKind::*;
+    let key = |spec_id: &SpecificationId, expr_id: ExpressionId| format!("{}_{}", spec_id, expr_id);
+    match &*assertion.kind {
+        Expr(expr) => expr_ids.push(key(&expr.spec_id, expr.expr_id)),
+        And(assertions) => {
+            for assertion in assertions {
+                collect_expression_ids(assertion, expr_ids);
+            }
+        }
+        Implies(lhs, rhs) => {
+            collect_expression_ids(lhs, expr_ids);
+            collect_expression_ids(rhs, expr_ids);
+        }
+        ForAll(vars, body, triggers) => {
+            expr_ids.push(key(&vars.spec_id, vars.expr_id));
+            collect_expression_ids(body, expr_ids);
+            for trigger in &triggers.0 {
+                for expr in &trigger.0 {
+                    expr_ids.push(key(&expr.spec_id, expr.expr_id));
+                }
+            }
+        }
+        SpecEntailment { closure, arg_binders, pres, posts } => {
+            expr_ids.push(key(&closure.spec_id, closure.expr_id));
+            expr_ids.push(key(&arg_binders.spec_id, arg_binders.pre_expr_id));
+            expr_ids.push(key(&arg_binders.spec_id, arg_binders.post_expr_id));
+            for assertion in pres.iter().chain(posts) {
+                collect_expression_ids(assertion, expr_ids);
+            }
+        }
+    }
+}
+
+/// Check that the closures that encode the expressions of the specifications
+/// are instantiated in the MIR of the items that encode the assertions (or in
+/// the MIR of other such closures), which is where the specification encoder
+/// looks for them. This fails if the exporting crate was compiled with MIR
+/// optimizations that removed the closures.
+fn check_spec_closures(
+    tcx: TyCtxt,
+    spec_item_ids: &[DefId],
+    expression_ids: &[DefId],
+) -> Result<(), String> {
+    let mut instantiated = HashSet::new();
+    let mut to_visit = spec_item_ids.to_vec();
+    while let Some(def_id) = to_visit.pop() {
+        if !tcx.is_mir_available(def_id) {
+            return Err(format!("the MIR of {} is not available", tcx.def_path_str(def_id)));
+        }
+        let mir = MirBody::new(tcx, def_id);
+        for bb_data in mir.basic_blocks() {
+            for stmt in &bb_data.statements {
+                if let mir::StatementKind::Assign(box (
+                    _,
+                    mir::Rvalue::Aggregate(box mir::AggregateKind::Closure(cl_def_id, _), _),
+                )) = stmt.kind {
+                    if instantiated.insert(cl_def_id) {
+                        to_visit.push(cl_def_id);
+                    }
+                }
+            }
+        }
+    }
+    for def_id in expression_ids {
+        if !instantiated.contains(def_id) {
+            return Err(format!(
+                "the specification closure {} is not in the MIR of the crate, which must be \
+                compiled with `CROSS_CRATE_SPECS` enabled",
+                tcx.def_path_str(*def_id)
+            ));
+        }
+    }
+    Ok(())
+}
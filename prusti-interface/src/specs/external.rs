@@ -128,8 +128,17 @@ impl<'tcx> Visitor<'tcx> for ExternSpecVisitor<'tcx> {
         }
         if let rustc_hir::ExprKind::Call(ref callee_expr, ref arguments) = ex.kind {
             if let rustc_hir::ExprKind::Path(ref qself) = callee_expr.kind {
-                let res = self.tcx.typeck(callee_expr.hir_id.owner).qpath_res(qself, callee_expr.hir_id);
+                let owner = callee_expr.hir_id.owner;
+                let typeck_results = self.tcx.typeck(owner);
+                let res = typeck_results.qpath_res(qself, callee_expr.hir_id);
                 if let rustc_hir::def::Res::Def(_, def_id) = res {
+                    // For a call of a trait method, specify the method of the
+                    // implementation if it is statically known.
+                    let substs = typeck_results.node_substs(callee_expr.hir_id);
+                    let def_id = Environment::new(self.tcx)
+                        .resolve_trait_method_call(owner.to_def_id(), def_id, substs)
+                        .map(|(impl_def_id, _)| impl_def_id)
+                        .unwrap_or(def_id);
                     self.spec_found = Some((def_id, get_impl_type(qself), ex.span));
                     return;
                 }
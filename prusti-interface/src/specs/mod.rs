@@ -14,7 +14,9 @@ use crate::utils::{
     has_spec_only_attr, has_extern_spec_attr, read_prusti_attr, read_prusti_attrs, has_prusti_attr
 };
 use log::debug;
+use prusti_common::config;
 
+mod cross_crate;
 pub mod external;
 pub mod typed;
 
@@ -55,7 +57,10 @@ pub struct SpecCollector<'tcx> {
     /// Collected assertions before deserialisation.
     spec_items: Vec<SpecItem>,
 
-    typed_expressions: HashMap<String, LocalDefId>,
+    typed_expressions: HashMap<String, DefId>,
+
+    /// Items that encode the collected assertions.
+    spec_item_ids: Vec<LocalDefId>,
 
     /// Collected, deserialised assertions, keyed by their specification id.
     typed_specs: typed::SpecificationMap<'tcx>,
@@ -63,6 +68,7 @@ pub struct SpecCollector<'tcx> {
     /// Resolved specifications.
     procedure_specs: HashMap<LocalDefId, ProcedureSpecRef>,
     loop_specs: HashMap<LocalDefId, Vec<SpecificationId>>,
+    trait_invariants: HashMap<LocalDefId, Vec<SpecificationId>>,
 }
 
 impl<'tcx> SpecCollector<'tcx> {
@@ -73,7 +79,9 @@ impl<'tcx> SpecCollector<'tcx> {
             typed_specs: HashMap::new(),
             procedure_specs: HashMap::new(),
             loop_specs: HashMap::new(),
+            trait_invariants: HashMap::new(),
             typed_expressions: HashMap::new(),
+            spec_item_ids: Vec::new(),
             extern_resolver: ExternSpecResolver::new(tcx),
         }
     }
@@ -101,6 +109,11 @@ impl<'tcx> SpecCollector<'tcx> {
         self.determine_extern_specs(&mut def_spec, env);
         self.determine_loop_specs(&mut def_spec);
         self.determine_struct_specs(&mut def_spec);
+        self.determine_trait_specs(&mut def_spec);
+        if config::cross_crate_specs() {
+            self.export_specs(env);
+            self.import_specs(&mut def_spec, env);
+        }
         def_spec
     }
 
@@ -171,6 +184,18 @@ impl<'tcx> SpecCollector<'tcx> {
 
     // TODO: struct specs
     fn determine_struct_specs(&self, def_spec: &mut typed::DefSpecificationMap<'tcx>) {}
+
+    fn determine_trait_specs(&self, def_spec: &mut typed::DefSpecificationMap<'tcx>) {
+        for (local_id, spec_ids) in self.trait_invariants.iter() {
+            let specs = spec_ids.iter()
+                .map(|spec_id| typed::Specification {
+                    typ: SpecType::Invariant,
+                    assertion: self.typed_specs.get(&spec_id).unwrap().clone(),
+                })
+                .collect();
+            def_spec.specs.insert(*local_id, typed::SpecificationSet::Struct(specs));
+        }
+    }
 }
 
 fn get_procedure_spec_ids(def_id: DefId, attrs: &[ast::Attribute]) -> Option<ProcedureSpecRef> {
@@ -226,7 +251,7 @@ fn get_procedure_spec_ids(def_id: DefId, attrs: &[ast::Attribute]) -> Option<Pro
 
 fn reconstruct_typed_assertion<'tcx>(
     assertion: JsonAssertion,
-    typed_expressions: &HashMap<String, LocalDefId>,
+    typed_expressions: &HashMap<String, DefId>,
     tcx: TyCtxt<'tcx>
 ) -> typed::Assertion<'tcx> {
     assertion.to_typed(typed_expressions, tcx)
@@ -246,6 +271,27 @@ impl<'tcx> intravisit::Visitor<'tcx> for SpecCollector<'tcx> {
         intravisit::NestedVisitorMap::All(map)
     }
 
+    fn visit_item(
+        &mut self,
+        item: &'tcx rustc_hir::Item,
+    ) {
+        intravisit::walk_item(self, item);
+
+        // Collect trait invariants
+        if let ItemKind::Trait(..) = item.kind {
+            let local_id = self.tcx.hir().local_def_id(item.hir_id);
+            let spec_ids: Vec<SpecificationId> = read_prusti_attrs("inv_spec_id_ref", item.attrs)
+                .into_iter()
+                .map(|raw_spec_id| raw_spec_id.try_into().expect(
+                    &format!("cannot parse the spec_id attached to {:?}", local_id)
+                ))
+                .collect();
+            if !spec_ids.is_empty() {
+                self.trait_invariants.insert(local_id, spec_ids);
+            }
+        }
+    }
+
     fn visit_trait_item(
         &mut self,
         ti: &'tcx rustc_hir::TraitItem,
@@ -289,7 +335,7 @@ impl<'tcx> intravisit::Visitor<'tcx> for SpecCollector<'tcx> {
 
         // Collect a typed expression
         if let Some(expr_id) = read_prusti_attr("expr_id", attrs) {
-            self.typed_expressions.insert(expr_id, local_id);
+            self.typed_expressions.insert(expr_id, def_id);
         }
 
         // Collect a specification id and its assertion
@@ -304,7 +350,8 @@ impl<'tcx> intravisit::Visitor<'tcx> for SpecCollector<'tcx> {
             // to its precondition with a #[pre_spec_id_ref=<id>] attribute,
             // where <id> is the unique identifier of the specification. Same
             // for postconditions and invariants.
-            let spec_type = if has_prusti_attr(attrs, "loop_body_invariant_spec") {
+            let is_loop_invariant = has_prusti_attr(attrs, "loop_body_invariant_spec");
+            let spec_type = if is_loop_invariant {
                 SpecType::Invariant
             } else {
                 let fn_name = match fn_kind {
@@ -321,6 +368,8 @@ impl<'tcx> intravisit::Visitor<'tcx> for SpecCollector<'tcx> {
                 } else if fn_name.starts_with("prusti_post_item_")
                     || fn_name.starts_with("prusti_post_closure_") {
                     SpecType::Postcondition
+                } else if fn_name.starts_with("prusti_inv_item_") {
+                    SpecType::Invariant
                 } else {
                     unreachable!()
                 }
@@ -330,11 +379,13 @@ impl<'tcx> intravisit::Visitor<'tcx> for SpecCollector<'tcx> {
             self.spec_items.push(spec_item);
 
             // Collect loop invariant
-            if spec_type == SpecType::Invariant {
+            if is_loop_invariant {
                 self.loop_specs
                     .entry(local_id)
                     .or_insert(vec![])
                     .push(spec_id);
+            } else {
+                self.spec_item_ids.push(local_id);
             }
         }
     }
@@ -4,7 +4,14 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use std::process::Command;
+use std::{
+    env,
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
 use prusti_launch::get_rust_toolchain_channel;
 
 fn main(){
@@ -26,19 +33,64 @@ fn process<I>(args: I) -> Result<(), i32>
 
     // Remove the leading "prusti" argument when `cargo-prusti` is invocated
     // as `cargo prusti` (note the space)
-    let clean_args = args.skip_while(|x| x == "prusti");
+    let mut clean_args = args.skip_while(|x| x == "prusti");
+
+    // The arguments before `--` are passed to cargo, the ones after it are for Prusti.
+    let cargo_args: Vec<String> = clean_args.by_ref().take_while(|x| x != "--").collect();
+    let mut verify_only = vec![];
+    let mut spawn_server = true;
+    while let Some(arg) = clean_args.next() {
+        if arg == "--no-server" {
+            spawn_server = false;
+        } else if arg == "--only" {
+            match clean_args.next() {
+                Some(pattern) => verify_only.push(pattern),
+                None => {
+                    eprintln!("error: missing pattern after `--only`");
+                    return Err(1);
+                }
+            }
+        } else if let Some(pattern) = arg.strip_prefix("--only=") {
+            verify_only.push(pattern.to_string());
+        } else {
+            eprintln!("error: unknown Prusti argument `{}`", arg);
+            return Err(1);
+        }
+    }
 
     let cargo_path = std::env::var("CARGO_PATH").unwrap_or("cargo".to_string());
 
-    let exit_status = Command::new(cargo_path)
+    let mut command = Command::new(cargo_path);
+    command
         .arg("check")
-        .args(clean_args)
+        .args(cargo_args)
         .env("RUST_TOOLCHAIN", get_rust_toolchain_channel())
         .env("PRUSTI_QUIET", "true")
         .env("PRUSTI_FULL_COMPILATION", "true")
-        .env("RUSTC_WRAPPER", prusti_rustc_path)
-        .status()
-        .expect("could not run cargo");
+        .env("PRUSTI_CROSS_CRATE_SPECS", "true")
+        .env("RUSTC_WRAPPER", prusti_rustc_path);
+    if !verify_only.is_empty() {
+        command.env("PRUSTI_VERIFY_ONLY", verify_only.join(","));
+    }
+    // Re-running cargo-prusti only verifies the procedures that changed since the last run.
+    if env::var_os("PRUSTI_ENABLE_CACHE").is_none() {
+        command.env("PRUSTI_ENABLE_CACHE", "true");
+    }
+
+    // Verify all crates on the same server, so that the JVM is started only once. A server
+    // given by `PRUSTI_SERVER_ADDRESS` is used as is.
+    let mut _server = None;
+    if spawn_server && env::var_os("PRUSTI_SERVER_ADDRESS").is_none() {
+        match LocalServer::spawn() {
+            Ok(server) => {
+                command.env("PRUSTI_SERVER_ADDRESS", &server.address);
+                _server = Some(server);
+            }
+            Err(error) => eprintln!("warning: {}, verifying each crate separately", error),
+        }
+    }
+
+    let exit_status = command.status().expect("could not run cargo");
 
     if exit_status.success() {
         Ok(())
@@ -46,3 +98,96 @@ fn process<I>(args: I) -> Result<(), i32>
         Err(exit_status.code().unwrap_or(-1))
     }
 }
+
+/// How long to wait for the server to start. Its worker processes, each with a JVM, are only
+/// started when the crates send requests.
+const SERVER_STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The number of worker processes of the server if `PRUSTI_NUM_PARALLEL_VERIFIERS` is not set.
+/// Each of them runs a JVM, so they are not as many as the cores of large machines.
+const DEFAULT_SERVER_WORKERS: usize = 4;
+
+/// A `prusti-server` that runs while the crates are verified. It exits when it is dropped, or
+/// when `cargo-prusti` exits.
+struct LocalServer {
+    process: Child,
+    address: String,
+}
+
+impl LocalServer {
+    fn spawn() -> Result<Self, String> {
+        let mut prusti_server_path = env::current_exe()
+            .expect("current executable path invalid")
+            .with_file_name("prusti-server");
+        if cfg!(windows) {
+            prusti_server_path.set_extension("exe");
+        }
+
+        let mut command = Command::new(prusti_server_path);
+        command
+            .arg("--port")
+            .arg("0")
+            .arg("--exit-on-stdin-eof")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped());
+        if env::var_os("RUST_LOG").is_none() {
+            command.env("RUST_LOG", "warn");
+        }
+        // Verify the requests of the crates concurrently on a bounded number of workers, each
+        // with its own JVM, which is started once for the whole build.
+        if env::var_os("PRUSTI_SERVER_WORKER_PROCESSES").is_none() {
+            command.env("PRUSTI_SERVER_WORKER_PROCESSES", "true");
+        }
+        if env::var_os("PRUSTI_SERVER_MAX_CONCURRENCY").is_none() {
+            let max_concurrency = env::var_os("PRUSTI_NUM_PARALLEL_VERIFIERS")
+                .unwrap_or_else(|| DEFAULT_SERVER_WORKERS.to_string().into());
+            command.env("PRUSTI_SERVER_MAX_CONCURRENCY", max_concurrency);
+        }
+        let mut process = command
+            .spawn()
+            .map_err(|error| format!("could not run prusti-server: {}", error))?;
+
+        let stdout = process.stdout.take().unwrap();
+        let (port_sender, port_receiver) = mpsc::channel();
+        thread::spawn(move || {
+            // the server reports its port on stdout once it is listening
+            let mut lines = BufReader::new(stdout).lines();
+            let port = lines
+                .by_ref()
+                .filter_map(Result::ok)
+                .find_map(|line| line.strip_prefix("port: ").map(str::to_string));
+            let _ = port_sender.send(port);
+            // keep reading the output, so that the server does not block on a full pipe
+            lines.for_each(drop);
+        });
+        let port = match port_receiver.recv_timeout(SERVER_STARTUP_TIMEOUT) {
+            Ok(Some(port)) => port,
+            Ok(None) | Err(mpsc::RecvTimeoutError::Disconnected) => {
+                let _ = process.kill();
+                let _ = process.wait();
+                return Err("prusti-server did not start".to_string());
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                let _ = process.kill();
+                let _ = process.wait();
+                return Err(format!(
+                    "prusti-server did not start within {} seconds",
+                    SERVER_STARTUP_TIMEOUT.as_secs()
+                ));
+            }
+        };
+
+        Ok(LocalServer {
+            process,
+            address: format!("127.0.0.1:{}", port),
+        })
+    }
+}
+
+impl Drop for LocalServer {
+    fn drop(&mut self) {
+        // closing stdin stops the server
+        drop(self.process.stdin.take());
+        let _ = self.process.wait();
+    }
+}
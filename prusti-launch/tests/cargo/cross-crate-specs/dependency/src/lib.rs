@@ -0,0 +1,7 @@
+use prusti_contracts::*;
+
+#[requires(x < 100)]
+#[ensures(result == x + 1)]
+pub fn increment(x: u32) -> u32 {
+    x + 1
+}
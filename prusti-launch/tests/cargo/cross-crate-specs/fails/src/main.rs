@@ -0,0 +1,7 @@
+use dependency::increment;
+
+fn main() {
+    // The precondition of `increment` does not hold.
+    let x = increment(100);
+    assert!(x == 101);
+}
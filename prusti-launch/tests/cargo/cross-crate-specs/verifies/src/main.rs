@@ -0,0 +1,6 @@
+use dependency::increment;
+
+fn main() {
+    let x = increment(41);
+    assert!(x == 42);
+}
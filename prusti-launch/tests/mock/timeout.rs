@@ -0,0 +1,18 @@
+use prusti_contracts::*;
+
+#[requires(x < 100)]
+#[ensures(result == x + 1)]
+fn slow(x: u32) -> u32 {
+    x + 1
+}
+
+#[requires(x < 100)]
+#[ensures(result == x + 1)]
+fn fast(x: u32) -> u32 {
+    x + 1
+}
+
+fn main() {
+    slow(1);
+    fast(2);
+}
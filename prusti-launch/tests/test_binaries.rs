@@ -5,7 +5,7 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use glob::glob;
-use std::process::{Command, ExitStatus, Stdio, Child};
+use std::process::{self, Command, ExitStatus, Stdio, Child};
 use std::path::PathBuf;
 use std::io::{BufReader, BufRead};
 use std::env;
@@ -166,3 +166,55 @@ fn test_prusti_rustc_with_server() {
             .expect("failed to execute prusti-rustc")
     });
 }
+
+#[test]
+fn test_prusti_rustc_with_mock_backend() {
+    let prusti_rustc = find_executable_path("prusti-rustc");
+    let test_dir = env::current_dir().unwrap().join("tests").join("mock");
+
+    // The mock backend answers with the results of the file instead of starting a JVM, so only
+    // the encoding and the translation of the results are tested.
+    let output = Command::new(&prusti_rustc)
+        .arg("--edition=2018")
+        .arg(test_dir.join("timeout.rs"))
+        .env("RUST_BACKTRACE", "1")
+        .env("PRUSTI_VERIFICATION_BACKEND", "mock")
+        .env("PRUSTI_MOCK_BACKEND_RESULTS", test_dir.join("results.json"))
+        .env("PRUSTI_DISABLE_NAME_MANGLING", "true")
+        .env_remove("PRUSTI_SERVER_ADDRESS")
+        .output()
+        .expect("failed to execute prusti-rustc");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "The mock timeout was not reported.");
+    assert!(
+        stderr.contains("slow timed out after 10 seconds"),
+        "Unexpected errors: {}",
+        stderr
+    );
+    assert!(!stderr.contains("fast"), "Unexpected errors: {}", stderr);
+}
+
+#[test]
+fn test_cargo_prusti_cross_crate_specs() {
+    let cargo_prusti = find_executable_path("cargo-prusti");
+    let target_dir = env::temp_dir().join(format!("prusti-cross-crate-specs-{}", process::id()));
+
+    // The specifications of `dependency` are used to verify the crates that depend on it.
+    let run = |package: &str| -> ExitStatus {
+        let manifest_path: PathBuf = ["tests", "cargo", "cross-crate-specs", package, "Cargo.toml"]
+            .iter()
+            .collect();
+        println!("Running {:?} on {:?}...", cargo_prusti.display(), manifest_path.display());
+        Command::new(&cargo_prusti)
+            .arg("--manifest-path")
+            .arg(&manifest_path)
+            .env("RUST_BACKTRACE", "1")
+            .env("CARGO_TARGET_DIR", &target_dir)
+            .status()
+            .expect("failed to execute cargo-prusti")
+    };
+    assert!(run("verifies").success(), "The specifications were not imported.");
+    assert!(!run("fails").success(), "The precondition of the dependency was not checked.");
+
+    let _ = std::fs::remove_dir_all(&target_dir);
+}
@@ -0,0 +1,207 @@
+// © 2021, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{
+    BackendVersion, Cancellation, PrustiServer, RemoteVerificationResult, VerificationOutcome,
+    VerificationProgress, WorkerPool,
+};
+use prusti_common::{config, verification_service::VerificationRequest};
+use serde_json;
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use viper::VerificationResult;
+
+/// Something that verifies the requests of Prusti, e.g. the Viper verifiers of a JVM.
+pub trait Backend: Send + Sync {
+    /// Verify the request, reporting when its verification starts and finishes. The backend
+    /// should stop the verification when it is cancelled.
+    fn verify(
+        &self,
+        request: VerificationRequest,
+        on_progress: &dyn Fn(VerificationProgress),
+        cancellation: &Cancellation,
+    ) -> RemoteVerificationResult;
+
+    fn version(&self) -> io::Result<BackendVersion>;
+
+    /// Whether the backend can verify requests.
+    fn is_healthy(&self) -> bool {
+        true
+    }
+
+    /// The number of verifiers that the backend keeps, for the metrics of the server.
+    fn num_verifiers(&self) -> usize {
+        0
+    }
+}
+
+impl Backend for PrustiServer {
+    fn verify(
+        &self,
+        request: VerificationRequest,
+        on_progress: &dyn Fn(VerificationProgress),
+        cancellation: &Cancellation,
+    ) -> RemoteVerificationResult {
+        self.run_cancellable_verifier(request, on_progress, cancellation)
+    }
+
+    fn version(&self) -> io::Result<BackendVersion> {
+        Ok(self.backend_version())
+    }
+
+    fn num_verifiers(&self) -> usize {
+        self.num_verifier_threads()
+    }
+}
+
+impl Backend for WorkerPool {
+    fn verify(
+        &self,
+        request: VerificationRequest,
+        on_progress: &dyn Fn(VerificationProgress),
+        cancellation: &Cancellation,
+    ) -> RemoteVerificationResult {
+        self.run_cancellable_verifier(request, on_progress, cancellation)
+    }
+
+    fn version(&self) -> io::Result<BackendVersion> {
+        Ok(self.backend_version())
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.can_start_workers()
+    }
+
+    fn num_verifiers(&self) -> usize {
+        self.num_workers()
+    }
+}
+
+/// A backend that answers each request with a result read from a file, without verifying it.
+/// Requests whose program is not in the file verify successfully.
+pub struct MockBackend {
+    path: Option<PathBuf>,
+    /// The result of each program, by program name.
+    results: HashMap<String, RemoteVerificationResult>,
+}
+
+impl MockBackend {
+    pub fn new(results: HashMap<String, RemoteVerificationResult>) -> Self {
+        MockBackend {
+            path: None,
+            results,
+        }
+    }
+
+    /// Read the results from a JSON object that maps program names to results.
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let file = fs::File::open(path)?;
+        let results = serde_json::from_reader(io::BufReader::new(file))
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        Ok(MockBackend {
+            path: Some(path.to_path_buf()),
+            results,
+        })
+    }
+}
+
+impl Backend for MockBackend {
+    fn verify(
+        &self,
+        request: VerificationRequest,
+        on_progress: &dyn Fn(VerificationProgress),
+        _cancellation: &Cancellation,
+    ) -> RemoteVerificationResult {
+        on_progress(VerificationProgress::Started {
+            program_name: request.program_name.clone(),
+        });
+        let result = self
+            .results
+            .get(&request.program_name)
+            .cloned()
+            .unwrap_or(Ok(VerificationResult::Success()));
+        on_progress(VerificationProgress::Finished {
+            program_name: request.program_name,
+            outcome: VerificationOutcome::of(&result),
+            duration: Duration::default(),
+        });
+        result
+    }
+
+    fn version(&self) -> io::Result<BackendVersion> {
+        Ok(BackendVersion {
+            name: "mock".to_string(),
+            build_version: match &self.path {
+                Some(path) => format!("results of {}", path.display()),
+                None => "no results".to_string(),
+            },
+        })
+    }
+}
+
+/// Creates a backend that keeps up to the given number of verifiers.
+pub type BackendFactory = dyn Fn(usize) -> io::Result<Arc<dyn Backend>> + Send + Sync;
+
+lazy_static! {
+    static ref BACKENDS: RwLock<HashMap<String, Arc<BackendFactory>>> = {
+        let mut backends: HashMap<String, Arc<BackendFactory>> = HashMap::new();
+        backends.insert(
+            "viper".to_string(),
+            Arc::new(|num_verifiers| {
+                Ok(Arc::new(PrustiServer::new(num_verifiers)) as Arc<dyn Backend>)
+            }),
+        );
+        backends.insert(
+            "mock".to_string(),
+            Arc::new(|_| {
+                let backend = match config::mock_backend_results() {
+                    Some(path) => MockBackend::from_file(Path::new(&path))?,
+                    None => MockBackend::new(HashMap::new()),
+                };
+                Ok(Arc::new(backend) as Arc<dyn Backend>)
+            }),
+        );
+        RwLock::new(backends)
+    };
+}
+
+/// Register a backend that can be selected with the `VERIFICATION_BACKEND` flag, replacing the
+/// backend with the same name, if any.
+pub fn register_backend<F>(name: &str, factory: F)
+where
+    F: Fn(usize) -> io::Result<Arc<dyn Backend>> + Send + Sync + 'static,
+{
+    BACKENDS
+        .write()
+        .unwrap()
+        .insert(name.to_lowercase(), Arc::new(factory));
+}
+
+/// Create the registered backend with the given name, which keeps up to `num_verifiers`
+/// verifiers.
+pub fn create_backend(name: &str, num_verifiers: usize) -> io::Result<Arc<dyn Backend>> {
+    let factory = BACKENDS.read().unwrap().get(&name.to_lowercase()).cloned();
+    match factory {
+        Some(factory) => factory(num_verifiers),
+        None => {
+            let mut names: Vec<_> = BACKENDS.read().unwrap().keys().cloned().collect();
+            names.sort();
+            Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "unknown verification backend '{}', the registered backends are {}",
+                    name,
+                    names.join(", ")
+                ),
+            ))
+        }
+    }
+}
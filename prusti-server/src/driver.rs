@@ -7,10 +7,17 @@
 extern crate clap;
 extern crate env_logger;
 extern crate log;
+extern crate prusti_common;
 extern crate prusti_server;
+extern crate viper;
 
-use clap::{App, Arg};
-use prusti_server::ServerSideService;
+use clap::{App, AppSettings, Arg, ArgGroup, SubCommand};
+use prusti_common::{config, verification_service::ViperBackendConfig};
+use prusti_server::{
+    describe_result, diff_results, PrustiServer, RecordedRequest, ServerSideService,
+};
+use std::{env, io, path::Path, process, thread};
+use viper::VerificationBackend;
 
 fn main() {
     env_logger::init_from_env(
@@ -18,23 +25,166 @@ fn main() {
     );
 
     let matches = App::new("Prusti Server")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::with_name("port")
                 .short("p")
                 .long("port")
                 .help("Sets the port on which to listen for incoming verification requests. Pass 0 to get a free one assigned by the OS.")
-                .required(true)
                 .takes_value(true)
                 .value_name("PORT"),
         )
+        .arg(
+            Arg::with_name("unix-socket")
+                .long("unix-socket")
+                .help("Listens for incoming verification requests on a Unix domain socket at the given path instead of a port.")
+                .takes_value(true)
+                .value_name("PATH"),
+        )
+        .arg(
+            Arg::with_name("socket-permissions")
+                .long("socket-permissions")
+                .help("Sets the permissions of the Unix domain socket, in octal. By default, only the current user can use it.")
+                .requires("unix-socket")
+                .takes_value(true)
+                .value_name("MODE")
+                .default_value("600"),
+        )
+        .arg(
+            Arg::with_name("stdio")
+                .long("stdio")
+                .help("Reads verification requests from stdin, one JSON request per line, and writes the responses to stdout. The server exits when stdin is closed."),
+        )
+        .group(
+            ArgGroup::with_name("transport")
+                .args(&["port", "unix-socket", "stdio"])
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("exit-on-stdin-eof")
+                .long("exit-on-stdin-eof")
+                .conflicts_with("stdio")
+                .help("Exits when stdin is closed, e.g. when the process that started the server exits."),
+        )
+        .subcommand(
+            SubCommand::with_name("replay")
+                .about("Verifies a request recorded with the RECORD_REQUESTS flag, and prints how the result differs from the recorded one. Exits with 1 if it differs.")
+                .arg(
+                    Arg::with_name("file")
+                        .help("The file of the recorded request.")
+                        .required(true)
+                        .value_name("FILE"),
+                )
+                .arg(
+                    Arg::with_name("backend")
+                        .long("backend")
+                        .help("Verifies the request with this backend instead of the recorded one, with the default verifier arguments of the backend.")
+                        .takes_value(true)
+                        .possible_values(&["silicon", "carbon"])
+                        .value_name("BACKEND"),
+                )
+                .arg(
+                    Arg::with_name("verifier-arg")
+                        .long("verifier-arg")
+                        .help("Verifies the request with these verifier arguments instead of the recorded ones.")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .allow_hyphen_values(true)
+                        .value_name("ARG"),
+                ),
+        )
         .get_matches();
 
-    let port = matches
-        .value_of("port")
-        .unwrap()
-        .parse()
-        .expect("Invalid port provided");
+    if let Some(matches) = matches.subcommand_matches("replay") {
+        let exit_code = replay(
+            Path::new(matches.value_of("file").unwrap()),
+            matches.value_of("backend"),
+            matches
+                .values_of("verifier-arg")
+                .map(|args| args.map(str::to_string).collect()),
+        );
+        process::exit(exit_code);
+    }
+
+    if matches.is_present("exit-on-stdin-eof") {
+        // the process that started the server holds our stdin until it exits
+        thread::spawn(|| {
+            let _ = io::copy(&mut io::stdin(), &mut io::sink());
+            process::exit(0);
+        });
+    }
+
+    let service = if config::server_worker_processes() {
+        let executable = env::current_exe().expect("could not find the server executable");
+        ServerSideService::with_worker_processes(executable)
+    } else {
+        ServerSideService::new().unwrap_or_else(|error| {
+            eprintln!("Could not start the server: {}", error);
+            process::exit(1);
+        })
+    };
+
+    if matches.is_present("stdio") {
+        service.serve_stdio();
+    } else if let Some(path) = matches.value_of("unix-socket") {
+        let permissions = matches.value_of("socket-permissions").unwrap();
+        listen_on_unix_socket(service, Path::new(path), permissions);
+    } else {
+        let port = matches
+            .value_of("port")
+            .unwrap()
+            .parse()
+            .expect("Invalid port provided");
+        service.listen_on_port(port);
+    }
+}
+
+/// Verify a recorded request again and print the differences between the results. Returns the
+/// exit code of the server: 0 if the results are equal, 1 otherwise.
+fn replay(path: &Path, backend: Option<&str>, verifier_args: Option<Vec<String>>) -> i32 {
+    let recording = RecordedRequest::read(path).unwrap_or_else(|error| {
+        panic!("Could not read the recorded request {:?}: {}", path, error)
+    });
+    let mut request = recording.request;
+    if let Some(backend) = backend {
+        // the backend has been validated by the argument parser
+        let backend: VerificationBackend = backend.parse().unwrap();
+        request.backend_config = ViperBackendConfig::new(backend);
+    }
+    if let Some(verifier_args) = verifier_args {
+        request.backend_config.verifier_args = verifier_args;
+    }
+
+    println!(
+        "Replaying {} with {} {:?}",
+        request.program_name, request.backend_config.backend, request.backend_config.verifier_args
+    );
+    let result = PrustiServer::new(1).run_verifier(request);
+    println!("recorded: {}", describe_result(&recording.result));
+    println!("replayed: {}", describe_result(&result));
+    let diff = diff_results(&recording.result, &result);
+    if diff.is_empty() {
+        println!("The results are equal.");
+        0
+    } else {
+        for line in diff {
+            println!("{}", line);
+        }
+        1
+    }
+}
+
+#[cfg(unix)]
+fn listen_on_unix_socket(service: ServerSideService, path: &Path, permissions: &str) {
+    let permissions =
+        u32::from_str_radix(permissions, 8).expect("Invalid socket permissions provided");
+    service
+        .listen_on_unix_socket(path, permissions)
+        .unwrap_or_else(|error| panic!("Server could not listen on {:?}: {}", path, error));
+}
 
-    let service = ServerSideService::new();
-    service.listen_on_port(port);
+#[cfg(not(unix))]
+fn listen_on_unix_socket(_service: ServerSideService, _path: &Path, _permissions: &str) {
+    panic!("Unix domain sockets are not supported on this platform");
 }
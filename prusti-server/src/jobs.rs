@@ -0,0 +1,227 @@
+// © 2021, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::VerificationResponse;
+use prusti_common::verification_service::VerificationRequest;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+};
+
+/// The number of finished or cancelled jobs whose status is kept by the server.
+const MAX_FINISHED_JOBS: usize = 1000;
+
+pub type JobId = u64;
+
+type CancellationHandler = Box<dyn FnOnce() + Send>;
+
+#[derive(Default)]
+struct CancellationState {
+    cancelled: bool,
+    handler: Option<CancellationHandler>,
+}
+
+/// A signal that the verification of a request should be stopped. The verifier that runs the
+/// request registers a handler, which stops its work when the request is cancelled.
+#[derive(Clone, Default)]
+pub struct Cancellation {
+    state: Arc<Mutex<CancellationState>>,
+}
+
+impl Cancellation {
+    pub fn cancel(&self) {
+        let handler = {
+            let mut state = self.state.lock().unwrap();
+            state.cancelled = true;
+            state.handler.take()
+        };
+        if let Some(handler) = handler {
+            handler();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.lock().unwrap().cancelled
+    }
+
+    /// Set the handler that is called when the request is cancelled, replacing the previous
+    /// one. If the request has already been cancelled, the handler is called immediately.
+    pub fn set_handler(&self, handler: CancellationHandler) {
+        let mut state = self.state.lock().unwrap();
+        if state.cancelled {
+            drop(state);
+            handler();
+        } else {
+            state.handler = Some(handler);
+        }
+    }
+
+    pub fn clear_handler(&self) {
+        self.state.lock().unwrap().handler = None;
+    }
+}
+
+/// The status of a job of the server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum JobStatus {
+    /// The job is waiting for a verifier.
+    Queued,
+    Running,
+    Finished(VerificationResponse),
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobInfo {
+    pub id: JobId,
+    pub program_name: String,
+    pub status: JobStatus,
+}
+
+struct Job {
+    program_name: String,
+    status: JobStatus,
+    /// The request of a queued job.
+    request: Option<VerificationRequest>,
+    cancellation: Cancellation,
+}
+
+impl Job {
+    fn info(&self, id: JobId) -> JobInfo {
+        JobInfo {
+            id,
+            program_name: self.program_name.clone(),
+            status: self.status.clone(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct JobsState {
+    next_id: JobId,
+    jobs: BTreeMap<JobId, Job>,
+    /// The queued jobs, in the order of their submission.
+    queue: VecDeque<JobId>,
+    /// The finished and cancelled jobs, in the order in which they stopped.
+    stopped: VecDeque<JobId>,
+}
+
+impl JobsState {
+    fn stop(&mut self, id: JobId, status: JobStatus) {
+        self.jobs.get_mut(&id).unwrap().status = status;
+        self.stopped.push_back(id);
+        if self.stopped.len() > MAX_FINISHED_JOBS {
+            let oldest = self.stopped.pop_front().unwrap();
+            self.jobs.remove(&oldest);
+        }
+    }
+}
+
+/// The verification requests submitted to the server as jobs, which are verified in the
+/// background in the order of their submission.
+#[derive(Default)]
+pub struct JobQueue {
+    state: Mutex<JobsState>,
+    job_queued: Condvar,
+}
+
+impl JobQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start `num_runners` threads that take the queued jobs and verify them with `verify`.
+    pub fn run_jobs<F>(self: &Arc<Self>, num_runners: usize, verify: F)
+    where
+        F: Fn(VerificationRequest, &Cancellation) -> VerificationResponse + Send + Sync + 'static,
+    {
+        let verify = Arc::new(verify);
+        for _ in 0..num_runners {
+            let queue = self.clone();
+            let verify = verify.clone();
+            thread::spawn(move || loop {
+                let (id, request, cancellation) = queue.next_job();
+                let response = verify(request, &cancellation);
+                queue.finish(id, response);
+            });
+        }
+    }
+
+    pub fn submit(&self, request: VerificationRequest) -> JobInfo {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        info!("Queued verification request {} as job {}", request.program_name, id);
+        let job = Job {
+            program_name: request.program_name.clone(),
+            status: JobStatus::Queued,
+            request: Some(request),
+            cancellation: Cancellation::default(),
+        };
+        let info = job.info(id);
+        state.jobs.insert(id, job);
+        state.queue.push_back(id);
+        self.job_queued.notify_one();
+        info
+    }
+
+    pub fn num_queued(&self) -> usize {
+        self.state.lock().unwrap().queue.len()
+    }
+
+    pub fn status(&self, id: JobId) -> Option<JobInfo> {
+        let state = self.state.lock().unwrap();
+        state.jobs.get(&id).map(|job| job.info(id))
+    }
+
+    /// The jobs that are known to the server, in the order of their submission.
+    pub fn list(&self) -> Vec<JobInfo> {
+        let state = self.state.lock().unwrap();
+        state.jobs.iter().map(|(&id, job)| job.info(id)).collect()
+    }
+
+    /// Cancel a job, stopping its verification if it is running. Returns `None` if the job
+    /// does not exist.
+    pub fn cancel(&self, id: JobId) -> Option<JobInfo> {
+        let mut state = self.state.lock().unwrap();
+        let job = state.jobs.get_mut(&id)?;
+        match job.status {
+            JobStatus::Queued => {
+                job.request = None;
+                state.queue.retain(|&queued_id| queued_id != id);
+            }
+            JobStatus::Running => job.cancellation.cancel(),
+            JobStatus::Finished(_) | JobStatus::Cancelled => {
+                return Some(job.info(id));
+            }
+        }
+        info!("Cancelled job {}", id);
+        state.stop(id, JobStatus::Cancelled);
+        Some(state.jobs[&id].info(id))
+    }
+
+    fn next_job(&self) -> (JobId, VerificationRequest, Cancellation) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(id) = state.queue.pop_front() {
+                let job = state.jobs.get_mut(&id).unwrap();
+                job.status = JobStatus::Running;
+                let request = job.request.take().unwrap();
+                return (id, request, job.cancellation.clone());
+            }
+            state = self.job_queued.wait(state).unwrap();
+        }
+    }
+
+    fn finish(&self, id: JobId, response: VerificationResponse) {
+        let mut state = self.state.lock().unwrap();
+        // the job has already been stopped if it was cancelled
+        if matches!(state.jobs.get(&id), Some(Job { status: JobStatus::Running, .. })) {
+            state.stop(id, JobStatus::Finished(response));
+        }
+    }
+}
@@ -11,35 +11,136 @@ extern crate warp;
 extern crate log;
 extern crate bincode;
 extern crate futures;
+extern crate hyper;
 extern crate num_cpus;
 extern crate prusti_common;
+extern crate sha2;
 extern crate tokio;
 #[macro_use]
 extern crate serde;
+extern crate serde_json;
+#[macro_use]
+extern crate lazy_static;
 
+mod backend;
+mod jobs;
+mod metrics;
+mod parallel;
+mod portfolio;
+mod recording;
 mod service;
+mod verification_cache;
 mod verifier_runner;
 mod verifier_thread;
+mod worker_pool;
 
+pub use backend::*;
 use futures::Future;
-use prusti_common::{verification_context::VerifierBuilder, verification_service::*, Stopwatch};
+pub use jobs::*;
+pub use metrics::*;
+use prusti_common::{
+    config, verification_context::VerifierBuilder, verification_service::*, Stopwatch,
+};
+pub use parallel::*;
+pub use portfolio::*;
+pub use recording::*;
 pub use service::*;
+pub use verification_cache::*;
 use std::{
     collections::VecDeque,
-    sync::{Arc, RwLock},
+    sync::{mpsc, Arc, Condvar, Mutex, RwLock},
+    thread,
+    time::{Duration, Instant},
 };
 pub use verifier_runner::*;
 use verifier_thread::*;
+pub use worker_pool::*;
 use viper::VerificationResult;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct VerifierPanicked;
-pub type RemoteVerificationResult = Result<VerificationResult, VerifierPanicked>;
+/// The version of the protocol between the server and its clients, which changes whenever the
+/// requests or responses of the server change in an incompatible way.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// The verification backend used by a server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackendVersion {
+    pub name: String,
+    /// The version reported by the backend itself.
+    pub build_version: String,
+}
+
+/// The versions reported by the `/version` endpoint of a server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub server_version: String,
+    pub protocol_version: u32,
+    /// The `request_schema_fingerprint` of the server.
+    pub request_schema: u64,
+    pub backend: BackendVersion,
+}
+
+/// The reason why a verification request did not produce a result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VerifierError {
+    /// The verifier panicked.
+    Panicked,
+    /// The verification did not finish within the timeout of the request, in seconds.
+    TimedOut(u64),
+    /// The request was cancelled before its verification finished.
+    Cancelled,
+}
+pub type RemoteVerificationResult = Result<VerificationResult, VerifierError>;
+
+/// The outcome of a finished verification request, as reported in its progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VerificationOutcome {
+    Verified,
+    /// The verification reported the given number of errors.
+    Failed(usize),
+    TimedOut,
+    /// The verifier panicked or raised an exception.
+    Panicked,
+    Cancelled,
+}
+
+impl VerificationOutcome {
+    pub fn of(result: &RemoteVerificationResult) -> Self {
+        match result {
+            Ok(VerificationResult::Success()) => VerificationOutcome::Verified,
+            Ok(VerificationResult::Failure(errors)) => VerificationOutcome::Failed(errors.len()),
+            Ok(VerificationResult::ConsistencyErrors(errors)) => {
+                VerificationOutcome::Failed(errors.len())
+            }
+            Ok(VerificationResult::JavaException(_)) => VerificationOutcome::Panicked,
+            Ok(VerificationResult::TimedOut) => VerificationOutcome::TimedOut,
+            Err(VerifierError::TimedOut(_)) => VerificationOutcome::TimedOut,
+            Err(VerifierError::Panicked) => VerificationOutcome::Panicked,
+            Err(VerifierError::Cancelled) => VerificationOutcome::Cancelled,
+        }
+    }
+}
+
+/// An event in the verification of a request, reported while the verification is running.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum VerificationProgress {
+    /// The request has been given to a verifier.
+    Started { program_name: String },
+    /// The verification of the request has finished after the given time.
+    Finished {
+        program_name: String,
+        outcome: VerificationOutcome,
+        duration: Duration,
+    },
+}
 
 pub struct PrustiServer {
     verifier_builder: Arc<VerifierBuilder>,
     threads: RwLock<VecDeque<VerifierThread>>,
     cache_size: usize,
+    /// The version of the default backend, determined when it is first requested.
+    backend_version: Mutex<Option<BackendVersion>>,
+    /// The number of verifications running on the JVM, including the abandoned ones.
+    running_verifications: Arc<(Mutex<usize>, Condvar)>,
 }
 
 impl PrustiServer {
@@ -52,31 +153,181 @@ impl PrustiServer {
             verifier_builder,
             threads: RwLock::new(VecDeque::with_capacity(cache_size)),
             cache_size,
+            backend_version: Mutex::new(None),
+            running_verifications: Arc::new((Mutex::new(0), Condvar::new())),
         }
     }
 
+    /// The version of the backend that verifies requests with the default configuration.
+    pub fn backend_version(&self) -> BackendVersion {
+        let mut backend_version = self.backend_version.lock().unwrap();
+        backend_version
+            .get_or_insert_with(|| {
+                VerifierRunner::with_default_configured_runner(&self.verifier_builder, |runner| {
+                    runner.backend_version()
+                })
+            })
+            .clone()
+    }
+
+    /// The number of idle verifier threads that are kept for later requests.
+    pub fn num_verifier_threads(&self) -> usize {
+        self.threads.read().unwrap().len()
+    }
+
     pub fn run_verifier(&self, request: VerificationRequest) -> RemoteVerificationResult {
+        self.run_verifier_with_progress(request, &|_| {})
+    }
+
+    /// Like `run_verifier`, but reports when the verification of the request starts and
+    /// finishes.
+    pub fn run_verifier_with_progress(
+        &self,
+        request: VerificationRequest,
+        on_progress: &dyn Fn(VerificationProgress),
+    ) -> RemoteVerificationResult {
+        self.run_cancellable_verifier(request, on_progress, &Cancellation::default())
+    }
+
+    /// Like `run_verifier_with_progress`, but stops waiting for the verification when it is
+    /// cancelled. The verifier thread cannot be interrupted, so it is abandoned instead, and the
+    /// next requests wait until it has finished, because Viper does not support concurrent
+    /// verifiers in the same JVM.
+    ///
+    /// The request is recorded if `RECORD_REQUESTS` is set. This is the only place where requests
+    /// are recorded, because all of them are eventually verified here, on the client or on the
+    /// server (or its worker processes).
+    pub fn run_cancellable_verifier(
+        &self,
+        request: VerificationRequest,
+        on_progress: &dyn Fn(VerificationProgress),
+        cancellation: &Cancellation,
+    ) -> RemoteVerificationResult {
+        let program_name = request.program_name.clone();
+        on_progress(VerificationProgress::Started {
+            program_name: program_name.clone(),
+        });
+        let start_time = Instant::now();
+        let recorded_request = config::record_requests().map(|_| request.clone());
+        let result = self.run_verifier_thread(request, cancellation);
+        if let Some(request) = recorded_request {
+            record_request(request, &result);
+        }
+        on_progress(VerificationProgress::Finished {
+            program_name,
+            outcome: VerificationOutcome::of(&result),
+            duration: start_time.elapsed(),
+        });
+        result
+    }
+
+    /// Wait until no other verification is running on the JVM, i.e. until the abandoned ones
+    /// have finished, and count the verification of the request as running. Returns `None` if
+    /// the request is cancelled while waiting.
+    fn start_running_verification(
+        &self,
+        program_name: &str,
+        cancellation: &Cancellation,
+    ) -> Option<RunningVerification> {
+        let (running_verifications, finished) = &*self.running_verifications;
+        let mut running_verifications = running_verifications.lock().unwrap();
+        if *running_verifications > 0 {
+            warn!(
+                "Verification request {} waits for an abandoned verification to finish",
+                program_name
+            );
+        }
+        while *running_verifications > 0 {
+            if cancellation.is_cancelled() {
+                return None;
+            }
+            running_verifications = finished
+                .wait_timeout(running_verifications, CANCELLATION_POLL_INTERVAL)
+                .unwrap()
+                .0;
+        }
+        *running_verifications += 1;
+        Some(RunningVerification {
+            running_verifications: self.running_verifications.clone(),
+        })
+    }
+
+    fn run_verifier_thread(
+        &self,
+        request: VerificationRequest,
+        cancellation: &Cancellation,
+    ) -> RemoteVerificationResult {
+        let program_name = request.program_name.clone();
+        let running_verification =
+            match self.start_running_verification(&program_name, cancellation) {
+                Some(running_verification) => running_verification,
+                None => {
+                    info!("Verification request {} was cancelled", program_name);
+                    return Err(VerifierError::Cancelled);
+                }
+            };
+        let timeout = request.timeout;
+        let backend_config = backend_config_with_timeout(&request.backend_config, timeout);
+
         // try to find and take out an existing threads from our cache
         let existing_thread = {
             let mut threads = self.threads.write().unwrap();
             let index = threads
                 .iter()
-                .position(|thread| thread.backend_config == request.backend_config);
+                .position(|thread| thread.backend_config == backend_config);
             index.map(|index| threads.remove(index).unwrap())
         };
 
         // if no thread found, create a new one
-        let thread = existing_thread.unwrap_or_else(|| {
-            VerifierThread::new(
-                self.verifier_builder.clone(),
-                request.backend_config.clone(),
-            )
+        let verifier_thread = existing_thread.unwrap_or_else(|| {
+            VerifierThread::new(self.verifier_builder.clone(), backend_config)
+        });
+
+        // Wait for the result on another thread, so that the verification can be abandoned when
+        // it is cancelled, or when it takes too long and the backend does not stop it by itself
+        // (Carbon has no timeout). The abandoned verifier thread is not reused.
+        let (result_sender, result_receiver) = mpsc::channel();
+        let cancellation_sender = result_sender.clone();
+        cancellation.set_handler(Box::new(move || {
+            let _ = cancellation_sender.send(None);
+        }));
+        thread::spawn(move || {
+            let result = verifier_thread
+                .verify(request.program, request.program_name)
+                .wait();
+            drop(running_verification);
+            // the receiver is gone if the verification timed out or was cancelled
+            let _ = result_sender.send(Some((verifier_thread, result)));
         });
+        let received = match timeout {
+            None => result_receiver
+                .recv()
+                .map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+            Some(timeout) => result_receiver
+                .recv_timeout(Duration::from_secs(timeout) + BACKEND_TIMEOUT_GRACE_PERIOD),
+        };
+        cancellation.clear_handler();
+        let (verifier_thread, result) = match received {
+            Ok(Some(thread_and_result)) => thread_and_result,
+            Ok(None) => {
+                info!("Verification request {} was cancelled", program_name);
+                return Err(VerifierError::Cancelled);
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                let timeout = timeout.unwrap();
+                warn!(
+                    "Verification request {} timed out after {} seconds",
+                    program_name, timeout
+                );
+                return Err(VerifierError::TimedOut(timeout));
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                error!("Panic while handling verification request {}", program_name);
+                return Err(VerifierError::Panicked);
+            }
+        };
 
-        match thread
-            .verify(request.program, request.program_name.clone())
-            .wait()
-        {
+        match result {
             Ok(result) => {
                 // put back the thread for later reuse
                 let mut threads = self.threads.write().unwrap();
@@ -84,17 +335,65 @@ impl PrustiServer {
                     // evict least-recently-used thread from cache)
                     threads.pop_back();
                 }
-                threads.push_front(thread);
-                Ok(result)
+                threads.push_front(verifier_thread);
+                match (result, timeout) {
+                    (VerificationResult::TimedOut, Some(timeout)) => {
+                        warn!(
+                            "Verification request {} timed out after {} seconds",
+                            program_name, timeout
+                        );
+                        Err(VerifierError::TimedOut(timeout))
+                    }
+                    (result, _) => Ok(result),
+                }
             }
             Err(_) => {
                 // canceled—the verifier thread panicked
                 error!(
                     "Panic while handling verification request {}",
-                    request.program_name
+                    program_name
                 );
-                Err(VerifierPanicked)
+                Err(VerifierError::Panicked)
             }
         }
     }
 }
+
+/// How often a request that waits for the abandoned verifications checks whether it has been
+/// cancelled.
+const CANCELLATION_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// A verification running on the JVM of a `PrustiServer`, which is counted until it is dropped.
+struct RunningVerification {
+    running_verifications: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl Drop for RunningVerification {
+    fn drop(&mut self) {
+        let (running_verifications, finished) = &*self.running_verifications;
+        *running_verifications.lock().unwrap() -= 1;
+        finished.notify_all();
+    }
+}
+
+/// How long to wait for the result of a request after its timeout, before abandoning it. The
+/// backend should stop the verification by itself within this period.
+pub(crate) const BACKEND_TIMEOUT_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// The configuration of the backend that verifies a request with the given timeout. Silicon
+/// stops the verification itself when the timeout elapses, while Carbon has no such option.
+fn backend_config_with_timeout(
+    backend_config: &ViperBackendConfig,
+    timeout: Option<u64>,
+) -> ViperBackendConfig {
+    let mut backend_config = backend_config.clone();
+    if let Some(timeout) = timeout {
+        if backend_config.backend == viper::VerificationBackend::Silicon {
+            backend_config.verifier_args.extend(vec![
+                "--timeout".to_string(),
+                timeout.to_string(),
+            ]);
+        }
+    }
+    backend_config
+}
@@ -0,0 +1,155 @@
+// © 2021, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::VerificationOutcome;
+use std::{fmt::Write, sync::Mutex, time::Duration};
+
+/// The labels of the outcomes of verification requests, in the order of `outcome_index`.
+const OUTCOME_LABELS: [&str; 5] = ["verified", "failed", "timed_out", "panicked", "cancelled"];
+
+fn outcome_index(outcome: VerificationOutcome) -> usize {
+    match outcome {
+        VerificationOutcome::Verified => 0,
+        VerificationOutcome::Failed(_) => 1,
+        VerificationOutcome::TimedOut => 2,
+        VerificationOutcome::Panicked => 3,
+        VerificationOutcome::Cancelled => 4,
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    requests: u64,
+    outcomes: [u64; OUTCOME_LABELS.len()],
+    cache_hits: u64,
+    cache_misses: u64,
+    running_verifications: u64,
+    /// The total time spent verifying the requests that were not cached.
+    verification_time: Duration,
+    verifications: u64,
+}
+
+/// The current state of the server, which is reported together with the counters.
+pub struct ServerLoad {
+    pub cached_results: usize,
+    /// The number of verifier threads kept for later requests, or of worker processes.
+    pub verifiers: usize,
+    pub queued_jobs: usize,
+}
+
+/// Counters of the requests handled by a server, reported by its `/metrics` endpoint in the
+/// text format of Prometheus.
+#[derive(Default)]
+pub struct ServerMetrics {
+    counters: Mutex<Counters>,
+}
+
+impl ServerMetrics {
+    pub fn record_request(&self) {
+        self.counters.lock().unwrap().requests += 1;
+    }
+
+    pub fn record_cache_hit(&self, outcome: VerificationOutcome) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.cache_hits += 1;
+        counters.outcomes[outcome_index(outcome)] += 1;
+    }
+
+    pub fn record_verification_start(&self) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.cache_misses += 1;
+        counters.running_verifications += 1;
+    }
+
+    pub fn record_verification_end(&self, outcome: VerificationOutcome, duration: Duration) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.running_verifications -= 1;
+        counters.outcomes[outcome_index(outcome)] += 1;
+        counters.verification_time += duration;
+        counters.verifications += 1;
+    }
+
+    pub fn render(&self, load: ServerLoad) -> String {
+        let counters = self.counters.lock().unwrap();
+        let mut text = String::new();
+        // each sample is given with the suffix of the metric name and its labels
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(String, String)]| {
+            writeln!(text, "# HELP prusti_server_{} {}", name, help).unwrap();
+            writeln!(text, "# TYPE prusti_server_{} {}", name, kind).unwrap();
+            for (suffix, value) in samples {
+                writeln!(text, "prusti_server_{}{} {}", name, suffix, value).unwrap();
+            }
+        };
+        let value = |value: u64| vec![(String::new(), value.to_string())];
+
+        metric(
+            "requests_total",
+            "counter",
+            "Verification requests received by the server.",
+            &value(counters.requests),
+        );
+        let outcomes: Vec<_> = OUTCOME_LABELS
+            .iter()
+            .zip(&counters.outcomes)
+            .map(|(label, count)| (format!("{{outcome=\"{}\"}}", label), count.to_string()))
+            .collect();
+        metric(
+            "results_total",
+            "counter",
+            "Verification requests that finished, by outcome.",
+            &outcomes,
+        );
+        metric(
+            "cache_hits_total",
+            "counter",
+            "Verification requests whose result was found in the cache.",
+            &value(counters.cache_hits),
+        );
+        metric(
+            "cache_misses_total",
+            "counter",
+            "Verification requests that had to be verified.",
+            &value(counters.cache_misses),
+        );
+        metric(
+            "verification_duration_seconds",
+            "summary",
+            "Time spent verifying the requests that were not cached.",
+            &[
+                (
+                    "_sum".to_string(),
+                    counters.verification_time.as_secs_f64().to_string(),
+                ),
+                ("_count".to_string(), counters.verifications.to_string()),
+            ],
+        );
+        metric(
+            "running_verifications",
+            "gauge",
+            "Verification requests that are being verified.",
+            &value(counters.running_verifications),
+        );
+        metric(
+            "queued_jobs",
+            "gauge",
+            "Jobs waiting for a verifier.",
+            &value(load.queued_jobs as u64),
+        );
+        metric(
+            "cached_results",
+            "gauge",
+            "Verification results in the cache.",
+            &value(load.cached_results as u64),
+        );
+        metric(
+            "verifiers",
+            "gauge",
+            "Idle verifier threads kept for later requests, or worker processes.",
+            &value(load.verifiers as u64),
+        );
+        text
+    }
+}
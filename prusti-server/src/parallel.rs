@@ -0,0 +1,89 @@
+// © 2021, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{RemoteVerificationResult, VerificationOutcome, VerificationProgress};
+use prusti_common::verification_service::VerificationRequest;
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Verify the given requests concurrently, running at most `max_concurrency` of them at the same
+/// time. `verify` is called on a separate thread for each request, so that a panic only
+/// affects the result of its own request.
+///
+/// `verify` reports the progress of each request to the given callback, which forwards it to
+/// `on_progress`. The results are returned in the same order as the requests, each together with
+/// the time that it took to verify the request.
+pub fn verify_concurrently<F, P>(
+    requests: Vec<VerificationRequest>,
+    max_concurrency: usize,
+    verify: F,
+    on_progress: P,
+) -> Vec<(RemoteVerificationResult, Duration)>
+where
+    F: Fn(VerificationRequest, &dyn Fn(VerificationProgress)) -> RemoteVerificationResult
+        + Send
+        + Sync
+        + 'static,
+    P: Fn(VerificationProgress) + Send + Sync + 'static,
+{
+    let num_requests = requests.len();
+    let num_threads = max_concurrency.max(1).min(num_requests);
+    let verify = Arc::new(verify);
+    let on_progress = Arc::new(on_progress);
+    let queue = Arc::new(Mutex::new(requests.into_iter().enumerate()));
+    let (result_sender, result_receiver) = mpsc::channel();
+
+    let workers: Vec<_> = (0..num_threads)
+        .map(|_| {
+            let verify = verify.clone();
+            let on_progress = on_progress.clone();
+            let queue = queue.clone();
+            let result_sender = result_sender.clone();
+            thread::spawn(move || loop {
+                let next_request = queue.lock().unwrap().next();
+                let (index, request) = match next_request {
+                    Some(next_request) => next_request,
+                    None => break,
+                };
+                let program_name = request.program_name.clone();
+                let verify = verify.clone();
+                let thread_on_progress = on_progress.clone();
+                let start_time = Instant::now();
+                let result = thread::spawn(move || verify(request, &*thread_on_progress))
+                    .join()
+                    .unwrap_or_else(|_| {
+                        error!("Panic while verifying {}", program_name);
+                        let result = Err(super::VerifierError::Panicked);
+                        on_progress(VerificationProgress::Finished {
+                            program_name,
+                            outcome: VerificationOutcome::of(&result),
+                            duration: start_time.elapsed(),
+                        });
+                        result
+                    });
+                let duration = start_time.elapsed();
+                result_sender.send((index, (result, duration))).unwrap();
+            })
+        })
+        .collect();
+    drop(result_sender);
+
+    let mut results: Vec<Option<(RemoteVerificationResult, Duration)>> =
+        (0..num_requests).map(|_| None).collect();
+    for (index, result) in result_receiver {
+        results[index] = Some(result);
+    }
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    results
+        .into_iter()
+        .map(|result| result.expect("missing verification result"))
+        .collect()
+}
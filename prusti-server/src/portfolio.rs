@@ -0,0 +1,252 @@
+// © 2021, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{
+    Cancellation, RemoteVerificationResult, VerificationOutcome,
+    VerificationProgress, VerifierError,
+};
+use prusti_common::{
+    config,
+    verification_service::{VerificationRequest, ViperBackendConfig},
+};
+use std::{
+    collections::HashSet,
+    str::FromStr,
+    sync::{mpsc, Arc},
+    thread,
+    time::Instant,
+};
+use viper::{VerificationBackend, VerificationError, VerificationResult};
+
+/// How the Viper backends verify a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendMode {
+    /// Verify with the backend of the request.
+    Single,
+    /// Verify with both Silicon and Carbon, and take the first definitive result.
+    Portfolio,
+    /// Verify with both Silicon and Carbon, and report whether their results differ.
+    CrossCheck,
+}
+
+impl BackendMode {
+    /// The mode selected by the `VIPER_BACKEND_MODE` flag, or an error if the flag is invalid.
+    pub fn from_config() -> Result<Self, String> {
+        config::viper_backend_mode().parse()
+    }
+}
+
+impl FromStr for BackendMode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode.to_lowercase().as_str() {
+            "single" => Ok(BackendMode::Single),
+            "portfolio" => Ok(BackendMode::Portfolio),
+            "cross-check" => Ok(BackendMode::CrossCheck),
+            _ => Err(format!(
+                "Invalid Viper backend mode: '{}'. Allowed values are 'single', 'portfolio' and \
+                'cross-check'",
+                mode
+            )),
+        }
+    }
+}
+
+/// The differing results of the backends that cross-checked a request.
+#[derive(Debug, Clone)]
+pub struct Disagreement {
+    pub program_name: String,
+    /// The result of each backend, starting with the backend selected by `VIPER_BACKEND`.
+    pub results: Vec<(VerificationBackend, RemoteVerificationResult)>,
+}
+
+/// Whether the result tells if the program verifies, rather than that the backend could not
+/// verify it.
+fn is_definitive(result: &RemoteVerificationResult) -> bool {
+    matches!(
+        result,
+        Ok(VerificationResult::Success()) | Ok(VerificationResult::Failure(_))
+    )
+}
+
+/// Whether the backends agree on the results: both verify the program, or both fail to verify it
+/// at the same positions. Silicon and Carbon often report a different number of errors, or a
+/// different reason, for the same failure, so only the positions of the errors are compared.
+fn results_agree(first: &RemoteVerificationResult, second: &RemoteVerificationResult) -> bool {
+    let failing_positions = |errors: &[VerificationError]| {
+        errors.iter().map(|error| error.pos_id.clone()).collect::<HashSet<_>>()
+    };
+    match (first, second) {
+        (
+            Ok(VerificationResult::Failure(first_errors)),
+            Ok(VerificationResult::Failure(second_errors)),
+        ) => failing_positions(first_errors) == failing_positions(second_errors),
+        _ => first == second,
+    }
+}
+
+/// Silicon and Carbon, starting with `primary`.
+fn both_backends(primary: VerificationBackend) -> Vec<VerificationBackend> {
+    let secondary = match primary {
+        VerificationBackend::Silicon => VerificationBackend::Carbon,
+        VerificationBackend::Carbon => VerificationBackend::Silicon,
+    };
+    vec![primary, secondary]
+}
+
+/// Verify the request with the backends of `mode`, each with its own call to `verify`, and
+/// report the progress of the request as a whole to `on_progress`. The backend of the request,
+/// i.e. the one selected by `VIPER_BACKEND`, is the primary one.
+///
+/// In portfolio mode, the first definitive result is returned; if no backend gives a definitive
+/// result, the result of the primary backend is returned. In cross-check mode, the result of the
+/// primary backend is returned together with the results of both backends if they differ.
+///
+/// If `concurrently` is true, the backends verify the request at the same time, on their own
+/// threads, and in portfolio mode the verification of the slower backend is cancelled. This is
+/// only useful if `verify` can really stop a cancelled verification, e.g. by killing the worker
+/// process of a server. Otherwise, the backends verify the request one after the other, and in
+/// portfolio mode the secondary backend only verifies it if the primary one gives no definitive
+/// result.
+pub fn verify_with_backends<F>(
+    mode: BackendMode,
+    request: VerificationRequest,
+    verify: &Arc<F>,
+    on_progress: &dyn Fn(VerificationProgress),
+    concurrently: bool,
+) -> (RemoteVerificationResult, Option<Disagreement>)
+where
+    F: Fn(VerificationRequest, &dyn Fn(VerificationProgress), &Cancellation)
+            -> RemoteVerificationResult
+        + Send
+        + Sync
+        + ?Sized
+        + 'static,
+{
+    let program_name = request.program_name.clone();
+    on_progress(VerificationProgress::Started {
+        program_name: program_name.clone(),
+    });
+    let start_time = Instant::now();
+    let backends = match mode {
+        BackendMode::Single => vec![request.backend_config.backend],
+        BackendMode::Portfolio | BackendMode::CrossCheck => {
+            both_backends(request.backend_config.backend)
+        }
+    };
+    let requests: Vec<_> = backends
+        .iter()
+        .map(|&backend| {
+            let mut request = request.clone();
+            if mode != BackendMode::Single {
+                request.backend_config = ViperBackendConfig::new(backend);
+            }
+            request
+        })
+        .collect();
+    let (results, first_definitive_result) = if concurrently {
+        verify_concurrently_with_backends(mode, &backends, requests, verify)
+    } else {
+        verify_sequentially_with_backends(mode, &backends, requests, verify)
+    };
+
+    let agree = mode != BackendMode::CrossCheck || results_agree(&results[0], &results[1]);
+    let disagreement = if !agree {
+        warn!("Silicon and Carbon disagree on the result of {}", program_name);
+        Some(Disagreement {
+            program_name: program_name.clone(),
+            results: backends.iter().cloned().zip(results.iter().cloned()).collect(),
+        })
+    } else {
+        None
+    };
+    let result = first_definitive_result.unwrap_or_else(|| results[0].clone());
+    on_progress(VerificationProgress::Finished {
+        program_name,
+        outcome: VerificationOutcome::of(&result),
+        duration: start_time.elapsed(),
+    });
+    (result, disagreement)
+}
+
+/// The result of each backend, and in portfolio mode the first definitive result, if any.
+type BackendResults = (Vec<RemoteVerificationResult>, Option<RemoteVerificationResult>);
+
+/// Verify the request of each backend on its own thread. In portfolio mode, the verifications of
+/// the other backends are cancelled once a backend gives a definitive result.
+fn verify_concurrently_with_backends<F>(
+    mode: BackendMode,
+    backends: &[VerificationBackend],
+    requests: Vec<VerificationRequest>,
+    verify: &Arc<F>,
+) -> BackendResults
+where
+    F: Fn(VerificationRequest, &dyn Fn(VerificationProgress), &Cancellation)
+            -> RemoteVerificationResult
+        + Send
+        + Sync
+        + ?Sized
+        + 'static,
+{
+    let cancellations: Vec<_> = backends.iter().map(|_| Cancellation::default()).collect();
+    let (result_sender, result_receiver) = mpsc::channel();
+    for (index, request) in requests.into_iter().enumerate() {
+        let verify = verify.clone();
+        let cancellation = cancellations[index].clone();
+        let result_sender = result_sender.clone();
+        thread::spawn(move || {
+            let result = verify(request, &|_| {}, &cancellation);
+            // the result is no longer needed if another backend has already given one
+            let _ = result_sender.send((index, result));
+        });
+    }
+    drop(result_sender);
+
+    // the threads that panicked do not send a result
+    let mut results = vec![Err(VerifierError::Panicked); backends.len()];
+    for (index, result) in result_receiver {
+        if mode == BackendMode::Portfolio && is_definitive(&result) {
+            info!("{} gave the first result", backends[index]);
+            for (other_index, cancellation) in cancellations.iter().enumerate() {
+                if other_index != index {
+                    cancellation.cancel();
+                }
+            }
+            return (results, Some(result));
+        }
+        results[index] = result;
+    }
+    (results, None)
+}
+
+/// Verify the request of each backend one after the other. In portfolio mode, the remaining
+/// backends are skipped once a backend gives a definitive result.
+fn verify_sequentially_with_backends<F>(
+    mode: BackendMode,
+    backends: &[VerificationBackend],
+    requests: Vec<VerificationRequest>,
+    verify: &Arc<F>,
+) -> BackendResults
+where
+    F: Fn(VerificationRequest, &dyn Fn(VerificationProgress), &Cancellation)
+            -> RemoteVerificationResult
+        + Send
+        + Sync
+        + ?Sized
+        + 'static,
+{
+    let mut results = vec![];
+    for (index, request) in requests.into_iter().enumerate() {
+        let result = verify(request, &|_| {}, &Cancellation::default());
+        if mode == BackendMode::Portfolio && is_definitive(&result) {
+            info!("{} gave a definitive result", backends[index]);
+            return (vec![], Some(result));
+        }
+        results.push(result);
+    }
+    (results, None)
+}
@@ -0,0 +1,132 @@
+// © 2021, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{CacheKey, RemoteVerificationResult, VerifierError};
+use prusti_common::{config, verification_service::VerificationRequest};
+use serde_json;
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use viper::{VerificationError, VerificationResult};
+
+/// A verification request recorded together with its result, so that the verification can be
+/// reproduced without the crate that produced the request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub request: VerificationRequest,
+    pub result: RemoteVerificationResult,
+}
+
+impl RecordedRequest {
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let file = fs::File::open(path)?;
+        serde_json::from_reader(io::BufReader::new(file))
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// Write the recording to a new file in `dir`, named after the program and the hash of the
+    /// request. Returns the path of the file.
+    pub fn write(&self, dir: &Path) -> io::Result<PathBuf> {
+        let program_name: String = self
+            .request
+            .program_name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+            .collect();
+        let path = dir.join(format!(
+            "{}-{:016x}.json",
+            program_name,
+            CacheKey::of(&self.request).hash
+        ));
+        fs::create_dir_all(dir)?;
+        let bytes = serde_json::to_vec_pretty(self)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        fs::write(&path, bytes)?;
+        Ok(path)
+    }
+}
+
+/// Record the request and its result in the `RECORD_REQUESTS` directory, if it is set.
+pub fn record_request(request: VerificationRequest, result: &RemoteVerificationResult) {
+    if let Some(dir) = config::record_requests() {
+        let program_name = request.program_name.clone();
+        let recording = RecordedRequest {
+            request,
+            result: result.clone(),
+        };
+        match recording.write(Path::new(&dir)) {
+            Ok(path) => info!("Recorded verification request {} in {:?}", program_name, path),
+            Err(error) => error!(
+                "Could not record verification request {} in {:?}: {}",
+                program_name, dir, error
+            ),
+        }
+    }
+}
+
+/// A one-line description of a result.
+pub fn describe_result(result: &RemoteVerificationResult) -> String {
+    match result {
+        Ok(VerificationResult::Success()) => "verified".to_string(),
+        Ok(VerificationResult::Failure(errors)) => format!("{} verification errors", errors.len()),
+        Ok(VerificationResult::ConsistencyErrors(errors)) => {
+            format!("{} consistency errors", errors.len())
+        }
+        Ok(VerificationResult::JavaException(exception)) => {
+            format!("Java exception: {}", exception)
+        }
+        Ok(VerificationResult::TimedOut) => "the backend timed out".to_string(),
+        Err(VerifierError::Panicked) => "the verifier panicked".to_string(),
+        Err(VerifierError::TimedOut(timeout)) => format!("timed out after {} seconds", timeout),
+        Err(VerifierError::Cancelled) => "cancelled".to_string(),
+    }
+}
+
+fn describe_error(error: &VerificationError) -> String {
+    format!(
+        "{} at {}: {}",
+        error.full_id,
+        error.pos_id.as_deref().unwrap_or("unknown position"),
+        error.message
+    )
+}
+
+/// The differences between the result of a recorded request and the result of its replay, one
+/// per line: the lines of the recorded result start with `-`, the ones of the replay with `+`.
+/// Verification errors are compared by kind and position, ignoring their counterexamples.
+pub fn diff_results(
+    recorded: &RemoteVerificationResult,
+    replayed: &RemoteVerificationResult,
+) -> Vec<String> {
+    match (recorded, replayed) {
+        (
+            Ok(VerificationResult::Failure(recorded_errors)),
+            Ok(VerificationResult::Failure(replayed_errors)),
+        ) => {
+            let key = |error: &VerificationError| {
+                (error.full_id.clone(), error.pos_id.clone(), error.reason_pos_id.clone())
+            };
+            let recorded_keys: HashSet<_> = recorded_errors.iter().map(key).collect();
+            let replayed_keys: HashSet<_> = replayed_errors.iter().map(key).collect();
+            let removed = recorded_errors
+                .iter()
+                .filter(|&error| !replayed_keys.contains(&key(error)))
+                .map(|error| format!("- {}", describe_error(error)));
+            let added = replayed_errors
+                .iter()
+                .filter(|&error| !recorded_keys.contains(&key(error)))
+                .map(|error| format!("+ {}", describe_error(error)));
+            removed.chain(added).collect()
+        }
+        _ if recorded == replayed => vec![],
+        _ => vec![
+            format!("- {}", describe_result(recorded)),
+            format!("+ {}", describe_result(replayed)),
+        ],
+    }
+}
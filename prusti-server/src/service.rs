@@ -4,32 +4,61 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use super::{PrustiServer, RemoteVerificationResult};
+use super::{
+    create_backend, Backend, CacheKey, Cancellation, JobId, JobInfo, JobQueue,
+    JobStatus, RemoteVerificationResult, ResultCache, ServerLoad, ServerMetrics,
+    VerificationOutcome, VerificationProgress, VerifierError, VersionInfo, WorkerPool,
+    PROTOCOL_VERSION,
+};
 use prusti_common::{config, verification_service::*};
 
 use bincode;
-use futures::{self};
+use futures::{self, Future, Stream};
+use hyper;
 use num_cpus;
 use reqwest::{self, Client, Url, UrlError};
+use serde_json;
+#[cfg(unix)]
+use std::{
+    fs,
+    os::unix::{
+        fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+        net::UnixStream,
+    },
+    path::Path,
+    process,
+};
 use std::{
+    error, fmt,
+    io::{self, BufRead, BufReader, Write},
     net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
     sync::{mpsc, Arc},
     thread,
+    time::{Duration, Instant},
 };
 use tokio;
-use viper::VerificationResult;
+#[cfg(unix)]
+use tokio::net::UnixListener;
 use warp::{self, Buf, Filter};
 
 #[derive(Clone)]
 pub struct ServerSideService {
-    server: Arc<PrustiServer>,
+    /// The backend that verifies the requests of the server.
+    backend: Arc<dyn Backend>,
     max_concurrency: usize,
+    cache: Arc<ResultCache>,
+    jobs: Arc<JobQueue>,
+    metrics: Arc<ServerMetrics>,
 }
 
 impl ServerSideService {
-    pub fn new() -> Self {
+    /// Create a service that verifies the requests on the threads of a JVM in this process.
+    /// Fails if the verification backend cannot be created.
+    pub fn new() -> io::Result<Self> {
         // FIXME: since viper seems to dislike using verifiers in parallel, this is what we're doing to ensure correctness for now.
         // Eventually, we should lock only specific parts, instantiate multiple JVMs, or even address the root cause.
+        // To verify requests concurrently on several JVMs, use `with_worker_processes` instead.
         let _max_concurrency = config::server_max_concurrency().unwrap_or_else(num_cpus::get);
         let max_concurrency = 1;
 
@@ -38,19 +67,48 @@ impl ServerSideService {
             warn!("PRUSTI_SERVER_MAX_STORED_VERIFIERS is lower than PRUSTI_SERVER_MAX_CONCURRENCY—you probably don't want to do this, since it means the server will likely have to keep creating new verifiers, reducing the performance gained from reuse.");
         }
 
+        let backend = create_backend(&config::verification_backend(), cache_size)?;
+        Ok(Self::with_backend(backend, max_concurrency))
+    }
+
+    /// Create a service that verifies up to `SERVER_MAX_CONCURRENCY` requests concurrently, each
+    /// on a child process running the server executable `executable`. The child processes are
+    /// started when the requests need them.
+    pub fn with_worker_processes(executable: PathBuf) -> Self {
+        let max_concurrency = config::server_max_concurrency().unwrap_or_else(num_cpus::get);
+        let pool = WorkerPool::new(executable, max_concurrency);
+        Self::with_backend(Arc::new(pool), max_concurrency)
+    }
+
+    /// Create a service that verifies up to `max_concurrency` requests concurrently with
+    /// `backend`.
+    pub fn with_backend(backend: Arc<dyn Backend>, max_concurrency: usize) -> Self {
         Self {
+            backend,
             max_concurrency,
-            server: Arc::new(PrustiServer::new(cache_size)),
+            cache: Arc::new(Self::new_result_cache()),
+            jobs: Arc::new(JobQueue::new()),
+            metrics: Arc::new(ServerMetrics::default()),
         }
     }
 
-    pub fn spawn_off_thread() -> SocketAddr {
+    fn new_result_cache() -> ResultCache {
+        ResultCache::new(
+            config::server_cache_size(),
+            config::server_cache_path().map(PathBuf::from),
+        )
+    }
+
+    /// Start a server on another thread, and return its address once it listens. Fails if the
+    /// verification backend cannot be created.
+    pub fn spawn_off_thread() -> io::Result<SocketAddr> {
         let (sender, receiver) = mpsc::channel();
-        thread::spawn(move || {
-            ServerSideService::new().listen_on_ephemeral_port(
+        thread::spawn(move || match ServerSideService::new() {
+            Ok(service) => service.listen_on_ephemeral_port(
                 0, // ask system for port
-                |address| sender.send(address).unwrap(),
-            );
+                |address| sender.send(Ok(address)).unwrap(),
+            ),
+            Err(error) => sender.send(Err(error)).unwrap(),
         });
         receiver.recv().unwrap()
     }
@@ -73,6 +131,150 @@ impl ServerSideService {
     where
         F: FnOnce(SocketAddr),
     {
+        self.start_job_runners();
+        info!("Prusti Server binding to port {}", port);
+        let (address, server_handle) =
+            warp::serve(self.endpoints()).bind_ephemeral((Ipv4Addr::LOCALHOST, port));
+        address_callback(address);
+
+        info!("Prusti Server starting on port {}", address.port());
+        self.run(server_handle, move || {
+            println!("port: {}", address.port()); // stdout, for use in other applications
+        });
+    }
+
+    /// Listen on a Unix domain socket at `path`, which is created with the given permissions.
+    /// Unlike a TCP port, the socket can only be used by the users allowed by its permissions.
+    /// Only the socket of a server that no longer runs is replaced; if `path` is anything else,
+    /// an error is returned.
+    #[cfg(unix)]
+    pub fn listen_on_unix_socket(self, path: &Path, permissions: u32) -> io::Result<()> {
+        if let Ok(metadata) = fs::symlink_metadata(path) {
+            if !metadata.file_type().is_socket() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", path.display()),
+                ));
+            }
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("another server is listening on {}", path.display()),
+                ));
+            }
+            // remove the socket of a previous server
+            fs::remove_file(path)?;
+        }
+        // Bind the socket in a directory that only the current user can access, so that nobody
+        // else can connect before its permissions are set, and then move it to `path`. Unlike a
+        // rename, a hard link fails if something has been created at `path` in the meantime.
+        let file_name = path.file_name().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "the socket path has no file name")
+        })?;
+        let parent = path.parent().unwrap_or_else(|| Path::new("."));
+        let private_dir = parent.join(format!(".prusti-server-{}", process::id()));
+        fs::DirBuilder::new().mode(0o700).create(&private_dir)?;
+        let private_path = private_dir.join(file_name);
+        let bound = UnixListener::bind(&private_path).and_then(|listener| {
+            fs::set_permissions(&private_path, fs::Permissions::from_mode(permissions))?;
+            fs::hard_link(&private_path, path)?;
+            Ok(listener)
+        });
+        let _ = fs::remove_file(&private_path);
+        fs::remove_dir(&private_dir)?;
+        let listener = bound?;
+
+        self.start_job_runners();
+        info!("Prusti Server starting on Unix socket {:?}", path);
+        let server = warp::serve(self.endpoints()).serve_incoming(listener.incoming());
+        let path = path.to_path_buf();
+        self.run(server, move || {
+            println!("socket: {}", path.display()); // stdout, for use in other applications
+        });
+        Ok(())
+    }
+
+    /// Handle the requests of a single client that talks to the server over stdin and stdout.
+    /// Each line of stdin is a JSON `VerificationRequest`, to which the server replies on stdout
+    /// with JSON lines of `ServerMessage`s, like the `json/verify_with_progress` endpoint.
+    /// The server exits when stdin is closed. The log is written to stderr.
+    pub fn serve_stdio(self) {
+        info!("Prusti Server reading requests from stdin");
+        let stdin = io::stdin();
+        let stdout = io::stdout();
+        let send = |message: ServerMessage| {
+            let mut stdout = stdout.lock();
+            serde_json::to_writer(&mut stdout, &message).expect("could not encode server message");
+            writeln!(stdout).and_then(|()| stdout.flush()).unwrap_or_else(|error| {
+                error!("Could not write to stdout: {}", error);
+            });
+        };
+        for line in stdin.lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(error) => {
+                    error!("Could not read from stdin: {}", error);
+                    break;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<VerificationRequest>(&line) {
+                Ok(request) => {
+                    let response = self.verify_with_progress(request, &|progress| {
+                        send(ServerMessage::Progress(progress))
+                    });
+                    send(ServerMessage::Response(response));
+                }
+                Err(error) => {
+                    error!("Invalid request on stdin: {}", error);
+                    send(ServerMessage::InvalidRequest(error.to_string()));
+                }
+            }
+        }
+        info!("Prusti Server stopping, stdin was closed");
+    }
+
+    /// Start the threads that verify the jobs submitted to the server.
+    fn start_job_runners(&self) {
+        let service = self.clone();
+        self.jobs.run_jobs(self.max_concurrency, move |request, cancellation| {
+            service.verify_cancellable(request, &|_| {}, cancellation)
+        });
+    }
+
+    /// Run the server on a Tokio runtime, calling `on_launch` once it has started. This blocks
+    /// the current thread.
+    fn run<S, F>(&self, server: S, on_launch: F)
+    where
+        S: Future<Item = (), Error = ()> + Send + 'static,
+        F: FnOnce() + Send + 'static,
+    {
+        let mut runtime = tokio::runtime::Builder::new()
+            .name_prefix("prusti-server-")
+            .core_threads(self.max_concurrency)
+            .build()
+            .expect("could not construct Tokio runtime!");
+
+        runtime.spawn(futures::lazy(move || {
+            warp::spawn(server);
+            info!("Prusti Server launched!");
+            on_launch();
+            Ok(())
+        }));
+
+        thread::park();
+    }
+
+    /// The HTTP endpoints of the server.
+    fn endpoints(
+        &self,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection>
+           + Clone
+           + Send
+           + Sync
+           + 'static {
         let clone = self.clone();
         let json_verify = warp::path("json")
             .and(warp::path("verify"))
@@ -99,43 +301,310 @@ impl ServerSideService {
                 )
             });
 
-        let endpoints = json_verify.or(bincode_verify);
+        // Stream the progress of the verification as JSON lines, followed by the result. Empty
+        // lines are sent meanwhile, so that the verification is cancelled once the client has
+        // disconnected.
+        let clone = self.clone();
+        let json_verify_with_progress = warp::path("json")
+            .and(warp::path("verify_with_progress"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .map(move |request: VerificationRequest| {
+                let (sender, receiver) = futures::sync::mpsc::unbounded();
+                let service = clone.clone();
+                let cancellation = Cancellation::default();
+                let (finished_sender, finished_receiver) = mpsc::channel::<()>();
+                {
+                    let sender = sender.clone();
+                    let cancellation = cancellation.clone();
+                    thread::spawn(move || {
+                        while let Err(mpsc::RecvTimeoutError::Timeout) =
+                            finished_receiver.recv_timeout(KEEP_ALIVE_INTERVAL)
+                        {
+                            // the receiver is gone if the client disconnected
+                            if sender.unbounded_send("\n".to_string()).is_err() {
+                                info!("The client disconnected, cancelling its request");
+                                cancellation.cancel();
+                                break;
+                            }
+                        }
+                    });
+                }
+                thread::spawn(move || {
+                    let send = |message: ServerMessage| {
+                        let mut line = serde_json::to_string(&message)
+                            .expect("could not encode server message");
+                        line.push('\n');
+                        // the receiver is gone if the client disconnected
+                        let _ = sender.unbounded_send(line);
+                    };
+                    let response = service.verify_cancellable(
+                        request,
+                        &|progress| send(ServerMessage::Progress(progress)),
+                        &cancellation,
+                    );
+                    drop(finished_sender);
+                    send(ServerMessage::Response(response));
+                });
+                let body = receiver.map_err(|()| {
+                    io::Error::new(io::ErrorKind::Other, "verification message channel failed")
+                });
+                warp::http::Response::new(hyper::Body::wrap_stream(body))
+            });
 
-        info!("Prusti Server binding to port {}", port);
-        let (address, server_handle) =
-            warp::serve(endpoints).bind_ephemeral((Ipv4Addr::LOCALHOST, port));
-        address_callback(address);
+        // Verify requests in the background, as jobs that can be inspected and cancelled.
+        let jobs = self.jobs.clone();
+        let submit_job = warp::post2()
+            .and(warp::path("jobs"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .map(move |request: VerificationRequest| warp::reply::json(&jobs.submit(request)));
 
-        info!("Prusti Server starting on port {}", address.port());
-        let mut runtime = tokio::runtime::Builder::new()
-            .name_prefix("prusti-server-")
-            .core_threads(self.max_concurrency)
-            .build()
-            .expect("could not construct Tokio runtime!");
+        let jobs = self.jobs.clone();
+        let list_jobs = warp::get2()
+            .and(warp::path("jobs"))
+            .and(warp::path::end())
+            .map(move || warp::reply::json(&jobs.list()));
 
-        runtime.spawn(futures::lazy(move || {
-            warp::spawn(server_handle);
-            info!("Prusti Server launched!");
-            println!("port: {}", address.port()); // stdout, for use in other applications
-            Ok(())
-        }));
+        let jobs = self.jobs.clone();
+        let job_status = warp::get2()
+            .and(warp::path("jobs"))
+            .and(warp::path::param::<JobId>())
+            .and(warp::path::end())
+            .and_then(move |id| match jobs.status(id) {
+                Some(info) => Ok(warp::reply::json(&info)),
+                None => Err(warp::reject::not_found()),
+            });
 
-        thread::park();
+        let jobs = self.jobs.clone();
+        let cancel_job = warp::delete2()
+            .and(warp::path("jobs"))
+            .and(warp::path::param::<JobId>())
+            .and(warp::path::end())
+            .and_then(move |id| match jobs.cancel(id) {
+                Some(info) => Ok(warp::reply::json(&info)),
+                None => Err(warp::reject::not_found()),
+            });
+
+        // Report whether the server can verify requests.
+        let clone = self.clone();
+        let health = warp::get2()
+            .and(warp::path("health"))
+            .and(warp::path::end())
+            .map(move || {
+                let healthy = clone.is_healthy();
+                let status = if healthy {
+                    warp::http::StatusCode::OK
+                } else {
+                    warp::http::StatusCode::SERVICE_UNAVAILABLE
+                };
+                warp::reply::with_status(warp::reply::json(&Health { healthy }), status)
+            });
+
+        let clone = self.clone();
+        let version = warp::get2()
+            .and(warp::path("version"))
+            .and(warp::path::end())
+            .and_then(move || match clone.version() {
+                Ok(version) => Ok(warp::reply::json(&version)),
+                Err(error) => {
+                    error!("Could not determine the version of the backend: {}", error);
+                    Err(warp::reject::custom(error))
+                }
+            });
+
+        let clone = self.clone();
+        let metrics = warp::get2()
+            .and(warp::path("metrics"))
+            .and(warp::path::end())
+            .map(move || {
+                let mut response = warp::http::Response::new(clone.metrics.render(clone.load()));
+                response.headers_mut().insert(
+                    warp::http::header::CONTENT_TYPE,
+                    warp::http::HeaderValue::from_static("text/plain; version=0.0.4"),
+                );
+                response
+            });
+
+        json_verify
+            .or(bincode_verify)
+            .or(json_verify_with_progress)
+            .or(submit_job)
+            .or(list_jobs)
+            .or(job_status)
+            .or(cancel_job)
+            .or(health)
+            .or(version)
+            .or(metrics)
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.backend.is_healthy()
+    }
+
+    fn version(&self) -> io::Result<VersionInfo> {
+        let backend = self.backend.version()?;
+        Ok(VersionInfo {
+            server_version: env!("CARGO_PKG_VERSION").to_string(),
+            protocol_version: PROTOCOL_VERSION,
+            request_schema: request_schema_fingerprint(),
+            backend,
+        })
+    }
+
+    fn load(&self) -> ServerLoad {
+        ServerLoad {
+            cached_results: self.cache.len(),
+            verifiers: self.backend.num_verifiers(),
+            queued_jobs: self.jobs.num_queued(),
+        }
+    }
+
+    fn verify(&self, request: VerificationRequest) -> VerificationResponse {
+        self.verify_with_progress(request, &|_| {})
     }
 
-    fn verify(&self, request: VerificationRequest) -> RemoteVerificationResult {
+    fn verify_with_progress(
+        &self,
+        request: VerificationRequest,
+        on_progress: &dyn Fn(VerificationProgress),
+    ) -> VerificationResponse {
+        self.verify_cancellable(request, on_progress, &Cancellation::default())
+    }
+
+    /// Verify the request, or take its result from the cache of the server.
+    fn verify_cancellable(
+        &self,
+        request: VerificationRequest,
+        on_progress: &dyn Fn(VerificationProgress),
+        cancellation: &Cancellation,
+    ) -> VerificationResponse {
         info!("Handling verification request for {}", request.program_name);
-        self.server.run_verifier(request)
+        self.metrics.record_request();
+        let key = CacheKey::of(&request);
+        if let Some(result) = self.cache.get(key.hash) {
+            info!("Using the cached result of {}", request.program_name);
+            let result = Ok(key.restore_result(result));
+            self.metrics.record_cache_hit(VerificationOutcome::of(&result));
+            on_progress(VerificationProgress::Started {
+                program_name: request.program_name.clone(),
+            });
+            on_progress(VerificationProgress::Finished {
+                program_name: request.program_name,
+                outcome: VerificationOutcome::of(&result),
+                duration: Duration::default(),
+            });
+            return VerificationResponse {
+                result,
+                cached: true,
+            };
+        }
+        self.metrics.record_verification_start();
+        let start_time = Instant::now();
+        let result = self.backend.verify(request, on_progress, cancellation);
+        self.metrics
+            .record_verification_end(VerificationOutcome::of(&result), start_time.elapsed());
+        if let Ok(result) = &result {
+            self.cache.insert(key.hash, &key.normalize_result(result));
+        }
+        VerificationResponse {
+            result,
+            cached: false,
+        }
     }
 }
 
+/// The response of the `/health` endpoint of a server.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Health {
+    /// Whether the server has verifiers to handle requests.
+    pub healthy: bool,
+}
+
+/// The response of the server to a verification request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationResponse {
+    pub result: RemoteVerificationResult,
+    /// Whether the result has been taken from the cache of the server.
+    pub cached: bool,
+}
+
+/// A message sent by the server in response to a verification request with progress.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ServerMessage {
+    Progress(VerificationProgress),
+    /// The response to the request, which is always the last message.
+    Response(VerificationResponse),
+    /// The request could not be decoded. This is only sent on stdio, because HTTP requests
+    /// are rejected instead.
+    InvalidRequest(String),
+}
+
+/// The reason why a request to a server failed.
+#[derive(Debug)]
+pub enum ServerError {
+    /// The address of the server is not a valid URL.
+    InvalidAddress(UrlError),
+    /// The server could not be reached.
+    Unreachable(reqwest::Error),
+    /// The server uses a different protocol or request schema than this client, e.g. because it
+    /// was built from a different version of Prusti.
+    Incompatible(String),
+    /// The server could not handle the request.
+    Request(reqwest::Error),
+    /// The server sent a response that could not be decoded.
+    InvalidResponse(String),
+    /// The connection to the server was lost before it sent its response.
+    Disconnected,
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ServerError::InvalidAddress(error) => write!(f, "invalid server address: {}", error),
+            ServerError::Unreachable(error) => write!(f, "the server is unreachable: {}", error),
+            ServerError::Incompatible(reason) => {
+                write!(f, "the server is incompatible with this client: {}", reason)
+            }
+            ServerError::Request(error) => write!(f, "the request to the server failed: {}", error),
+            ServerError::InvalidResponse(error) => {
+                write!(f, "the response of the server could not be decoded: {}", error)
+            }
+            ServerError::Disconnected => {
+                write!(f, "the server closed the connection before sending its response")
+            }
+        }
+    }
+}
+
+impl error::Error for ServerError {}
+
+impl From<UrlError> for ServerError {
+    fn from(error: UrlError) -> Self {
+        ServerError::InvalidAddress(error)
+    }
+}
+
+impl From<reqwest::Error> for ServerError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_serialization() {
+            ServerError::InvalidResponse(error.to_string())
+        } else {
+            ServerError::Request(error)
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct PrustiServerConnection {
     client: Client,
     server_url: Url,
 }
 
 impl PrustiServerConnection {
-    pub fn new<S: ToString>(server_address: S) -> Result<Self, UrlError> {
+    /// Create a connection to the server at the given address, without checking that the server
+    /// is reachable and compatible. See `connect`.
+    pub fn new<S: ToString>(server_address: S) -> Result<Self, ServerError> {
         let mut address = server_address.to_string();
         if !address.starts_with("http") {
             address = format!("http://{}", address);
@@ -146,10 +615,40 @@ impl PrustiServerConnection {
         })
     }
 
+    /// Connect to the server at the given address, checking that it uses the same protocol and
+    /// the same request schema as this client.
+    pub fn connect<S: ToString>(server_address: S) -> Result<Self, ServerError> {
+        let connection = Self::new(server_address)?;
+        let version = connection.version().map_err(|error| match error {
+            ServerError::Request(error) => match error.status() {
+                // servers older than the handshake have no `/version` endpoint
+                Some(status) => ServerError::Incompatible(format!(
+                    "the server does not report its version ({})",
+                    status
+                )),
+                None => ServerError::Unreachable(error),
+            },
+            error => error,
+        })?;
+        if version.protocol_version != PROTOCOL_VERSION {
+            return Err(ServerError::Incompatible(format!(
+                "the server uses protocol version {}, but the client uses version {}",
+                version.protocol_version, PROTOCOL_VERSION
+            )));
+        }
+        if version.request_schema != request_schema_fingerprint() {
+            return Err(ServerError::Incompatible(format!(
+                "the server (version {}) expects requests with a different schema",
+                version.server_version
+            )));
+        }
+        Ok(connection)
+    }
+
     pub fn verify_checked(
         &self,
         request: VerificationRequest,
-    ) -> reqwest::Result<RemoteVerificationResult> {
+    ) -> Result<VerificationResponse, ServerError> {
         let use_json = config::json_communication();
         let base = self.client.post(
             self.server_url
@@ -165,17 +664,201 @@ impl PrustiServerConnection {
                 .body(bincode::serialize(&request).expect("error encoding verification request"))
                 .send()?
                 .error_for_status()?;
-            bincode::deserialize_from(raw).expect("error decoding verification result")
+            bincode::deserialize_from(raw)
+                .map_err(|error| ServerError::InvalidResponse(error.to_string()))?
         };
         Ok(response)
     }
+
+    /// Like `verify_checked`, but reports the progress of the verification while it runs.
+    /// The messages of the server are always encoded as JSON lines.
+    pub fn verify_with_progress(
+        &self,
+        request: VerificationRequest,
+        on_progress: &dyn Fn(VerificationProgress),
+    ) -> Result<VerificationResponse, ServerError> {
+        let response = self.client
+            .post(self.server_url.join("json/verify_with_progress/").unwrap())
+            .json(&request)
+            .send()?
+            .error_for_status()?;
+        for line in BufReader::new(response).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(error) => {
+                    error!("Error reading the messages of the server: {}", error);
+                    break;
+                }
+            };
+            // the server keeps the connection alive with empty lines
+            if line.is_empty() {
+                continue;
+            }
+            let message = serde_json::from_str(&line)
+                .map_err(|error| ServerError::InvalidResponse(error.to_string()))?;
+            match message {
+                ServerMessage::Progress(progress) => on_progress(progress),
+                ServerMessage::Response(response) => return Ok(response),
+                ServerMessage::InvalidRequest(error) => {
+                    return Err(ServerError::InvalidResponse(format!(
+                        "the server rejected the request: {}",
+                        error
+                    )))
+                }
+            }
+        }
+        Err(ServerError::Disconnected)
+    }
+
+    /// Like `verify_with_progress`, but verifies the request as a job of the server, which is
+    /// cancelled when `cancellation` is cancelled or when this call is interrupted, e.g. by a
+    /// panic. The progress is polled, so it is reported with a delay of up to
+    /// `JOB_POLL_INTERVAL`.
+    ///
+    /// A server with worker processes stops the verification of a cancelled job by killing its
+    /// worker. A server that verifies on the threads of its own JVM cannot interrupt them, so it
+    /// only abandons the verification, which keeps running in the background until it finishes;
+    /// the next requests wait until then.
+    pub fn verify_cancellable(
+        &self,
+        request: VerificationRequest,
+        on_progress: &dyn Fn(VerificationProgress),
+        cancellation: &Cancellation,
+    ) -> Result<VerificationResponse, ServerError> {
+        let program_name = request.program_name.clone();
+        let mut job = SubmittedJob {
+            connection: self.clone(),
+            id: self.submit_job(request)?.id,
+            has_stopped: false,
+        };
+        let connection = self.clone();
+        let id = job.id;
+        cancellation.set_handler(Box::new(move || {
+            if let Err(error) = connection.cancel_job(id) {
+                warn!("Could not cancel job {} of the server: {}", id, error);
+            }
+        }));
+        let response = job.wait(program_name, on_progress);
+        cancellation.clear_handler();
+        response
+    }
+
+    /// Submit the request as a job, which the server verifies in the background.
+    pub fn submit_job(&self, request: VerificationRequest) -> Result<JobInfo, ServerError> {
+        Ok(self.client
+            .post(self.server_url.join("jobs").unwrap())
+            .json(&request)
+            .send()?
+            .error_for_status()?
+            .json()?)
+    }
+
+    /// The status of a job, with its response once it has finished.
+    pub fn job(&self, id: JobId) -> Result<JobInfo, ServerError> {
+        Ok(self.client
+            .get(self.job_url(id))
+            .send()?
+            .error_for_status()?
+            .json()?)
+    }
+
+    /// Cancel a job, stopping its verification if it is running.
+    pub fn cancel_job(&self, id: JobId) -> Result<JobInfo, ServerError> {
+        Ok(self.client
+            .delete(self.job_url(id))
+            .send()?
+            .error_for_status()?
+            .json()?)
+    }
+
+    /// The jobs known to the server, in the order of their submission.
+    pub fn jobs(&self) -> Result<Vec<JobInfo>, ServerError> {
+        Ok(self.client
+            .get(self.server_url.join("jobs").unwrap())
+            .send()?
+            .error_for_status()?
+            .json()?)
+    }
+
+    /// The versions of the server, its protocol and its backend.
+    pub fn version(&self) -> Result<VersionInfo, ServerError> {
+        Ok(self.client
+            .get(self.server_url.join("version").unwrap())
+            .send()?
+            .error_for_status()?
+            .json()?)
+    }
+
+    fn job_url(&self, id: JobId) -> Url {
+        self.server_url.join(&format!("jobs/{}", id)).unwrap()
+    }
 }
 
-impl VerificationService for PrustiServerConnection {
-    /// panics if the verification request fails
-    fn verify(&self, request: VerificationRequest) -> VerificationResult {
-        self.verify_checked(request)
-            .expect("Verification request to server failed!")
-            .expect("Server panicked while processing request!")
+/// How often a client polls the status of the job that verifies its request.
+const JOB_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How often the server sends an empty line to the clients that wait for the result of their
+/// request, to find out whether they have disconnected.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A job submitted by a client, which is cancelled when it is dropped before it has stopped.
+struct SubmittedJob {
+    connection: PrustiServerConnection,
+    id: JobId,
+    has_stopped: bool,
+}
+
+impl SubmittedJob {
+    /// Wait until the job stops, reporting when it starts and finishes.
+    fn wait(
+        &mut self,
+        program_name: String,
+        on_progress: &dyn Fn(VerificationProgress),
+    ) -> Result<VerificationResponse, ServerError> {
+        let mut start_time = None;
+        loop {
+            let response = match self.connection.job(self.id)?.status {
+                JobStatus::Queued => None,
+                JobStatus::Running => {
+                    if start_time.is_none() {
+                        start_time = Some(Instant::now());
+                        on_progress(VerificationProgress::Started {
+                            program_name: program_name.clone(),
+                        });
+                    }
+                    None
+                }
+                JobStatus::Finished(response) => Some(response),
+                JobStatus::Cancelled => Some(VerificationResponse {
+                    result: Err(VerifierError::Cancelled),
+                    cached: false,
+                }),
+            };
+            if let Some(response) = response {
+                self.has_stopped = true;
+                if start_time.is_none() {
+                    on_progress(VerificationProgress::Started {
+                        program_name: program_name.clone(),
+                    });
+                }
+                on_progress(VerificationProgress::Finished {
+                    program_name,
+                    outcome: VerificationOutcome::of(&response.result),
+                    duration: start_time.map(|time| time.elapsed()).unwrap_or_default(),
+                });
+                return Ok(response);
+            }
+            thread::sleep(JOB_POLL_INTERVAL);
+        }
+    }
+}
+
+impl Drop for SubmittedJob {
+    fn drop(&mut self) {
+        if !self.has_stopped {
+            if let Err(error) = self.connection.cancel_job(self.id) {
+                warn!("Could not cancel job {} of the server: {}", self.id, error);
+            }
+        }
     }
 }
@@ -0,0 +1,469 @@
+// © 2021, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use bincode;
+use serde_json;
+use prusti_common::verification_service::VerificationRequest;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryInto,
+    env, fs, io,
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::{Duration, UNIX_EPOCH},
+};
+use viper::VerificationResult;
+
+lazy_static! {
+    /// The versions of Prusti and Viper, which are part of the hash of each request so that the
+    /// cached results of another version are not used.
+    static ref VERSIONS: String = format!(
+        "prusti {} ({}), viper {}",
+        env!("CARGO_PKG_VERSION"),
+        env::current_exe()
+            .map(|path| file_version(&path))
+            .unwrap_or_else(|_| "unknown build".to_string()),
+        viper_version(),
+    );
+}
+
+/// The size and modification time of a file, which change whenever the file is rebuilt.
+fn file_version(path: &Path) -> String {
+    match fs::metadata(path) {
+        Ok(metadata) => {
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_nanos())
+                .unwrap_or_default();
+            format!("{} bytes, modified at {}", metadata.len(), modified)
+        }
+        Err(_) => "unknown".to_string(),
+    }
+}
+
+/// The names and versions of the Viper JAR files in `VIPER_HOME`.
+fn viper_version() -> String {
+    let viper_home = match env::var("VIPER_HOME") {
+        Ok(viper_home) => viper_home,
+        Err(_) => return "unknown".to_string(),
+    };
+    let mut jars: Vec<String> = fs::read_dir(&viper_home)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |extension| extension == "jar"))
+        .map(|path| format!("{} ({})", path.display(), file_version(&path)))
+        .collect();
+    jars.sort();
+    jars.join(", ")
+}
+
+/// The key of a verification request in the caches of verification results.
+///
+/// The positions of the program are normalised before it is hashed: their ids are renumbered in
+/// the order in which they appear and their lines and columns are dropped. Otherwise, the hash of
+/// a procedure would change whenever an unrelated edit moves it or changes how many positions are
+/// registered before it. Because of this, the results are cached with the normalised ids of
+/// their positions, which must be mapped back to the ids of the current program when a cached
+/// result is used.
+pub struct CacheKey {
+    /// The hash of the normalised program and backend configuration of the request, together
+    /// with the versions of Prusti and Viper. The name of the program is ignored, because it does
+    /// not influence the result. The hash is stable across runs and Rust releases, because it is
+    /// stored in persistent caches.
+    pub hash: u64,
+    /// The id of each position of the program, indexed by its normalised id minus one. The
+    /// normalised id of the default position is zero.
+    position_ids: Vec<u64>,
+}
+
+impl CacheKey {
+    pub fn of(request: &VerificationRequest) -> Self {
+        let mut value = serde_json::to_value(&(&request.program, &request.backend_config))
+            .expect("could not serialize the verification request");
+        let mut position_ids = vec![];
+        normalize_positions(&mut value, &mut HashMap::new(), &mut position_ids);
+        let bytes = serde_json::to_vec(&value)
+            .expect("could not serialize the verification request");
+        let mut hasher = Sha256::new();
+        hasher.update(VERSIONS.as_bytes());
+        hasher.update(&bytes);
+        let digest = hasher.finalize();
+        CacheKey {
+            hash: u64::from_le_bytes(digest[..8].try_into().unwrap()),
+            position_ids,
+        }
+    }
+
+    /// Replace the position ids of a result of the request by their normalised ids, so that the
+    /// result can be cached.
+    pub fn normalize_result(&self, result: &VerificationResult) -> VerificationResult {
+        let normalized_ids: HashMap<u64, u64> = self
+            .position_ids
+            .iter()
+            .enumerate()
+            .map(|(index, &id)| (id, index as u64 + 1))
+            .collect();
+        map_position_ids(result.clone(), |id| normalized_ids.get(&id).cloned())
+    }
+
+    /// Replace the normalised position ids of a cached result by the ids of the positions of
+    /// the request.
+    pub fn restore_result(&self, result: VerificationResult) -> VerificationResult {
+        map_position_ids(result, |id| match id {
+            0 => Some(0),
+            _ => self.position_ids.get(id as usize - 1).cloned(),
+        })
+    }
+}
+
+/// Whether the value is a serialized `vir::Position`.
+fn is_position(object: &serde_json::Map<String, serde_json::Value>) -> bool {
+    object.len() == 3
+        && ["line", "column", "id"]
+            .iter()
+            .all(|key| object.get(*key).map_or(false, |value| value.is_u64() || value.is_i64()))
+}
+
+/// Renumber the positions in a serialized program in the order in which they appear, starting
+/// from one, and drop their lines and columns. The default position keeps the id zero.
+fn normalize_positions(
+    value: &mut serde_json::Value,
+    normalized_ids: &mut HashMap<u64, u64>,
+    position_ids: &mut Vec<u64>,
+) {
+    match value {
+        serde_json::Value::Object(object) if is_position(object) => {
+            let id = object["id"].as_u64().unwrap_or_default();
+            let normalized_id = if id == 0 {
+                0
+            } else {
+                *normalized_ids.entry(id).or_insert_with(|| {
+                    position_ids.push(id);
+                    position_ids.len() as u64
+                })
+            };
+            object.insert("line".to_string(), 0.into());
+            object.insert("column".to_string(), 0.into());
+            object.insert("id".to_string(), normalized_id.into());
+        }
+        serde_json::Value::Object(object) => {
+            for field in object.values_mut() {
+                normalize_positions(field, normalized_ids, position_ids);
+            }
+        }
+        serde_json::Value::Array(elements) => {
+            for element in elements {
+                normalize_positions(element, normalized_ids, position_ids);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Map the position ids of the errors of a result. Ids that cannot be mapped are kept as they
+/// are.
+fn map_position_ids<F>(result: VerificationResult, map_id: F) -> VerificationResult
+where
+    F: Fn(u64) -> Option<u64>,
+{
+    let map_pos_id = |pos_id: Option<String>| {
+        pos_id.map(|pos_id| {
+            pos_id
+                .parse()
+                .ok()
+                .and_then(&map_id)
+                .map_or(pos_id, |id| id.to_string())
+        })
+    };
+    match result {
+        VerificationResult::Failure(errors) => VerificationResult::Failure(
+            errors
+                .into_iter()
+                .map(|mut error| {
+                    error.pos_id = map_pos_id(error.pos_id.take());
+                    error.reason_pos_id = map_pos_id(error.reason_pos_id.take());
+                    error
+                })
+                .collect(),
+        ),
+        result => result,
+    }
+}
+
+/// Only successes and verification failures can be cached, because the other results depend on
+/// the state of the verifier rather than on the program.
+fn is_cacheable(result: &VerificationResult) -> bool {
+    match result {
+        VerificationResult::Success() | VerificationResult::Failure(_) => true,
+        VerificationResult::ConsistencyErrors(_)
+        | VerificationResult::JavaException(_)
+        | VerificationResult::TimedOut => false,
+    }
+}
+
+/// A cache of verification results that is persisted to a file, so that unchanged procedures
+/// do not need to be verified again by the next run.
+pub struct PersistentCache {
+    path: PathBuf,
+    results: HashMap<u64, VerificationResult>,
+    new_results: HashMap<u64, VerificationResult>,
+}
+
+impl PersistentCache {
+    /// Load the cache from the given file. If the file does not exist or cannot be read, the
+    /// cache starts empty.
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let results = match Self::read_results(&path) {
+            Ok(results) => results,
+            Err(error) => {
+                info!("Could not load the verification cache from {:?}: {}", path, error);
+                HashMap::new()
+            }
+        };
+        PersistentCache {
+            path,
+            results,
+            new_results: HashMap::new(),
+        }
+    }
+
+    fn read_results(path: &Path) -> io::Result<HashMap<u64, VerificationResult>> {
+        let bytes = fs::read(path)?;
+        bincode::deserialize(&bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    pub fn get(&self, request_hash: u64) -> Option<VerificationResult> {
+        self.new_results
+            .get(&request_hash)
+            .or_else(|| self.results.get(&request_hash))
+            .cloned()
+    }
+
+    /// Store the result of a request, if it can be cached.
+    pub fn insert(&mut self, request_hash: u64, result: &VerificationResult) {
+        if is_cacheable(result) {
+            self.new_results.insert(request_hash, result.clone());
+        }
+    }
+
+    /// Write the new results to the cache file. The file is re-read first, so that the results
+    /// stored by concurrent runs (e.g. of other crates of the same workspace) are preserved.
+    pub fn save(&self) -> io::Result<()> {
+        if self.new_results.is_empty() {
+            return Ok(());
+        }
+        let mut results = Self::read_results(&self.path).unwrap_or_else(|_| self.results.clone());
+        results.extend(self.new_results.clone());
+        let bytes = bincode::serialize(&results)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write to a temporary file first, so that readers never observe a partial file.
+        let temp_path = self.path.with_extension(format!("{}.tmp", process::id()));
+        fs::write(&temp_path, bytes)?;
+        fs::rename(&temp_path, &self.path)
+    }
+}
+
+/// How long the changes of a `ResultCache` are collected before they are written to its file.
+const PERSIST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The results of a `ResultCache`, each with the time at which it was last used.
+#[derive(Default)]
+struct CacheEntries {
+    results: HashMap<u64, (Arc<VerificationResult>, u64)>,
+    /// The hash of the result last used at each time, ordered from the least recently used.
+    usages: BTreeMap<u64, u64>,
+    clock: u64,
+    /// Whether results have changed since the cache was last written to its file.
+    has_unsaved_changes: bool,
+    /// Whether the cache has been dropped, so that it is written one last time.
+    is_closed: bool,
+}
+
+impl CacheEntries {
+    /// Mark the result as the most recently used one.
+    fn touch(&mut self, request_hash: u64) -> Option<Arc<VerificationResult>> {
+        self.clock += 1;
+        let clock = self.clock;
+        let (result, last_use) = self.results.get_mut(&request_hash)?;
+        self.usages.remove(last_use);
+        *last_use = clock;
+        self.usages.insert(clock, request_hash);
+        Some(result.clone())
+    }
+
+    fn insert(&mut self, request_hash: u64, result: Arc<VerificationResult>) {
+        self.clock += 1;
+        let clock = self.clock;
+        if let Some((_, last_use)) = self.results.insert(request_hash, (result, clock)) {
+            self.usages.remove(&last_use);
+        }
+        self.usages.insert(clock, request_hash);
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let least_recently_used = self.usages.keys().next().cloned();
+        if let Some(last_use) = least_recently_used {
+            let request_hash = self.usages.remove(&last_use).unwrap();
+            self.results.remove(&request_hash);
+        }
+    }
+
+    /// The results, from the least to the most recently used one.
+    fn snapshot(&self) -> Vec<(u64, Arc<VerificationResult>)> {
+        self.usages
+            .values()
+            .map(|request_hash| (*request_hash, self.results[request_hash].0.clone()))
+            .collect()
+    }
+}
+
+/// A cache of verification results shared by the clients of a server. When the cache is full,
+/// the least recently used result is evicted. If a path is given, the cache is loaded from that
+/// file, and its changes are written to it in batches by a background thread, so that it
+/// survives restarts of the server.
+pub struct ResultCache {
+    capacity: usize,
+    entries: Arc<(Mutex<CacheEntries>, Condvar)>,
+    persister: Option<thread::JoinHandle<()>>,
+}
+
+impl ResultCache {
+    pub fn new(capacity: usize, path: Option<PathBuf>) -> Self {
+        let mut entries = CacheEntries::default();
+        if let Some(path) = &path {
+            match Self::read_results(path) {
+                Ok(stored_results) => {
+                    // keep the most recently used results
+                    let skipped = stored_results.len().saturating_sub(capacity);
+                    for (hash, result) in stored_results.into_iter().skip(skipped) {
+                        entries.insert(hash, Arc::new(result));
+                    }
+                    info!(
+                        "Loaded {} cached verification results from {:?}",
+                        entries.results.len(),
+                        path
+                    );
+                }
+                Err(error) => {
+                    info!("Could not load the verification cache from {:?}: {}", path, error);
+                }
+            }
+        }
+        let entries = Arc::new((Mutex::new(entries), Condvar::new()));
+        let persister = path.map(|path| {
+            let entries = entries.clone();
+            thread::spawn(move || Self::persist(&path, &entries))
+        });
+        ResultCache {
+            capacity,
+            entries,
+            persister,
+        }
+    }
+
+    /// Read the results stored by `persist`, from the least to the most recently used one.
+    fn read_results(path: &Path) -> io::Result<Vec<(u64, VerificationResult)>> {
+        let bytes = fs::read(path)?;
+        bincode::deserialize(&bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.0.lock().unwrap().results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, request_hash: u64) -> Option<VerificationResult> {
+        let mut entries = self.entries.0.lock().unwrap();
+        let result = entries.touch(request_hash)?;
+        // the order of use is stored too
+        entries.has_unsaved_changes = true;
+        self.entries.1.notify_one();
+        Some((*result).clone())
+    }
+
+    /// Store the result of a request, if it can be cached, evicting the least recently used
+    /// result if the cache is full.
+    pub fn insert(&self, request_hash: u64, result: &VerificationResult) {
+        if self.capacity == 0 || !is_cacheable(result) {
+            return;
+        }
+        let result = Arc::new(result.clone());
+        let mut entries = self.entries.0.lock().unwrap();
+        entries.insert(request_hash, result);
+        if entries.results.len() > self.capacity {
+            entries.evict_least_recently_used();
+        }
+        entries.has_unsaved_changes = true;
+        self.entries.1.notify_one();
+    }
+
+    /// Write the changes of the cache to `path` until the cache is dropped. The changes made
+    /// during `PERSIST_INTERVAL` are written at once, and the results are serialized and written
+    /// without holding the lock of the cache.
+    fn persist(path: &Path, entries: &(Mutex<CacheEntries>, Condvar)) {
+        let (entries_mutex, changed) = entries;
+        let mut entries = entries_mutex.lock().unwrap();
+        loop {
+            while !entries.has_unsaved_changes && !entries.is_closed {
+                entries = changed.wait(entries).unwrap();
+            }
+            if !entries.has_unsaved_changes {
+                return;
+            }
+            if !entries.is_closed {
+                drop(entries);
+                thread::sleep(PERSIST_INTERVAL);
+                entries = entries_mutex.lock().unwrap();
+            }
+            let snapshot = entries.snapshot();
+            entries.has_unsaved_changes = false;
+            drop(entries);
+            if let Err(error) = Self::save(path, &snapshot) {
+                error!("Could not store the verification cache in {:?}: {}", path, error);
+            }
+            entries = entries_mutex.lock().unwrap();
+        }
+    }
+
+    fn save(path: &Path, results: &[(u64, Arc<VerificationResult>)]) -> io::Result<()> {
+        let results: Vec<(u64, &VerificationResult)> = results.iter()
+            .map(|(hash, result)| (*hash, &**result))
+            .collect();
+        let bytes = bincode::serialize(&results)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temp_path = path.with_extension(format!("{}.tmp", process::id()));
+        fs::write(&temp_path, bytes)?;
+        fs::rename(&temp_path, path)
+    }
+}
+
+impl Drop for ResultCache {
+    /// Write the last changes of the cache to its file.
+    fn drop(&mut self) {
+        if let Some(persister) = self.persister.take() {
+            self.entries.0.lock().unwrap().is_closed = true;
+            self.entries.1.notify_one();
+            let _ = persister.join();
+        }
+    }
+}
@@ -12,6 +12,7 @@ use prusti_common::{
     vir::{Program, ToViper},
     Stopwatch,
 };
+use super::BackendVersion;
 use viper::{self, VerificationResult};
 
 pub struct VerifierRunner<'v> {
@@ -55,6 +56,13 @@ impl<'v> VerifierRunner<'v> {
         }
     }
 
+    pub fn backend_version(&self) -> BackendVersion {
+        BackendVersion {
+            name: self.verifier.name(),
+            build_version: self.verifier.build_version(),
+        }
+    }
+
     pub fn verify(&self, program: Program, program_name: &str) -> VerificationResult {
         let mut stopwatch = Stopwatch::start("prusti-server", "construction of JVM objects");
         let viper_program = program.to_viper(&self.ast_factory);
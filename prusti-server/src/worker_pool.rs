@@ -0,0 +1,323 @@
+// © 2021, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{
+    BackendVersion, Cancellation, PrustiServerConnection, RemoteVerificationResult,
+    VerificationProgress, VerifierError, BACKEND_TIMEOUT_GRACE_PERIOD,
+};
+use prusti_common::verification_service::VerificationRequest;
+use std::{
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Condvar, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+/// How long to wait for a worker process to start its JVM and report its port.
+const WORKER_STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A child process running a Prusti server, with its own JVM.
+struct Worker {
+    /// Shared with the cancellation handler of the request that the worker is verifying.
+    process: Arc<Mutex<Child>>,
+    id: u32,
+    connection: PrustiServerConnection,
+}
+
+impl Worker {
+    /// Start a server process on a port assigned by the system, and connect to it.
+    fn spawn(executable: &Path) -> io::Result<Self> {
+        let mut process = Command::new(executable)
+            .arg("--port")
+            .arg("0")
+            .arg("--exit-on-stdin-eof")
+            // the worker verifies the requests on its own JVM
+            .env("PRUSTI_SERVER_WORKER_PROCESSES", "false")
+            // the results are cached by this server
+            .env("PRUSTI_SERVER_CACHE_SIZE", "0")
+            // the worker exits when its stdin is closed, i.e. when this process exits
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdout = process.stdout.take().unwrap();
+        let (port_sender, port_receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut lines = BufReader::new(stdout).lines();
+            // the server reports its port on stdout once it is listening
+            for line in &mut lines {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => return,
+                };
+                if let Some(port) = line.strip_prefix("port: ") {
+                    let _ = port_sender.send(port.parse::<u16>().ok());
+                    break;
+                }
+            }
+            // keep reading the output, so that the worker does not block on a full pipe
+            for line in lines {
+                match line {
+                    Ok(line) => debug!("Worker output: {}", line),
+                    Err(_) => break,
+                }
+            }
+        });
+
+        let error = match port_receiver.recv_timeout(WORKER_STARTUP_TIMEOUT) {
+            Ok(Some(port)) => {
+                info!("Started worker process {} on port {}", process.id(), port);
+                let connection = PrustiServerConnection::new(format!("127.0.0.1:{}", port))
+                    .expect("invalid worker address");
+                return Ok(Worker {
+                    id: process.id(),
+                    process: Arc::new(Mutex::new(process)),
+                    connection,
+                });
+            }
+            Ok(None) | Err(mpsc::RecvTimeoutError::Disconnected) => {
+                "the worker process did not report its port".to_string()
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => format!(
+                "the worker process did not start within {} seconds",
+                WORKER_STARTUP_TIMEOUT.as_secs()
+            ),
+        };
+        let _ = process.kill();
+        let _ = process.wait();
+        Err(io::Error::new(io::ErrorKind::Other, error))
+    }
+
+    fn has_exited(&self) -> bool {
+        !matches!(self.process.lock().unwrap().try_wait(), Ok(None))
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        let mut process = self.process.lock().unwrap();
+        let _ = process.kill();
+        let _ = process.wait();
+    }
+}
+
+struct PoolState {
+    idle_workers: Vec<Worker>,
+    /// The number of workers, either starting, idle or verifying a request.
+    num_workers: usize,
+    /// The version of the backend, as reported by the first worker that started.
+    backend_version: Option<BackendVersion>,
+    /// Whether the last worker that was started failed to start.
+    start_failed: bool,
+}
+
+/// A pool of worker processes that verify requests concurrently, one request per worker at a
+/// time. The workers are started when requests need them, up to the size of the pool. Workers
+/// that crash, or that are still busy with a request that timed out, are killed and replaced by
+/// new ones when needed.
+pub struct WorkerPool {
+    executable: PathBuf,
+    max_workers: usize,
+    state: Mutex<PoolState>,
+    worker_returned: Condvar,
+}
+
+impl WorkerPool {
+    /// Create a pool of up to `max_workers` worker processes running the server executable
+    /// `executable`. No worker is started until a request is verified.
+    pub fn new(executable: PathBuf, max_workers: usize) -> Self {
+        WorkerPool {
+            executable,
+            max_workers,
+            state: Mutex::new(PoolState {
+                idle_workers: vec![],
+                num_workers: 0,
+                backend_version: None,
+                start_failed: false,
+            }),
+            worker_returned: Condvar::new(),
+        }
+    }
+
+    /// The number of workers, either starting, idle or verifying a request.
+    pub fn num_workers(&self) -> usize {
+        self.state.lock().unwrap().num_workers
+    }
+
+    /// Whether the pool can start workers, i.e. the last worker that was started did not fail.
+    pub fn can_start_workers(&self) -> bool {
+        !self.state.lock().unwrap().start_failed
+    }
+
+    /// The version of the backend of the workers, as reported by the first worker that started.
+    /// Until then, the build version of the backend is unknown.
+    pub fn backend_version(&self) -> BackendVersion {
+        self.state.lock().unwrap().backend_version.clone().unwrap_or_else(|| BackendVersion {
+            name: "viper".to_string(),
+            build_version: "unknown until a worker process has started".to_string(),
+        })
+    }
+
+    pub fn run_verifier(&self, request: VerificationRequest) -> RemoteVerificationResult {
+        self.run_verifier_with_progress(request, &|_| {})
+    }
+
+    /// Verify the request on the next idle worker. If all of them are busy, a new worker is
+    /// started, or the request waits for a worker if the pool is full.
+    pub fn run_verifier_with_progress(
+        &self,
+        request: VerificationRequest,
+        on_progress: &dyn Fn(VerificationProgress),
+    ) -> RemoteVerificationResult {
+        self.run_cancellable_verifier(request, on_progress, &Cancellation::default())
+    }
+
+    /// Like `run_verifier_with_progress`, but kills the worker when the request is cancelled.
+    /// The worker is also killed when it does not report a result soon after the timeout of the
+    /// request, e.g. because its backend cannot stop the verification.
+    pub fn run_cancellable_verifier(
+        &self,
+        request: VerificationRequest,
+        on_progress: &dyn Fn(VerificationProgress),
+        cancellation: &Cancellation,
+    ) -> RemoteVerificationResult {
+        let worker = match self.take_idle_worker() {
+            Some(worker) => worker,
+            None => {
+                error!("No worker process could be started to verify {}", request.program_name);
+                return Err(VerifierError::Panicked);
+            }
+        };
+
+        let program_name = request.program_name.clone();
+        let timeout = request.timeout;
+        let process = worker.process.clone();
+        cancellation.set_handler(Box::new(move || {
+            let _ = process.lock().unwrap().kill();
+        }));
+        // the sender is dropped when the worker has reported the result
+        let (finished_sender, finished_receiver) = mpsc::channel::<()>();
+        let killed_after_timeout = Arc::new(AtomicBool::new(false));
+        if let Some(timeout) = timeout {
+            let process = worker.process.clone();
+            let killed_after_timeout = killed_after_timeout.clone();
+            // the worker reports its own timeout after one grace period
+            let deadline = Duration::from_secs(timeout) + 2 * BACKEND_TIMEOUT_GRACE_PERIOD;
+            thread::spawn(move || {
+                if let Err(mpsc::RecvTimeoutError::Timeout) =
+                    finished_receiver.recv_timeout(deadline)
+                {
+                    killed_after_timeout.store(true, Ordering::SeqCst);
+                    let _ = process.lock().unwrap().kill();
+                }
+            });
+        }
+        let result = worker
+            .connection
+            .verify_with_progress(request, on_progress)
+            .map(|response| response.result)
+            .unwrap_or_else(|error| {
+                error!("Request {} to worker process failed: {}", program_name, error);
+                Err(VerifierError::Panicked)
+            });
+        drop(finished_sender);
+        cancellation.clear_handler();
+        let result = if cancellation.is_cancelled() {
+            info!("Verification request {} was cancelled", program_name);
+            Err(VerifierError::Cancelled)
+        } else if killed_after_timeout.load(Ordering::SeqCst) {
+            let timeout = timeout.unwrap();
+            warn!(
+                "Killed the worker process {} verifying {}, which timed out after {} seconds",
+                worker.id, program_name, timeout
+            );
+            Err(VerifierError::TimedOut(timeout))
+        } else {
+            result
+        };
+
+        // a worker whose request timed out is still busy verifying it
+        if worker.has_exited() || result.is_err() {
+            warn!("Stopping worker process {}", worker.id);
+            self.retire_worker(worker);
+        } else {
+            self.return_worker(worker);
+        }
+        result
+    }
+
+    /// Take an idle worker, or start a new one if all of them are busy and the pool is not full.
+    /// Otherwise, wait until a worker is returned.
+    fn take_idle_worker(&self) -> Option<Worker> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(worker) = state.idle_workers.pop() {
+                if !worker.has_exited() {
+                    return Some(worker);
+                }
+                warn!("Worker process {} exited while idle", worker.id);
+                state.num_workers -= 1;
+                continue;
+            }
+            if state.num_workers < self.max_workers {
+                state.num_workers += 1;
+                // the other requests are not blocked while the JVM of the new worker starts
+                drop(state);
+                return self.start_worker();
+            }
+            state = self.worker_returned.wait(state).unwrap();
+        }
+    }
+
+    /// Start a worker that has already been counted in the number of workers of the pool.
+    fn start_worker(&self) -> Option<Worker> {
+        let worker = Worker::spawn(&self.executable);
+        let needs_backend_version = self.state.lock().unwrap().backend_version.is_none();
+        let backend_version = match &worker {
+            Ok(worker) if needs_backend_version => {
+                match worker.connection.version() {
+                    Ok(version) => Some(version.backend),
+                    Err(error) => {
+                        warn!("Could not get the version of the worker backend: {}", error);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        let mut state = self.state.lock().unwrap();
+        state.start_failed = worker.is_err();
+        if backend_version.is_some() && state.backend_version.is_none() {
+            state.backend_version = backend_version;
+        }
+        match worker {
+            Ok(worker) => Some(worker),
+            Err(error) => {
+                error!("Could not start worker process: {}", error);
+                state.num_workers -= 1;
+                // another request may succeed in starting the worker
+                self.worker_returned.notify_one();
+                None
+            }
+        }
+    }
+
+    /// Kill a worker that is not in the pool of idle workers, making room for a new one.
+    fn retire_worker(&self, worker: Worker) {
+        drop(worker);
+        self.state.lock().unwrap().num_workers -= 1;
+        self.worker_returned.notify_one();
+    }
+
+    fn return_worker(&self, worker: Worker) {
+        self.state.lock().unwrap().idle_workers.push(worker);
+        self.worker_returned.notify_one();
+    }
+}
@@ -1,19 +1,26 @@
 extern crate prusti_common;
 extern crate prusti_server;
+extern crate reqwest;
 extern crate viper;
 #[macro_use]
 extern crate lazy_static;
 
-use prusti_common::{
-    verification_service::{VerificationRequest, VerificationService},
-    vir::*,
+mod common;
+
+use common::dummy_request;
+use prusti_common::vir::*;
+use prusti_server::{
+    JobStatus, PrustiServerConnection, ServerError, ServerSideService, VerificationOutcome,
+    VerificationProgress, PROTOCOL_VERSION,
 };
-use prusti_server::{PrustiServerConnection, ServerSideService};
+use std::{sync::Mutex, thread, time::Duration};
 use viper::VerificationResult;
 
 lazy_static! {
     // only start the jvm & server once
-    static ref SERVER_ADDRESS: String = ServerSideService::spawn_off_thread().to_string();
+    static ref SERVER_ADDRESS: String = ServerSideService::spawn_off_thread()
+        .expect("could not start the server")
+        .to_string();
 }
 
 #[test]
@@ -47,28 +54,127 @@ fn empty_program() {
     }
 }
 
-fn process_program<F>(configure: F) -> VerificationResult
-where
-    F: FnOnce(&mut Program),
-{
+#[test]
+fn progress() {
     let service =
         PrustiServerConnection::new(SERVER_ADDRESS.clone()).expect("Could not connect to server!");
+    let events = Mutex::new(vec![]);
+    let response = service
+        .verify_with_progress(dummy_request("dummy"), &|progress| {
+            events.lock().unwrap().push(progress)
+        })
+        .expect("Verification request to server failed!");
 
-    let mut program = Program {
-        domains: vec![],
-        fields: vec![],
-        builtin_methods: vec![],
-        methods: vec![],
-        functions: vec![],
-        viper_predicates: vec![],
+    assert!(matches!(response.result, Ok(VerificationResult::Success())));
+    match &events.into_inner().unwrap()[..] {
+        [VerificationProgress::Started { .. }, VerificationProgress::Finished { outcome, .. }] => {
+            assert_eq!(*outcome, VerificationOutcome::Verified)
+        }
+        other => panic!("unexpected progress {:?}", other),
+    }
+}
+
+#[test]
+fn cached_result() {
+    let service =
+        PrustiServerConnection::new(SERVER_ADDRESS.clone()).expect("Could not connect to server!");
+    let request = || {
+        let mut request = dummy_request("dummy");
+        request.program.fields.push(Field {
+            name: "cached_field".to_string(),
+            typ: Type::Int,
+        });
+        request
     };
-    configure(&mut program);
 
-    let request = VerificationRequest {
-        program,
-        program_name: "dummy".to_string(),
-        backend_config: Default::default(),
+    let first = service
+        .verify_checked(request())
+        .expect("Verification request to server failed!");
+    let second = service
+        .verify_checked(request())
+        .expect("Verification request to server failed!");
+
+    assert!(!first.cached);
+    assert!(second.cached);
+    assert!(matches!(second.result, Ok(VerificationResult::Success())));
+}
+
+#[test]
+fn job() {
+    let service =
+        PrustiServerConnection::new(SERVER_ADDRESS.clone()).expect("Could not connect to server!");
+    let job = service
+        .submit_job(dummy_request("dummy"))
+        .expect("Job submission to server failed!");
+    assert!(service
+        .jobs()
+        .expect("Job listing failed!")
+        .iter()
+        .any(|other| other.id == job.id));
+
+    let response = loop {
+        match service.job(job.id).expect("Job status request failed!").status {
+            JobStatus::Finished(response) => break response,
+            JobStatus::Queued | JobStatus::Running => thread::sleep(Duration::from_millis(100)),
+            JobStatus::Cancelled => panic!("job was cancelled"),
+        }
     };
+    assert!(matches!(response.result, Ok(VerificationResult::Success())));
+
+    // cancelling a finished job has no effect
+    let cancelled = service.cancel_job(job.id).expect("Job cancellation failed!");
+    assert!(matches!(cancelled.status, JobStatus::Finished(_)));
+    assert!(service.job(job.id + 1000).is_err());
+}
+
+#[test]
+fn version() {
+    let service =
+        PrustiServerConnection::new(SERVER_ADDRESS.clone()).expect("Could not connect to server!");
+    let version = service.version().expect("Version request to server failed!");
+
+    assert_eq!(version.protocol_version, PROTOCOL_VERSION);
+    assert!(!version.backend.build_version.is_empty());
+}
+
+#[test]
+fn handshake() {
+    PrustiServerConnection::connect(SERVER_ADDRESS.clone())
+        .expect("Handshake with server failed!");
+
+    // nothing listens on port 1
+    match PrustiServerConnection::connect("127.0.0.1:1") {
+        Err(ServerError::Unreachable(_)) => (),
+        other => panic!("unreachable server not identified, instead found {:?}", other.err()),
+    }
+}
+
+#[test]
+fn health_and_metrics() {
+    let url = format!("http://{}/", *SERVER_ADDRESS);
+    let health = reqwest::get(&format!("{}health", url)).expect("Health request failed!");
+    assert!(health.status().is_success());
+
+    process_program(|_| ());
+    let metrics = reqwest::get(&format!("{}metrics", url))
+        .and_then(|mut response| response.text())
+        .expect("Metrics request failed!");
+    assert!(metrics.contains("# TYPE prusti_server_requests_total counter"));
+    assert!(metrics.contains("prusti_server_results_total{outcome=\"verified\"}"));
+}
+
+fn process_program<F>(configure: F) -> VerificationResult
+where
+    F: FnOnce(&mut Program),
+{
+    let service =
+        PrustiServerConnection::new(SERVER_ADDRESS.clone()).expect("Could not connect to server!");
 
-    service.verify(request)
+    let mut request = dummy_request("dummy");
+    configure(&mut request.program);
+    service
+        .verify_checked(request)
+        .expect("Verification request to server failed!")
+        .result
+        .expect("Server panicked while processing request!")
 }
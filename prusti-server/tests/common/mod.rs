@@ -0,0 +1,18 @@
+use prusti_common::{verification_service::VerificationRequest, vir::Program};
+
+/// A request to verify an empty program with the given name.
+pub fn dummy_request(program_name: &str) -> VerificationRequest {
+    VerificationRequest {
+        program: Program {
+            domains: vec![],
+            fields: vec![],
+            builtin_methods: vec![],
+            methods: vec![],
+            functions: vec![],
+            viper_predicates: vec![],
+        },
+        program_name: program_name.to_string(),
+        backend_config: Default::default(),
+        timeout: None,
+    }
+}
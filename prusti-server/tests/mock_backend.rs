@@ -0,0 +1,76 @@
+extern crate prusti_common;
+extern crate prusti_server;
+extern crate serde_json;
+extern crate viper;
+
+mod common;
+
+use common::dummy_request;
+use prusti_server::{
+    create_backend, register_backend, Backend, Cancellation, MockBackend,
+    RemoteVerificationResult, VerificationProgress, VerifierError,
+};
+use std::{
+    collections::HashMap,
+    env, fs, process,
+    sync::{Arc, Mutex},
+};
+use viper::{VerificationError, VerificationResult};
+
+#[test]
+fn recorded_results() {
+    let failure: RemoteVerificationResult = Ok(VerificationResult::Failure(vec![
+        VerificationError::new(
+            "assert.failed:assertion.false".to_string(),
+            Some("42".to_string()),
+            None,
+            "Assert might fail. Assertion false might not hold.".to_string(),
+            None,
+        ),
+    ]));
+    let mut results = HashMap::new();
+    results.insert("failing".to_string(), failure.clone());
+    results.insert("timing_out".to_string(), Err(VerifierError::TimedOut(10)));
+    let path = env::temp_dir().join(format!("prusti-mock-results-{}.json", process::id()));
+    fs::write(&path, serde_json::to_vec(&results).unwrap()).unwrap();
+    let backend = MockBackend::from_file(&path).expect("Could not read the mock results!");
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(verify(&backend, "failing"), failure);
+    assert_eq!(verify(&backend, "timing_out"), Err(VerifierError::TimedOut(10)));
+    assert_eq!(verify(&backend, "other"), Ok(VerificationResult::Success()));
+    assert_eq!(backend.version().unwrap().name, "mock");
+}
+
+#[test]
+fn progress() {
+    let backend = MockBackend::new(HashMap::new());
+    let events = Mutex::new(vec![]);
+    backend.verify(
+        dummy_request("dummy"),
+        &|progress| events.lock().unwrap().push(progress),
+        &Cancellation::default(),
+    );
+    let events = events.into_inner().unwrap();
+    assert_eq!(events.len(), 2);
+    assert!(matches!(events[0], VerificationProgress::Started { .. }));
+    assert!(matches!(events[1], VerificationProgress::Finished { .. }));
+}
+
+#[test]
+fn registered_backend() {
+    register_backend("always-panicking", |_| {
+        let mut results = HashMap::new();
+        results.insert("dummy".to_string(), Err(VerifierError::Panicked));
+        Ok(Arc::new(MockBackend::new(results)) as Arc<dyn Backend>)
+    });
+    let backend = create_backend("Always-Panicking", 1).expect("Backend was not registered!");
+    assert_eq!(verify(&*backend, "dummy"), Err(VerifierError::Panicked));
+
+    let error = create_backend("unknown", 1).err().expect("Unknown backend was created!");
+    assert!(error.to_string().contains("always-panicking"));
+}
+
+fn verify(backend: &dyn Backend, program_name: &str) -> RemoteVerificationResult {
+    backend.verify(dummy_request(program_name), &|_| {}, &Cancellation::default())
+}
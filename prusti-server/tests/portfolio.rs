@@ -0,0 +1,191 @@
+extern crate prusti_common;
+extern crate prusti_server;
+extern crate viper;
+
+mod common;
+
+use common::dummy_request;
+use prusti_common::verification_service::VerificationRequest;
+use prusti_server::{
+    verify_with_backends, BackendMode, Cancellation, RemoteVerificationResult,
+    VerificationProgress, VerifierError,
+};
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+use viper::{VerificationBackend, VerificationError, VerificationResult};
+
+#[test]
+fn portfolio_takes_first_definitive_result() {
+    // Silicon only finishes after it has been cancelled, so Carbon gives the first result.
+    let verify = Arc::new(
+        |request: VerificationRequest,
+         _: &dyn Fn(VerificationProgress),
+         cancellation: &Cancellation| match request.backend_config.backend {
+            VerificationBackend::Silicon => {
+                let (sender, receiver) = mpsc::channel();
+                cancellation.set_handler(Box::new(move || sender.send(()).unwrap()));
+                receiver.recv_timeout(Duration::from_secs(60)).unwrap();
+                Err(VerifierError::Cancelled)
+            }
+            VerificationBackend::Carbon => Ok(VerificationResult::Success()),
+        },
+    );
+    let events = Mutex::new(vec![]);
+    let (result, disagreement) = verify_with_backends(
+        BackendMode::Portfolio,
+        dummy_request("dummy"),
+        &verify,
+        &|progress| events.lock().unwrap().push(progress),
+        true,
+    );
+    assert_eq!(result, Ok(VerificationResult::Success()));
+    assert!(disagreement.is_none());
+    assert_eq!(events.into_inner().unwrap().len(), 2);
+}
+
+#[test]
+fn sequential_portfolio_only_falls_back_without_definitive_result() {
+    let verified_backends = Arc::new(Mutex::new(vec![]));
+    let verify = {
+        let verified_backends = verified_backends.clone();
+        Arc::new(
+            move |request: VerificationRequest,
+                  _: &dyn Fn(VerificationProgress),
+                  _: &Cancellation| {
+                let backend = request.backend_config.backend;
+                verified_backends.lock().unwrap().push(backend);
+                match (request.program_name.as_str(), backend) {
+                    ("dummy", _) => Ok(VerificationResult::Success()),
+                    (_, VerificationBackend::Silicon) => Err(VerifierError::TimedOut(1)),
+                    (_, VerificationBackend::Carbon) => Ok(VerificationResult::Success()),
+                }
+            },
+        )
+    };
+
+    // Silicon gives a definitive result, so Carbon is not run.
+    let (result, _) = verify_with_backends(
+        BackendMode::Portfolio,
+        dummy_request("dummy"),
+        &verify,
+        &|_| {},
+        false,
+    );
+    assert_eq!(result, Ok(VerificationResult::Success()));
+    assert_eq!(
+        *verified_backends.lock().unwrap(),
+        vec![VerificationBackend::Silicon]
+    );
+
+    verified_backends.lock().unwrap().clear();
+    let (result, _) = verify_with_backends(
+        BackendMode::Portfolio,
+        dummy_request("slow"),
+        &verify,
+        &|_| {},
+        false,
+    );
+    assert_eq!(result, Ok(VerificationResult::Success()));
+    assert_eq!(
+        *verified_backends.lock().unwrap(),
+        vec![VerificationBackend::Silicon, VerificationBackend::Carbon]
+    );
+}
+
+#[test]
+fn cross_check_reports_disagreement() {
+    let failure: RemoteVerificationResult = Ok(VerificationResult::Failure(vec![
+        VerificationError::new(
+            "assert.failed:assertion.false".to_string(),
+            Some("42".to_string()),
+            None,
+            "Assert might fail. Assertion false might not hold.".to_string(),
+            None,
+        ),
+    ]));
+    let carbon_result = failure.clone();
+    let verify = Arc::new(
+        move |request: VerificationRequest,
+              _: &dyn Fn(VerificationProgress),
+              _: &Cancellation| match request.backend_config.backend {
+            VerificationBackend::Silicon => Ok(VerificationResult::Success()),
+            VerificationBackend::Carbon => carbon_result.clone(),
+        },
+    );
+    let (result, disagreement) = verify_with_backends(
+        BackendMode::CrossCheck,
+        dummy_request("dummy"),
+        &verify,
+        &|_| {},
+        false,
+    );
+    assert_eq!(result, Ok(VerificationResult::Success()));
+    let disagreement = disagreement.expect("The disagreement was not reported!");
+    assert_eq!(
+        disagreement.results,
+        vec![
+            (VerificationBackend::Silicon, Ok(VerificationResult::Success())),
+            (VerificationBackend::Carbon, failure),
+        ]
+    );
+}
+
+#[test]
+fn cross_check_agreement() {
+    let verify = Arc::new(
+        |_: VerificationRequest, _: &dyn Fn(VerificationProgress), _: &Cancellation| {
+            Ok(VerificationResult::Success())
+        },
+    );
+    let (_, disagreement) = verify_with_backends(
+        BackendMode::CrossCheck,
+        dummy_request("dummy"),
+        &verify,
+        &|_| {},
+        true,
+    );
+    assert!(disagreement.is_none());
+}
+
+#[test]
+fn cross_check_ignores_reasons_and_repeated_errors() {
+    let error = |reason_pos_id: &str| {
+        VerificationError::new(
+            "assert.failed:assertion.false".to_string(),
+            Some("42".to_string()),
+            Some(reason_pos_id.to_string()),
+            "Assert might fail. Assertion false might not hold.".to_string(),
+            None,
+        )
+    };
+    let silicon_result: RemoteVerificationResult =
+        Ok(VerificationResult::Failure(vec![error("43")]));
+    let carbon_result: RemoteVerificationResult =
+        Ok(VerificationResult::Failure(vec![error("44"), error("45")]));
+    let verify = Arc::new(
+        move |request: VerificationRequest,
+              _: &dyn Fn(VerificationProgress),
+              _: &Cancellation| match request.backend_config.backend {
+            VerificationBackend::Silicon => silicon_result.clone(),
+            VerificationBackend::Carbon => carbon_result.clone(),
+        },
+    );
+    let (_, disagreement) = verify_with_backends(
+        BackendMode::CrossCheck,
+        dummy_request("dummy"),
+        &verify,
+        &|_| {},
+        false,
+    );
+    assert!(disagreement.is_none());
+}
+
+#[test]
+fn backend_modes() {
+    assert_eq!("Single".parse::<BackendMode>(), Ok(BackendMode::Single));
+    assert_eq!("portfolio".parse::<BackendMode>(), Ok(BackendMode::Portfolio));
+    assert_eq!("cross-check".parse::<BackendMode>(), Ok(BackendMode::CrossCheck));
+    assert!("both".parse::<BackendMode>().is_err());
+}
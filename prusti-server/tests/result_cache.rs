@@ -0,0 +1,91 @@
+extern crate prusti_common;
+extern crate prusti_server;
+extern crate viper;
+
+mod common;
+
+use common::dummy_request;
+use prusti_common::{
+    verification_service::VerificationRequest,
+    vir::{Const, Expr, Function, Position, Type},
+};
+use prusti_server::{CacheKey, ResultCache};
+use std::{env, fs, process};
+use viper::{VerificationError, VerificationResult};
+
+fn failure(pos_id: &str) -> VerificationResult {
+    VerificationResult::Failure(vec![VerificationError::new(
+        "assert.failed:assertion.false".to_string(),
+        Some(pos_id.to_string()),
+        None,
+        "Assert might fail. Assertion false might not hold.".to_string(),
+        None,
+    )])
+}
+
+#[test]
+fn evicts_least_recently_used() {
+    let cache = ResultCache::new(2, None);
+    cache.insert(1, &VerificationResult::Success());
+    cache.insert(2, &failure("2"));
+    assert!(cache.get(1).is_some());
+    cache.insert(3, &failure("3"));
+
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.get(1), Some(VerificationResult::Success()));
+    assert_eq!(cache.get(2), None);
+    assert_eq!(cache.get(3), Some(failure("3")));
+}
+
+#[test]
+fn keeps_most_recently_used_when_loaded() {
+    let path = env::temp_dir().join(format!("prusti-result-cache-{}.bin", process::id()));
+    let _ = fs::remove_file(&path);
+    {
+        let cache = ResultCache::new(3, Some(path.clone()));
+        cache.insert(1, &failure("1"));
+        cache.insert(2, &failure("2"));
+        cache.insert(3, &failure("3"));
+        assert!(cache.get(1).is_some());
+        // the cache is written when it is dropped
+    }
+
+    let cache = ResultCache::new(2, Some(path.clone()));
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.get(1), Some(failure("1")));
+    assert_eq!(cache.get(2), None);
+    assert_eq!(cache.get(3), Some(failure("3")));
+    drop(cache);
+    let _ = fs::remove_file(&path);
+}
+
+/// A request to verify a function whose postcondition `result` has the given position.
+fn request_with_postcondition(result: bool, pos: Position) -> VerificationRequest {
+    let mut request = dummy_request("function");
+    request.program.functions.push(Function {
+        name: "f".to_string(),
+        formal_args: vec![],
+        return_type: Type::Bool,
+        pres: vec![Expr::Const(Const::Bool(true), Position::default())],
+        posts: vec![Expr::Const(Const::Bool(result), pos)],
+        body: None,
+    });
+    request
+}
+
+#[test]
+fn hits_after_unrelated_edit() {
+    let cache = ResultCache::new(2, None);
+    let key = CacheKey::of(&request_with_postcondition(false, Position::new(3, 5, 17)));
+    cache.insert(key.hash, &key.normalize_result(&failure("17")));
+
+    // An edit before the function moves it and shifts the ids of its positions.
+    let moved_key = CacheKey::of(&request_with_postcondition(false, Position::new(8, 5, 42)));
+    assert_eq!(moved_key.hash, key.hash);
+    let result = cache.get(moved_key.hash).map(|result| moved_key.restore_result(result));
+    assert_eq!(result, Some(failure("42")));
+
+    let changed_key = CacheKey::of(&request_with_postcondition(true, Position::new(8, 5, 42)));
+    assert_ne!(changed_key.hash, key.hash);
+    assert_eq!(cache.get(changed_key.hash), None);
+}
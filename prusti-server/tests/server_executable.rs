@@ -1,6 +1,18 @@
+extern crate prusti_common;
+extern crate prusti_server;
+extern crate viper;
+
+mod common;
+
+use common::dummy_request;
+use prusti_server::RecordedRequest;
+#[cfg(unix)]
+use std::{fs, os::unix::fs::PermissionsExt};
 use std::{
     env,
-    process::{Command, Stdio},
+    io::Write,
+    path::PathBuf,
+    process::{self, Command, Stdio},
     thread,
     time::Duration,
 };
@@ -34,18 +46,120 @@ fn system_assigned_port() {
     );
 }
 
+/// test that the server starts up with a pool of worker processes
+#[test]
+fn worker_processes() {
+    let stdout = run_server_with_env(
+        0,
+        &[
+            ("PRUSTI_SERVER_WORKER_PROCESSES", "true"),
+            ("PRUSTI_SERVER_MAX_CONCURRENCY", "2"),
+        ],
+    );
+
+    let line = stdout.lines().next().expect("at least one line in stdout");
+
+    assert!(line.starts_with("port: "), "port message as first stdout line");
+}
+
+/// test that the server listens on a Unix domain socket that only the current user can use
+#[cfg(unix)]
+#[test]
+fn unix_socket() {
+    let socket_path =
+        env::temp_dir().join(format!("prusti-server-test-{}.sock", process::id()));
+    let stdout = run_server_with_args(&["--unix-socket", socket_path.to_str().unwrap()], &[]);
+
+    assert_eq!(
+        stdout.lines().next().expect("at least one line in stdout"),
+        &format!("socket: {}", socket_path.display()),
+        "socket message as first stdout line"
+    );
+    let permissions = fs::metadata(&socket_path).expect("socket file").permissions();
+    assert_eq!(permissions.mode() & 0o777, 0o600);
+    fs::remove_file(&socket_path).unwrap();
+}
+
+/// test that the server does not replace a file that is not a socket
+#[cfg(unix)]
+#[test]
+fn unix_socket_keeps_other_files() {
+    let socket_path =
+        env::temp_dir().join(format!("prusti-server-test-{}.txt", process::id()));
+    fs::write(&socket_path, "not a socket").unwrap();
+    let stdout = run_server_with_args(&["--unix-socket", socket_path.to_str().unwrap()], &[]);
+
+    assert!(!stdout.contains("socket: "), "server did not start");
+    assert_eq!(fs::read_to_string(&socket_path).unwrap(), "not a socket");
+    fs::remove_file(&socket_path).unwrap();
+}
+
+/// test that the server answers the requests on stdin, and exits when stdin is closed
+#[test]
+fn stdio() {
+    let mut server = Command::new(server_path())
+        .arg("--stdio")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run prusti-server");
+
+    let mut stdin = server.stdin.take().unwrap();
+    stdin.write_all(b"not a request\n").unwrap();
+    drop(stdin);
+
+    let output = server.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).expect("could not parse server stdout as utf-8");
+    assert!(
+        stdout.lines().any(|line| line.starts_with("{\"InvalidRequest\":")),
+        "invalid request message in stdout"
+    );
+}
+
+/// test that a recorded request is verified again with the same result
+#[test]
+fn replay() {
+    let recording = RecordedRequest {
+        request: dummy_request("replay"),
+            program_name: "replay".to_string(),
+            backend_config: Default::default(),
+            timeout: None,
+        },
+        result: Ok(viper::VerificationResult::Success()),
+    };
+    let dir = env::temp_dir().join(format!("prusti-server-test-{}", process::id()));
+    let path = recording.write(&dir).expect("could not record the request");
+
+    let output = Command::new(server_path())
+        .arg("replay")
+        .arg(&path)
+        .output()
+        .expect("failed to run prusti-server");
+    let stdout = String::from_utf8(output.stdout).expect("could not parse server stdout as utf-8");
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.status.success(), "equal results");
+    assert!(stdout.contains("The results are equal."));
+}
+
 /// runs the server for a short duration and returns its stdout as a string
 fn run_server_with_port(port: u16) -> String {
-    let server_path = env::current_exe()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .with_file_name("prusti-server-driver") // can't run prusti-server itself because we need to kill it later, and that wouldn't kill the driver
-        .with_extension(env::consts::EXE_EXTENSION);
+    run_server_with_env(port, &[])
+}
+
+/// runs the server with the given environment variables for a short duration and returns its
+/// stdout as a string
+fn run_server_with_env(port: u16, env_vars: &[(&str, &str)]) -> String {
+    run_server_with_args(&["--port", &port.to_string()], env_vars)
+}
 
-    let mut server = Command::new(server_path)
-        .arg("--port")
-        .arg(port.to_string())
+/// runs the server with the given arguments and environment variables for a short duration and
+/// returns its stdout as a string
+fn run_server_with_args(args: &[&str], env_vars: &[(&str, &str)]) -> String {
+    let mut server = Command::new(server_path())
+        .args(args)
+        .envs(env_vars.iter().cloned())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()
@@ -58,3 +172,12 @@ fn run_server_with_port(port: u16) -> String {
     let output = server.wait_with_output().unwrap();
     String::from_utf8(output.stdout).expect("could not parse server stdout as utf-8")
 }
+
+fn server_path() -> PathBuf {
+    env::current_exe()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .with_file_name("prusti-server-driver") // can't run prusti-server itself because we need to kill it later, and that wouldn't kill the driver
+        .with_extension(env::consts::EXE_EXTENSION)
+}
@@ -25,11 +25,14 @@ pub fn rewrite_mod(item_mod: &mut syn::ItemMod, path: &mut syn::Path) -> syn::Re
     for item in item_mod.content.as_mut().unwrap().1.iter_mut() {
         match item {
             syn::Item::Fn(item_fn) => {
-                rewrite_fn(item_fn, path);
+                rewrite_fn(item_fn, path)?;
             },
             syn::Item::Mod(inner_mod) => {
                 rewrite_mod(inner_mod, path)?;
             },
+            syn::Item::Trait(item_trait) => {
+                *item = syn::Item::Verbatim(rewrite_trait(item_trait, path)?);
+            },
             syn::Item::Verbatim(tokens) => {
                 // Transforms function stubs (functions with a `;` after the
                 // signature instead of the body) into functions, then
@@ -54,7 +57,7 @@ pub fn rewrite_mod(item_mod: &mut syn::ItemMod, path: &mut syn::Path) -> syn::Re
 
                 let mut item = res.unwrap();
                 if let syn::Item::Fn(item_fn) = &mut item {
-                    rewrite_fn(item_fn, path);
+                    rewrite_fn(item_fn, path)?;
                 }
                 *tokens = quote!(#item)
             }
@@ -70,9 +73,13 @@ pub fn rewrite_mod(item_mod: &mut syn::ItemMod, path: &mut syn::Path) -> syn::Re
 
 /// Rewrite a specification function to a call to the specified function.
 /// The result of this rewriting is then parsed in `ExternSpecResolver`.
-fn rewrite_fn(item_fn: &mut syn::ItemFn, path: &mut syn::Path) {
+fn rewrite_fn(item_fn: &mut syn::ItemFn, path: &mut syn::Path) -> syn::Result<()> {
     let ident = &item_fn.sig.ident;
-    let args = &item_fn.sig.inputs;
+    let mut args: syn::punctuated::Punctuated<syn::Expr, syn::token::Comma> =
+        syn::punctuated::Punctuated::new();
+    for input in item_fn.sig.inputs.iter() {
+        args.push(rewrite_typed_input(input)?);
+    }
     let item_fn_span = item_fn.span();
     item_fn.block = parse_quote_spanned! {item_fn_span=>
         {
@@ -83,14 +90,42 @@ fn rewrite_fn(item_fn: &mut syn::ItemFn, path: &mut syn::Path) {
 
     item_fn.attrs.push(parse_quote_spanned!(item_fn_span=> #[prusti::extern_spec]));
     item_fn.attrs.push(parse_quote_spanned!(item_fn_span=> #[trusted]));
+    Ok(())
+}
+
+/// Get the expression that forwards a (non-receiver) argument of a
+/// specification function to the specified function.
+fn rewrite_typed_input(input: &syn::FnArg) -> syn::Result<syn::Expr> {
+    if let syn::FnArg::Typed(syn::PatType { pat: box syn::Pat::Ident(ident), .. }) = input {
+        let arg = &ident.ident;
+        return Ok(parse_quote_spanned!(input.span()=> #arg));
+    }
+    Err(syn::Error::new(
+        input.span(),
+        "only identifiers are supported as arguments of external specifications",
+    ))
 }
 
 /// Rewrite all methods in an impl block to calls to the specified methods.
+/// If the impl block implements a trait, the methods are called through the
+/// trait, so that the specifications get attached to the methods of the
+/// implementation of the trait for the specified type.
 /// The result of this rewriting is then parsed in `ExternSpecResolver`.
 pub fn rewrite_impl(
     impl_item: &mut syn::ItemImpl,
     new_ty: Box<syn::Type>,
 ) -> syn::Result<TokenStream> {
+    let trait_path = match impl_item.trait_.take() {
+        Some((Some(bang), _, _)) => {
+            return Err(syn::Error::new(
+                bang.span(),
+                "negative trait implementations cannot have specifications".to_string(),
+            ));
+        }
+        Some((None, trait_path, _)) => Some(trait_path),
+        None => None,
+    };
+
     let item_ty = &mut impl_item.self_ty;
     if let syn::Type::Path(type_path) = item_ty.as_mut() {
         for seg in type_path.path.segments.iter_mut() {
@@ -108,14 +143,19 @@ pub fn rewrite_impl(
                     attr.tokens = rewrite_self(attr.tokens.clone());
                 }
 
-                let args = rewrite_method_inputs(item_ty, method);
+                let args = rewrite_method_inputs(item_ty, method)?;
                 let ident = &method.sig.ident;
 
                 method.attrs.push(parse_quote_spanned!(item_span=> #[prusti::extern_spec]));
                 method.attrs.push(parse_quote_spanned!(item_span=> #[trusted]));
 
-                let mut method_path: syn::ExprPath = parse_quote_spanned! {ident.span()=>
-                    #item_ty :: #ident
+                let mut method_path: syn::ExprPath = match &trait_path {
+                    Some(trait_path) => parse_quote_spanned! {ident.span()=>
+                        < #item_ty as #trait_path > :: #ident
+                    },
+                    None => parse_quote_spanned! {ident.span()=>
+                        #item_ty :: #ident
+                    },
                 };
 
                 // Fix the span
@@ -130,6 +170,9 @@ pub fn rewrite_impl(
                         unimplemented!()
                     }
                 };
+
+                // `Self` refers to the specified type, not to the new struct.
+                *method = syn::parse2(rewrite_self_type(method.to_token_stream(), &*item_ty))?;
             }
             _ => {
                 return Err(syn::Error::new(
@@ -146,6 +189,140 @@ pub fn rewrite_impl(
     })
 }
 
+/// Rewrite a trait declaration to an impl block of a new generic struct, whose
+/// methods call the corresponding methods of the specified trait. The first
+/// type parameter of the struct stands for `Self` and is bounded by the trait,
+/// so that the specifications are attached to the trait methods themselves.
+/// The result of this rewriting is then parsed in `ExternSpecResolver`.
+pub fn rewrite_trait(
+    item_trait: &syn::ItemTrait,
+    path: &syn::Path,
+) -> syn::Result<TokenStream> {
+    let item_span = item_trait.span();
+    let mut trait_path = path.clone();
+    trait_path.segments.push(syn::PathSegment {
+        ident: item_trait.ident.clone(),
+        arguments: syn::PathArguments::None,
+    });
+
+    let name_generator = NameGenerator::new();
+    let struct_ident = syn::Ident::new(
+        &name_generator.generate_trait_struct_name(&trait_path),
+        item_span,
+    );
+    let self_param = syn::Ident::new("PrustiSelf", item_span);
+
+    let trait_params: Vec<&syn::Ident> = item_trait.generics.type_params()
+        .map(|param| &param.ident)
+        .collect();
+    let trait_bound: syn::Path = if trait_params.is_empty() {
+        trait_path.clone()
+    } else {
+        parse_quote_spanned!(item_span=> #trait_path < #(#trait_params),* >)
+    };
+    // The bounds of the trait's parameters are needed for `#self_param: #trait_bound`.
+    let trait_generic_params: Vec<syn::TypeParam> = item_trait.generics.type_params()
+        .map(|param| syn::TypeParam {
+            eq_token: None,
+            default: None,
+            ..param.clone()
+        })
+        .collect();
+    let where_clause = rewrite_self_type(
+        item_trait.generics.where_clause.to_token_stream(),
+        &self_param,
+    );
+    let item_ty: Box<syn::Type> = box parse_quote_spanned! {item_span=>
+        #self_param
+    };
+
+    let mut methods = Vec::new();
+    for item in item_trait.items.iter() {
+        let item_span = item.span();
+        let trait_method = match item {
+            syn::TraitItem::Method(trait_method) => trait_method,
+            _ => {
+                return Err(syn::Error::new(
+                    item_span,
+                    "expected a method".to_string(),
+                ));
+            }
+        };
+        let mut method = syn::ImplItemMethod {
+            attrs: trait_method.attrs.clone(),
+            vis: syn::Visibility::Inherited,
+            defaultness: None,
+            sig: trait_method.sig.clone(),
+            block: parse_quote_spanned!(item_span=> {}),
+        };
+        for attr in method.attrs.iter_mut() {
+            attr.tokens = rewrite_self(attr.tokens.clone());
+        }
+
+        let args = rewrite_method_inputs(&item_ty, &mut method)?;
+        let ident = &method.sig.ident;
+
+        method.attrs.push(parse_quote_spanned!(item_span=> #[prusti::extern_spec]));
+        method.attrs.push(parse_quote_spanned!(item_span=> #[trusted]));
+
+        let mut method_path: syn::ExprPath = parse_quote_spanned! {ident.span()=>
+            < #item_ty as #trait_bound > :: #ident
+        };
+
+        // Fix the span
+        syn::visit_mut::visit_expr_path_mut(
+            &mut SpanOverrider::new(ident.span()),
+            &mut method_path
+        );
+
+        method.block = parse_quote_spanned! {item_span=>
+            {
+                #method_path (#args);
+                unimplemented!()
+            }
+        };
+
+        // `Self` refers to the type parameter of the new struct.
+        let method_tokens = rewrite_self_type(method.to_token_stream(), &self_param);
+        methods.push(method_tokens);
+    }
+
+    Ok(quote_spanned! {item_span=>
+        struct #struct_ident < #self_param : #trait_bound, #(#trait_generic_params),* > (
+            std::marker::PhantomData<#self_param>,
+            #(std::marker::PhantomData<#trait_params>),*
+        ) #where_clause;
+
+        impl < #self_param : #trait_bound, #(#trait_generic_params),* >
+            #struct_ident < #self_param, #(#trait_params),* > #where_clause
+        {
+            #(#methods)*
+        }
+    })
+}
+
+/// Replace all occurrences of the `Self` type by `self_ty`.
+fn rewrite_self_type<T: ToTokens>(tokens: TokenStream, self_ty: &T) -> TokenStream {
+    let mut new_tokens = TokenStream::new();
+    for token in tokens.into_iter() {
+        match token {
+            TokenTree::Group(group) => {
+                let mut new_group = Group::new(group.delimiter(),
+                                               rewrite_self_type(group.stream(), self_ty));
+                new_group.set_span(group.span());
+                new_tokens.extend(new_group.to_token_stream());
+            }
+            TokenTree::Ident(ident) if ident == "Self" => {
+                new_tokens.extend(self_ty.to_token_stream());
+            }
+            _ => {
+                new_tokens.extend(token.into_token_stream());
+            }
+        }
+    }
+    new_tokens
+}
+
 fn rewrite_self(tokens: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
     let mut new_tokens = proc_macro2::TokenStream::new();
     for token in tokens.into_iter() {
@@ -170,7 +347,7 @@ fn rewrite_self(tokens: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
 }
 
 fn rewrite_method_inputs(item_ty: &Box<syn::Type>, method: &mut ImplItemMethod) ->
-    syn::punctuated::Punctuated<syn::Expr, syn::token::Comma> {
+    syn::Result<syn::punctuated::Punctuated<syn::Expr, syn::token::Comma>> {
     let mut args: syn::punctuated::Punctuated<syn::Expr, syn::token::Comma> =
         syn::punctuated::Punctuated::new();
 
@@ -192,17 +369,13 @@ fn rewrite_method_inputs(item_ty: &Box<syn::Type>, method: &mut ImplItemMethod)
                 let expr: syn::Expr = parse_quote_spanned!(input_span=> _self);
                 args.push_value(expr);
             }
-            syn::FnArg::Typed(typed) => {
-                if let syn::Pat::Ident(ident) = &*typed.pat {
-                    let arg = &ident.ident;
-                    let expr: syn::Expr = syn::parse_quote!(#arg);
-                    args.push_value(expr);
-                }
+            syn::FnArg::Typed(_) => {
+                args.push_value(rewrite_typed_input(input)?);
             }
         }
         args.push_punct(syn::token::Comma::default());
     };
-    args
+    Ok(args)
 }
 
 /// Generate an empty struct to be able to define impl blocks (in
@@ -89,6 +89,7 @@ fn generate_spec_and_assertions(
             SpecAttributeKind::AfterExpiryIf => generate_for_after_expiry_if(attr_tokens, item),
             SpecAttributeKind::Pure => generate_for_pure(attr_tokens, item),
             SpecAttributeKind::Trusted => generate_for_trusted(attr_tokens, item),
+            SpecAttributeKind::Timeout => generate_for_timeout(attr_tokens, item),
         };
         let (new_items, new_attributes) = rewriting_result?;
         generated_items.extend(new_items);
@@ -230,6 +231,18 @@ fn generate_for_trusted(_attr: TokenStream, item: &untyped::AnyFnItem) -> Genera
     ))
 }
 
+/// Generate spec items and attributes to typecheck and later retrieve "timeout" annotations.
+fn generate_for_timeout(attr: TokenStream, item: &untyped::AnyFnItem) -> GeneratedResult {
+    let seconds: syn::LitInt = syn::parse2(attr)?;
+    let seconds_str = seconds.base10_parse::<u64>()?.to_string();
+    Ok((
+        vec![],
+        vec![parse_quote_spanned! {item.span()=>
+            #[prusti::timeout = #seconds_str]
+        }],
+    ))
+}
+
 pub fn body_invariant(tokens: TokenStream) -> TokenStream {
     let mut rewriter = rewriter::AstRewriter::new();
     let spec_id = rewriter.generate_spec_id();
@@ -385,6 +398,35 @@ pub fn refine_trait_spec(_attr: TokenStream, tokens: TokenStream) -> TokenStream
     }
 }
 
+/// Generate spec items and attributes to typecheck and later retrieve "invariant" annotations
+/// on traits.
+pub fn invariant(attr: TokenStream, tokens: TokenStream) -> TokenStream {
+    let item: syn::Item = handle_result!(syn::parse2(tokens));
+    let mut item_trait = match item {
+        syn::Item::Trait(item_trait) => item_trait,
+        item => {
+            return syn::Error::new(
+                item.span(),
+                "invariants are currently only supported on traits",
+            ).to_compile_error();
+        }
+    };
+    let mut rewriter = rewriter::AstRewriter::new();
+    let spec_id = rewriter.generate_spec_id();
+    let spec_id_str = spec_id.to_string();
+    let assertion = handle_result!(rewriter.parse_assertion(spec_id, attr));
+    let spec_trait = handle_result!(
+        rewriter.generate_spec_item_trait_inv(spec_id, assertion, &item_trait)
+    );
+    item_trait.attrs.push(parse_quote_spanned! {item_trait.span()=>
+        #[prusti::inv_spec_id_ref = #spec_id_str]
+    });
+    quote_spanned! {item_trait.span()=>
+        #spec_trait
+        #item_trait
+    }
+}
+
 pub fn extern_spec(_attr: TokenStream, tokens:TokenStream) -> TokenStream {
     let item: syn::Item = handle_result!(syn::parse2(tokens));
     let item_span = item.span();
@@ -418,6 +460,16 @@ pub fn extern_spec(_attr: TokenStream, tokens:TokenStream) -> TokenStream {
             handle_result!(extern_spec_rewriter::rewrite_mod(&mut item_mod, &mut path));
             quote!(#item_mod)
         }
-        _ => { unimplemented!() }
+        syn::Item::Trait(item_trait) => {
+            let path = syn::Path {
+                leading_colon: None,
+                segments: syn::punctuated::Punctuated::new(),
+            };
+            handle_result!(extern_spec_rewriter::rewrite_trait(&item_trait, &path))
+        }
+        _ => syn::Error::new(
+            item_span,
+            "extern specs are only supported on impl blocks, modules and traits",
+        ).to_compile_error(),
     }
 }
@@ -134,6 +134,48 @@ impl AstRewriter {
         Ok(syn::Item::Fn(spec_item))
     }
 
+    /// Generate a private trait with a dummy method for checking the given trait invariant.
+    ///
+    /// The generated trait has the trait of the invariant as its supertrait, so that the
+    /// invariant can be instantiated with the receiver of any method of any implementation of
+    /// the trait, and so that the dummy method is not part of the API of the trait.
+    pub fn generate_spec_item_trait_inv(
+        &mut self,
+        spec_id: untyped::SpecificationId,
+        assertion: untyped::Assertion,
+        item: &syn::ItemTrait,
+    ) -> syn::Result<syn::ItemTrait> {
+        let item_span = item.span();
+        let trait_name = syn::Ident::new(
+            &format!("PrustiTraitInv{}{}", item.ident, spec_id),
+            item_span,
+        );
+        let item_name = syn::Ident::new(
+            &format!("prusti_inv_item_{}_{}", item.ident, spec_id),
+            item_span,
+        );
+        let mut statements = TokenStream::new();
+        assertion.encode_type_check(&mut statements);
+        let spec_id_str = spec_id.to_string();
+        let assertion_json = crate::specifications::json::to_json_string(&assertion);
+        let trait_ident = &item.ident;
+        let (_, ty_generics, where_clause) = item.generics.split_for_impl();
+        let mut spec_trait: syn::ItemTrait = parse_quote_spanned! {item_span=>
+            #[allow(dead_code)]
+            trait #trait_name: #trait_ident #ty_generics #where_clause {
+                #[allow(unused_must_use, unused_variables, dead_code)]
+                #[prusti::spec_only]
+                #[prusti::spec_id = #spec_id_str]
+                #[prusti::assertion = #assertion_json]
+                fn #item_name(&self) {
+                    #statements
+                }
+            }
+        };
+        spec_trait.generics.params = item.generics.params.clone();
+        Ok(spec_trait)
+    }
+
     /// Generate statements for checking the given loop invariant.
     pub fn generate_spec_loop(
         &mut self,
@@ -141,6 +141,15 @@ impl NameGenerator {
         Ok(format!("PrustiStruct{}{}", path_str, uuid))
     }
 
+    pub(crate) fn generate_trait_struct_name(&self, path: &syn::Path) -> String {
+        let mut path_str: String = String::new();
+        for seg in path.segments.iter() {
+            path_str.push_str(&seg.ident.to_string());
+        }
+        let uuid = Uuid::new_v4().to_simple();
+        format!("PrustiTraitStruct{}{}", path_str, uuid)
+    }
+
     pub(crate) fn generate_mod_name(&self, ident: &syn::Ident) -> String {
         let uuid = Uuid::new_v4().to_simple();
         format!("{}{}", ident.to_string(), uuid)
@@ -147,7 +147,8 @@ fn run_verification_core_proof(group_name: &str, filter: &Option<String>) {
 
 fn test_runner(_tests: &[&()]) {
     // Spawn server process as child (so it stays around until main function terminates)
-    let server_address = ServerSideService::spawn_off_thread();
+    let server_address = ServerSideService::spawn_off_thread()
+        .expect("could not start the server");
     env::set_var("PRUSTI_SERVER_ADDRESS", server_address.to_string());
 
     // Filter the tests to run
@@ -0,0 +1,8 @@
+use prusti_contracts::*;
+
+#[timeout(ten)] //~ ERROR expected integer literal
+fn wrong_timeout(x: i32) -> i32 {
+    x
+}
+
+fn main() {}
@@ -0,0 +1,41 @@
+// compile-flags: -Pproduce_counterexample=true
+use prusti_contracts::*;
+
+struct Counter {
+    value: u32,
+}
+
+enum Reading {
+    Value { amount: u32 },
+    Missing,
+}
+
+#[pure]
+fn reading_amount(reading: &Reading) -> u32 {
+    match reading {
+        Reading::Value { amount } => *amount,
+        Reading::Missing => 0,
+    }
+}
+
+#[requires(x == 5)]
+#[ensures(result < 10)] //~ ERROR postcondition might not hold
+//~| NOTE counterexample: result = 10, x = 5
+fn double(x: u32) -> u32 { //~ NOTE the error originates here
+    x + x
+}
+
+#[ensures(counter.value > 0)] //~ ERROR postcondition might not hold
+//~| NOTE counterexample: counter.value = 0
+fn reset(counter: &mut Counter) { //~ NOTE the error originates here
+    counter.value = 0;
+}
+
+#[requires(amount == 3)]
+#[ensures(reading_amount(&result) > 3)] //~ ERROR postcondition might not hold
+//~| NOTE (result as Value).amount = 3
+fn read(amount: u32) -> Reading { //~ NOTE the error originates here
+    Reading::Value { amount }
+}
+
+fn main() {}
@@ -0,0 +1,24 @@
+// compile-flags: -Pexplain_verification_errors=true
+use prusti_contracts::*;
+
+#[ensures(result > 0)] //~ ERROR postcondition might not hold
+fn abs(x: i32) -> i32 {
+    if x >= 0 {
+        x
+    } else {
+        -x
+    }
+}
+
+#[requires(n >= 0)]
+#[ensures(result == n)] //~ ERROR postcondition might not hold
+fn count(n: i32) -> i32 {
+    let mut i = 0;
+    while i < n {
+        body_invariant!(i >= 0);
+        i += 1;
+    }
+    i
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+extern crate prusti_contracts;
+use prusti_contracts::*;
+
+pub trait Counter {
+    fn count(&self) -> usize;
+}
+
+#[extern_spec]
+trait Counter {
+    #[ensures(result <= 10)]
+    fn count(&self) -> usize;
+}
+
+fn test<T: Counter>(t: &T) {
+    let c = t.count();
+    assert!(c <= 9); //~ ERROR the asserted expression might not hold
+}
+
+fn main() {}
@@ -0,0 +1,27 @@
+extern crate prusti_contracts;
+use prusti_contracts::*;
+
+pub trait Size {
+    fn size(&self) -> usize;
+}
+
+pub struct Wrapper<T> {
+    inner: T,
+}
+
+impl<T> Size for Wrapper<T> {
+    fn size(&self) -> usize {
+        1
+    }
+}
+
+#[extern_spec]
+impl<T> Size for Wrapper<T> {
+    #[ensures(result == 1)]
+    fn size(&self) -> usize;
+}
+
+fn main() {
+    let w = Wrapper { inner: 3 };
+    assert!(w.size() == 2); //~ ERROR the asserted expression might not hold
+}
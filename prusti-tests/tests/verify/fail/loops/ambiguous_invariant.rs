@@ -0,0 +1,14 @@
+use prusti_contracts::*;
+
+fn test() {
+    let mut i = 0;
+    let mut j = 0;
+    while i < 10 {
+        body_invariant!(i < 10); //~ ERROR the position of the loop invariant is ambiguous
+        i += 1;
+        body_invariant!(i <= 10);
+        j += 1;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,22 @@
+use prusti_contracts::*;
+
+fn count() {
+    let mut count = 0;
+    for _ in 0..10 {
+        body_invariant!(count == 0); //~ ERROR loop invariant might not hold
+        count += 1;
+    }
+}
+
+fn count_nested() {
+    let mut outer = 0;
+    for _ in 0..10 {
+        body_invariant!(outer >= 0);
+        outer += 1;
+        for _ in 0..10 {
+            body_invariant!(outer == 0); //~ ERROR loop invariant might not hold
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,33 @@
+use prusti_contracts::*;
+
+fn break_inner(n: u32) {
+    let mut i = 0;
+    while i < n {
+        body_invariant!(i < n);
+        let mut j = 0;
+        while j < n {
+            body_invariant!(j < i); //~ ERROR loop invariant might not hold
+            if j == i {
+                break;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+}
+
+fn exit_inner_without_break(n: u32) {
+    let mut i = 0;
+    while i < n {
+        body_invariant!(i < n);
+        let mut j = 0;
+        while j < n {
+            body_invariant!(j < n);
+            j += 1;
+        }
+        i += 1;
+    }
+    assert!(i == n + 1); //~ ERROR the asserted expression might not hold
+}
+
+fn main() {}
@@ -0,0 +1,45 @@
+extern crate prusti_contracts;
+use prusti_contracts::*;
+
+#[extern_spec]
+impl<T> std::option::Option<T> {
+    #[pure]
+    #[ensures(matches!(*self, Some(_)) == result)]
+    pub fn is_some(&self) -> bool;
+}
+
+fn test_todo(x: u32) -> u32 {
+    if x > 10 {
+        todo!() //~ ERROR todo!(..) statement might be reachable
+    }
+    x
+}
+
+fn test_unwrap(x: Option<u32>) -> u32 {
+    x.unwrap() //~ ERROR unwrap() or expect(..) might be called on a `None` or `Err` value
+}
+
+fn test_unwrap_checked(x: Option<u32>) -> u32 {
+    if x.is_some() {
+        x.unwrap()
+    } else {
+        0
+    }
+}
+
+fn test_expect(x: Result<u32, ()>) -> u32 {
+    x.expect("no value") //~ ERROR unwrap() or expect(..) might be called on a `None` or `Err` value
+}
+
+fn test_expect_checked(x: Result<u32, ()>) -> u32 {
+    match x {
+        Ok(_) => x.expect("no value"),
+        Err(_) => 0,
+    }
+}
+
+fn test_division(x: u32, y: u32) -> u32 {
+    x / y //~ ERROR attempt to divide by zero
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use prusti_contracts::*;
+
+#[prusti::skip]
+fn skipped() {
+    assert!(false);
+}
+
+fn verified() {
+    assert!(false); //~ ERROR the asserted expression might not hold
+}
+
+fn main() {}
@@ -0,0 +1,32 @@
+use prusti_contracts::*;
+
+#[invariant(self.value() <= 100)]
+trait Percentage {
+    #[pure]
+    fn value(&self) -> u8;
+
+    fn check(&self);
+}
+
+struct Fixed {
+    v: u8,
+}
+
+impl Percentage for Fixed {
+    #[pure]
+    fn value(&self) -> u8 {
+        self.v
+    }
+
+    fn check(&self) {
+        assert!(self.v <= 100);
+        assert!(self.v <= 99); //~ ERROR the asserted expression might not hold
+    }
+}
+
+fn test<T: Percentage>(t: &T) {
+    assert!(t.value() <= 100);
+    assert!(t.value() <= 99); //~ ERROR the asserted expression might not hold
+}
+
+fn main() {}
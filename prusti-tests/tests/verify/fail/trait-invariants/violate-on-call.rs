@@ -0,0 +1,40 @@
+use prusti_contracts::*;
+
+#[invariant(self.value() <= 100)]
+trait Percentage {
+    #[pure]
+    fn value(&self) -> u8;
+}
+
+struct Fixed {
+    v: u8,
+}
+
+impl Percentage for Fixed {
+    #[pure]
+    fn value(&self) -> u8 {
+        self.v
+    }
+}
+
+fn test(f: &Fixed) {
+    assert!(f.value() <= 100);
+}
+
+fn test_generic<T: Percentage>(_t: &T) {}
+
+fn overflow(f: &mut Fixed) { //~ ERROR the invariant of trait `Percentage` might not hold at the end of the method.
+    f.v = 200;
+}
+
+fn call() {
+    let f = Fixed { v: 200 };
+    test(&f); //~ ERROR the invariant of trait `Percentage` might not hold before the method call.
+}
+
+fn call_generic() {
+    let f = Fixed { v: 200 };
+    test_generic(&f); //~ ERROR the invariant of trait `Percentage` might not hold before the method call.
+}
+
+fn main() {}
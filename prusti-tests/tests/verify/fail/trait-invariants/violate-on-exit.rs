@@ -0,0 +1,26 @@
+use prusti_contracts::*;
+
+#[invariant(self.value() <= 100)]
+trait Percentage {
+    #[pure]
+    fn value(&self) -> u8;
+
+    fn set(&mut self, arg: u8);
+}
+
+struct Fixed {
+    v: u8,
+}
+
+impl Percentage for Fixed {
+    #[pure]
+    fn value(&self) -> u8 {
+        self.v
+    }
+
+    fn set(&mut self, arg: u8) { //~ ERROR the invariant of trait `Percentage` might not hold at the end of the method.
+        self.v = arg;
+    }
+}
+
+fn main() {}
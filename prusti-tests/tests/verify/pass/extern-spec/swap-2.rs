@@ -0,0 +1,22 @@
+extern crate prusti_contracts;
+use prusti_contracts::*;
+
+#[extern_spec]
+mod std {
+    mod mem {
+        use prusti_contracts::*;
+
+        #[ensures(*a == old(*b) && *b == old(*a))]
+        pub fn swap(a: &mut u32, b: &mut u32);
+    }
+}
+
+fn main() {
+    let mut x = 5;
+    let mut y = 42;
+
+    std::mem::swap(&mut x, &mut y);
+
+    assert!(42 == x);
+    assert!(5 == y);
+}
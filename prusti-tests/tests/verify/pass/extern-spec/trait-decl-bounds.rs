@@ -0,0 +1,20 @@
+extern crate prusti_contracts;
+use prusti_contracts::*;
+
+/// A trait with a bounded parameter, as if it was declared in another crate.
+pub trait Measure<T: Copy> where Self: Sized {
+    fn measure(&self, unit: T) -> usize;
+}
+
+#[extern_spec]
+trait Measure<T: Copy> where Self: Sized {
+    #[ensures(result <= 10)]
+    fn measure(&self, unit: T) -> usize;
+}
+
+fn test<M: Measure<T>, T: Copy>(m: &M, unit: T) {
+    let length = m.measure(unit);
+    assert!(length <= 10);
+}
+
+fn main() {}
@@ -0,0 +1,20 @@
+extern crate prusti_contracts;
+use prusti_contracts::*;
+
+/// A trait without specifications, as if it was declared in another crate.
+pub trait Counter {
+    fn count(&self) -> usize;
+}
+
+#[extern_spec]
+trait Counter {
+    #[ensures(result <= 10)]
+    fn count(&self) -> usize;
+}
+
+fn test<T: Counter>(t: &T) {
+    let c = t.count();
+    assert!(c <= 10);
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+extern crate prusti_contracts;
+use prusti_contracts::*;
+
+use std::cmp::Ordering;
+
+#[extern_spec]
+impl<T: Ord> Ord for std::option::Option<T> {
+    #[ensures((matches!(*self, None) && matches!(*other, Some(_)))
+        ==> matches!(result, Ordering::Less))]
+    fn cmp(&self, other: &std::option::Option<T>) -> Ordering;
+}
+
+fn test<T: Ord>(x: T) {
+    let none: Option<T> = None;
+    let some = Some(x);
+    assert!(matches!(none.cmp(&some), Ordering::Less));
+}
+
+fn main() {}
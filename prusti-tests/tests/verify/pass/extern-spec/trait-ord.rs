@@ -0,0 +1,27 @@
+extern crate prusti_contracts;
+use prusti_contracts::*;
+
+use std::cmp::Ordering;
+
+#[extern_spec]
+mod std {
+    mod cmp {
+        use prusti_contracts::*;
+
+        pub trait Ord {
+            // A deliberately strong specification, so that the test only verifies if it is used.
+            #[ensures(matches!(result, std::cmp::Ordering::Equal))]
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering;
+        }
+    }
+}
+
+#[ensures(result)]
+fn equal<T: Ord>(x: &T, y: &T) -> bool {
+    match x.cmp(y) {
+        Ordering::Equal => true,
+        _ => false,
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use prusti_contracts::*;
+
+fn test() {
+    let mut i = 0;
+    let mut j = 0;
+    while i < 10 {
+        body_invariant!(i < 10);
+        body_invariant!(i == j);
+        i += 1;
+        j += 1;
+    }
+    assert!(i == j);
+}
+
+fn main() {}
@@ -0,0 +1,38 @@
+use prusti_contracts::*;
+
+fn count() {
+    let mut count = 0;
+    for _ in 0..10 {
+        body_invariant!(count >= 0);
+        count += 1;
+    }
+    assert!(count >= 0);
+}
+
+fn count_with_break(n: u32) {
+    let mut count = 0;
+    for _ in 0..n {
+        body_invariant!(count <= 5);
+        if count == 5 {
+            break;
+        }
+        count += 1;
+    }
+    assert!(count <= 5);
+}
+
+fn count_nested() {
+    let mut outer = 0;
+    let mut inner = 0;
+    for _ in 0..10 {
+        body_invariant!(outer >= 0 && inner >= 0);
+        outer += 1;
+        for _ in 0..10 {
+            body_invariant!(outer >= 1 && inner >= 0);
+            inner += 1;
+        }
+    }
+    assert!(outer >= 0 && inner >= 0);
+}
+
+fn main() {}
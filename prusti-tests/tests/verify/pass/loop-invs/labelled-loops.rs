@@ -0,0 +1,54 @@
+use prusti_contracts::*;
+
+#[trusted]
+fn random(i: u32) -> bool {
+    unimplemented!()
+}
+
+fn break_outer_before_invariant() {
+    let mut i = 0;
+    'outer: while i < 10 {
+        'inner: while random(i) {
+            if i == 5 {
+                break 'outer;
+            }
+        }
+        body_invariant!(i < 10);
+        i += 1;
+    }
+}
+
+fn continue_after_invariant() {
+    let mut i = 0;
+    while i < 10 {
+        body_invariant!(i < 10);
+        i += 1;
+        if random(i) {
+            continue;
+        }
+        if random(i + 1) {
+            break;
+        }
+    }
+    assert!(i <= 10);
+}
+
+fn continue_outer_after_invariants() {
+    let mut i = 0;
+    let mut j = 0;
+    'outer: while i < 10 {
+        body_invariant!(i < 10);
+        i += 1;
+        j = 0;
+        'inner: while j < 10 {
+            body_invariant!(j < 10);
+            j += 1;
+            if random(j) {
+                continue 'outer;
+            }
+        }
+    }
+    assert!(i == 10);
+}
+
+fn main() {}
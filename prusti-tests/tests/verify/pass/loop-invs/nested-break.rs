@@ -0,0 +1,42 @@
+use prusti_contracts::*;
+
+#[trusted]
+fn random(i: u32) -> bool {
+    unimplemented!()
+}
+
+fn break_inner(n: u32) {
+    let mut i = 0;
+    while i < n {
+        body_invariant!(i < n);
+        let mut j = 0;
+        // The exit of the inner loop is not an exit of the outer loop.
+        while j < n {
+            body_invariant!(i < n && j < n);
+            if j == i {
+                break;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    assert!(i == n);
+}
+
+fn conditional_inner_loop(n: u32) {
+    let mut i = 0;
+    while i < n {
+        body_invariant!(i < n);
+        if random(i) {
+            let mut j = 0;
+            while j < i {
+                body_invariant!(j < i && i < n);
+                j += 1;
+            }
+        }
+        i += 1;
+    }
+    assert!(i == n);
+}
+
+fn main() {}
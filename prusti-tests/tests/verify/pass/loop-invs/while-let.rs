@@ -0,0 +1,21 @@
+use prusti_contracts::*;
+
+fn next(i: u32, n: u32) -> Option<u32> {
+    if i < n {
+        Some(i + 1)
+    } else {
+        None
+    }
+}
+
+#[requires(n < 100)]
+fn count(n: u32) -> u32 {
+    let mut i = 0;
+    while let Some(j) = next(i, n) {
+        body_invariant!(n < 100);
+        i = j;
+    }
+    i
+}
+
+fn main() {}
@@ -0,0 +1,37 @@
+// compile-flags: -Pdisabled_panic_checks=todo,unimplemented,division_by_zero,unwrap
+use prusti_contracts::*;
+
+fn test_todo(x: u32) -> u32 {
+    if x > 10 {
+        todo!()
+    }
+    x
+}
+
+fn test_unimplemented() {
+    unimplemented!()
+}
+
+#[pure]
+fn pure_todo(x: u32) -> u32 {
+    if x > 10 {
+        todo!()
+    }
+    x
+}
+
+fn test_unwrap(x: Option<u32>) -> u32 {
+    x.unwrap()
+}
+
+fn test_division(x: u32, y: u32) -> u32 {
+    x / y
+}
+
+#[ensures(result == 1)]
+fn test_assumed_division(x: u32) -> u32 {
+    // The divisor is assumed to be different from zero
+    x / x
+}
+
+fn main() {}
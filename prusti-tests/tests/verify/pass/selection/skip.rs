@@ -0,0 +1,13 @@
+use prusti_contracts::*;
+
+#[prusti::skip]
+fn skipped() {
+    assert!(false);
+}
+
+#[ensures(result == 42)]
+fn verified() -> i32 {
+    42
+}
+
+fn main() {}
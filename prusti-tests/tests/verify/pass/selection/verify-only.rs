@@ -0,0 +1,34 @@
+// compile-flags: -Pverify_only=module::selected_*,Counter::increment
+
+use prusti_contracts::*;
+
+mod module {
+    use prusti_contracts::*;
+
+    #[ensures(result == 1)]
+    pub fn selected_one() -> i32 {
+        1
+    }
+
+    pub fn not_selected() {
+        assert!(false);
+    }
+}
+
+struct Counter {
+    value: u32,
+}
+
+impl Counter {
+    #[requires(self.value < 100)]
+    #[ensures(self.value == old(self.value) + 1)]
+    fn increment(&mut self) {
+        self.value += 1;
+    }
+
+    fn reset(&mut self) {
+        assert!(false);
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use prusti_contracts::*;
+
+fn not_selected() {
+    assert!(false);
+}
+
+#[prusti::verify]
+#[ensures(result == 42)]
+fn selected() -> i32 {
+    42
+}
+
+fn main() {}
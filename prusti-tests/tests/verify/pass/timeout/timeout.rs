@@ -0,0 +1,16 @@
+// compile-flags: -Pprocedure_timeout=600
+
+use prusti_contracts::*;
+
+#[timeout(300)]
+#[ensures(result == x + 1)]
+fn with_timeout(x: u32) -> u32 {
+    x + 1
+}
+
+#[ensures(result == x)]
+fn with_global_timeout(x: u32) -> u32 {
+    with_timeout(x) - 1
+}
+
+fn main() {}
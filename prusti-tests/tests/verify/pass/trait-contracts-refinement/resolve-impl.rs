@@ -0,0 +1,33 @@
+use prusti_contracts::*;
+
+trait Foo {
+    #[ensures(result > 42)]
+    fn foo(&self) -> i32;
+}
+
+struct Wrapper<T> {
+    inner: T,
+}
+
+#[refine_trait_spec]
+impl Foo for Wrapper<i32> {
+    #[ensures(result > 84)]
+    fn foo(&self) -> i32 {
+        100
+    }
+}
+
+#[refine_trait_spec]
+impl Foo for Wrapper<bool> {
+    #[ensures(result > 168)]
+    fn foo(&self) -> i32 {
+        200
+    }
+}
+
+fn main() {
+    let a = Wrapper { inner: 1 };
+    assert!(a.foo() > 84);
+    let b = Wrapper { inner: true };
+    assert!(b.foo() > 168);
+}
@@ -0,0 +1,42 @@
+use prusti_contracts::*;
+
+#[invariant(self.value() <= 100)]
+trait Percentage {
+    #[pure]
+    fn value(&self) -> u8;
+
+    fn set(&mut self, arg: u8);
+}
+
+struct Fixed {
+    v: u8,
+}
+
+impl Percentage for Fixed {
+    #[pure]
+    fn value(&self) -> u8 {
+        self.v
+    }
+
+    fn set(&mut self, arg: u8) {
+        if arg <= 100 {
+            self.v = arg;
+        }
+    }
+}
+
+fn test<T: Percentage>(t: &mut T) {
+    t.set(42);
+    assert!(t.value() <= 100);
+}
+
+fn test_fixed(f: &mut Fixed) {
+    f.set(42);
+    assert!(f.value() <= 100);
+}
+
+fn main() {
+    let mut f = Fixed { v: 0 };
+    test(&mut f);
+    test_fixed(&mut f);
+}
@@ -0,0 +1,22 @@
+// compile-flags: -Pskip_unsupported_features=true
+
+use prusti_contracts::*;
+
+// Skipped, because floating-point numbers are not supported.
+fn floats(x: f32) -> f32 {
+    assert!(false);
+    x + 1.0
+}
+
+// Skipped, because raw pointers are not supported.
+fn raw_pointer(x: &u32) -> *const u32 {
+    assert!(false);
+    x as *const u32
+}
+
+#[ensures(result == x + 1)]
+fn supported(x: u32) -> u32 {
+    x + 1
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+// compile-flags: -Pdisabled_panic_checks=overflow
+use prusti_contracts::*;
+
+fn add(a: u32, b: u32) -> u32 {
+    a + b
+}
+
+fn negate(x: i32) -> i32 {
+    -x
+}
+
+fn main() {}
@@ -39,6 +39,7 @@ use rustc_hir::def_id::DefId;
 use rustc_middle::mir;
 // use rustc::mir::interpret::GlobalId;
 use rustc_middle::ty;
+use rustc_middle::ty::fold::TypeFoldable;
 use std::cell::{RefCell, RefMut};
 use std::collections::HashMap;
 use std::io::Write;
@@ -98,6 +99,10 @@ pub struct Encoder<'v, 'tcx: 'v> {
     vir_program_before_viper_writer: RefCell<Box<Write>>,
     pub typaram_repl: RefCell<Vec<HashMap<ty::Ty<'tcx>, ty::Ty<'tcx>>>>,
     encoding_errors_counter: RefCell<usize>,
+    /// The encoding errors and warnings that have been reported so far.
+    encoding_errors: RefCell<Vec<PrustiError>>,
+    /// The encoding errors and warnings reported while encoding each procedure of the queue.
+    procedure_encoding_errors: RefCell<HashMap<ProcedureDefId, Vec<PrustiError>>>,
     name_interner: RefCell<NameInterner>,
     axiomatized_function_domain: RefCell<vir::Domain>,
 }
@@ -165,6 +170,8 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
             type_snapshots: RefCell::new(HashMap::new()),
             snap_mirror_funcs: RefCell::new(HashMap::new()),
             encoding_errors_counter: RefCell::new(0),
+            encoding_errors: RefCell::new(vec![]),
+            procedure_encoding_errors: RefCell::new(HashMap::new()),
             name_interner: RefCell::new(NameInterner::new()),
             axiomatized_function_domain: RefCell::new(axiomatized_functions_domain),
         }
@@ -197,7 +204,10 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
     }
 
     fn initialize(&mut self) {
-        self.closures_collector.borrow_mut().collect_from_all_spec_items(self.env);
+        self.closures_collector.borrow_mut().collect_from_all_spec_items(
+            self.env,
+            &self.def_spec.imported_spec_items,
+        );
         // These are used in optimization passes
         self.encode_builtin_method_def(BuiltinMethodKind::HavocBool);
         self.encode_builtin_method_def(BuiltinMethodKind::HavocInt);
@@ -233,6 +243,7 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
         if prusti_error.is_error() {
             self.encoding_errors_counter.borrow_mut().add_assign(1);
         }
+        self.encoding_errors.borrow_mut().push(prusti_error.clone());
         prusti_error.emit(self.env);
     }
 
@@ -240,6 +251,20 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
         *self.encoding_errors_counter.borrow()
     }
 
+    /// The encoding errors and warnings that were reported while encoding the given procedure
+    /// from the encoding queue.
+    pub fn get_encoding_errors(&self, def_id: ProcedureDefId) -> Vec<PrustiError> {
+        self.procedure_encoding_errors.borrow().get(&def_id).cloned().unwrap_or_default()
+    }
+
+    /// The procedures that have been encoded as Viper methods, by the name of their method.
+    pub fn get_encoded_procedures(&self) -> HashMap<String, ProcedureDefId> {
+        self.procedures.borrow()
+            .iter()
+            .map(|(&def_id, method)| (method.name(), def_id))
+            .collect()
+    }
+
     pub fn get_used_viper_domains(&self) -> Vec<vir::Domain> {
         let mirrors: Vec<_> = self
             .snap_mirror_funcs
@@ -506,6 +531,108 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
         Some(spec.expect_procedure().clone())
     }
 
+    /// Get the invariants attached to the `trait_def_id` trait.
+    pub fn get_trait_invariants(&self, trait_def_id: DefId) -> Vec<typed::Assertion<'tcx>> {
+        self.def_spec.get(&trait_def_id)
+            .map(|spec| spec.expect_struct()
+                .iter()
+                .map(|spec| spec.assertion.clone())
+                .collect())
+            .unwrap_or_else(Vec::new)
+    }
+
+    /// Get the traits, of this crate or of other crates, that have invariants.
+    fn get_traits_with_invariants(&self) -> Vec<DefId> {
+        let tcx = self.env().tcx();
+        let local_def_ids = self.def_spec.specs.keys().map(|local_id| local_id.to_def_id());
+        let imported_def_ids = self.def_spec.imported_specs.keys().cloned();
+        let mut traits: Vec<DefId> = local_def_ids
+            .chain(imported_def_ids)
+            .filter(|&def_id| tcx.is_trait(def_id))
+            .filter(|&def_id| !self.get_trait_invariants(def_id).is_empty())
+            .collect();
+        // the invariants are encoded in a deterministic order
+        traits.sort_by_key(|&def_id| tcx.def_path_str(def_id));
+        traits
+    }
+
+    /// Get the trait invariants that constrain the arguments of the `proc_def_id` procedure.
+    /// Each invariant is returned together with the index of the argument it constrains, the
+    /// `DefId` of the trait that declares it and the type that `Self` stands for in it.
+    ///
+    /// An invariant constrains:
+    /// * the receiver of a method implementing a trait that has invariants;
+    /// * any reference argument whose type is a type parameter (including `Self` in trait
+    ///   methods) bounded by a trait that has invariants;
+    /// * any other reference argument whose type is a struct or enum implementing a trait that
+    ///   has invariants.
+    pub fn get_trait_invariants_of_args(
+        &self,
+        proc_def_id: ProcedureDefId,
+    ) -> Vec<(usize, DefId, ty::Ty<'tcx>, typed::Assertion<'tcx>)> {
+        let tcx = self.env().tcx();
+        if tcx.is_closure(proc_def_id) {
+            return vec![];
+        }
+        let mut result = vec![];
+        let fn_sig = tcx.fn_sig(proc_def_id).skip_binder();
+        let is_ref = |ty: ty::Ty<'tcx>| matches!(ty.kind(), ty::TyKind::Ref(..));
+
+        // The receiver of a method in a trait implementation.
+        let impl_def_id = tcx.impl_of_method(proc_def_id);
+        let impl_trait_def_id = impl_def_id
+            .and_then(|impl_def_id| tcx.trait_id_of_impl(impl_def_id));
+        if let (Some(impl_def_id), Some(trait_def_id)) = (impl_def_id, impl_trait_def_id) {
+            if tcx.associated_item(proc_def_id).fn_has_self_parameter
+                && is_ref(fn_sig.inputs()[0])
+            {
+                let self_ty = tcx.type_of(impl_def_id);
+                for assertion in self.get_trait_invariants(trait_def_id) {
+                    result.push((0, trait_def_id, self_ty, assertion));
+                }
+            }
+        }
+
+        // Reference arguments of a struct or enum, or of a type parameter bounded by a trait.
+        let param_env = tcx.param_env(proc_def_id);
+        for (index, arg_ty) in fn_sig.inputs().iter().enumerate() {
+            let inner_ty = match arg_ty.kind() {
+                ty::TyKind::Ref(_, inner_ty, _) => inner_ty,
+                _ => continue,
+            };
+            if let ty::TyKind::Adt(..) = inner_ty.kind() {
+                if inner_ty.needs_subst() {
+                    continue;
+                }
+                for trait_def_id in self.get_traits_with_invariants() {
+                    // The receiver may already be constrained by the trait that it implements.
+                    let is_constrained = result.iter()
+                        .any(|&(i, t, _, _)| i == index && t == trait_def_id);
+                    if !is_constrained && self.env().type_implements_trait(inner_ty, trait_def_id) {
+                        for assertion in self.get_trait_invariants(trait_def_id) {
+                            result.push((index, trait_def_id, inner_ty, assertion));
+                        }
+                    }
+                }
+                continue;
+            }
+            if !matches!(inner_ty.kind(), ty::TyKind::Param(_)) {
+                continue;
+            }
+            for predicate in param_env.caller_bounds() {
+                if let ty::PredicateAtom::Trait(trait_pred, _) = predicate.skip_binders() {
+                    if trait_pred.self_ty() == *inner_ty {
+                        let trait_def_id = trait_pred.def_id();
+                        for assertion in self.get_trait_invariants(trait_def_id) {
+                            result.push((index, trait_def_id, inner_ty, assertion));
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+
     /// Get a local wrapper `DefId` for functions that have external specs.
     /// Return the original `DefId` for everything else.
     fn get_wrapper_def_id(&self, def_id: DefId) -> DefId {
@@ -536,9 +663,15 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
             .map_err(|err| err.clone())
     }
 
+    /// Get the contract of the `proc_def_id` procedure at a call site in `caller_def_id`.
+    ///
+    /// If the called procedure is a trait method whose implementation is statically known
+    /// from `call_substs` (e.g. because the call is monomorphic), the specification of the
+    /// implementation refines the one of the trait method.
     pub fn get_procedure_contract_for_call(
         &self,
-        self_ty: Option<&'tcx ty::TyS<'tcx>>,
+        caller_def_id: ProcedureDefId,
+        call_substs: Option<ty::subst::SubstsRef<'tcx>>,
         proc_def_id: ProcedureDefId,
         args: &Vec<places::Local>,
         target: places::Local,
@@ -558,31 +691,16 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
             ));
         }
 
-        // get receiver object base type
+        // get specification on the implementation of the called trait method
         let mut impl_spec = typed::ProcedureSpecification::empty();
-
-        // let mut self_ty = None;
-
-        // for (key, val) in tymap[0].iter() {
-        //     if key.is_self() {   // FIXME: This check does not work anymore.
-        //         self_ty = Some(val.clone());
-        //     }
-        // }
-
-        if let Some(ty) = self_ty {
-            if let Some(id) = self.env().tcx().trait_of_item(proc_def_id) {
-                let proc_name = self.env().tcx().item_name(proc_def_id);
-                let procs = self.env().get_trait_method_decl_for_type(ty, id, proc_name);
-                if procs.len() == 1 {
-                    // FIXME(@jakob): if several methods are found, we currently don't know which
-                    // one to pick.
-                    let item = procs[0];
-                    if let Some(spec) = self.get_procedure_specs(item.def_id) {
-                        impl_spec = spec;
-                    } else {
-                        debug!("Procedure {:?} has no specification", item.def_id);
-                    }
-                }
+        let impl_def_id = call_substs.and_then(|substs| {
+            self.env().resolve_trait_method_call(caller_def_id, proc_def_id, substs)
+        }).map(|(impl_def_id, _)| impl_def_id);
+        if let Some(impl_def_id) = impl_def_id {
+            if let Some(spec) = self.get_procedure_specs(impl_def_id) {
+                impl_spec = spec;
+            } else {
+                debug!("Procedure {:?} has no specification", impl_def_id);
             }
         }
 
@@ -598,6 +716,35 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
         Ok(contract.to_call_site_contract(args, target))
     }
 
+    /// Resolve the call of the trait method `called_def_id` from the pure code of `caller_def_id`
+    /// to the method of the implementation, if it is statically known once the type parameters
+    /// of `call_substs` are replaced by the current type substitutions (e.g. `Self` by the type
+    /// of an implementation when encoding a trait invariant) and the method of the implementation
+    /// is pure too. Otherwise, the called method and its substitutions are returned unchanged.
+    pub fn resolve_pure_trait_method_call(
+        &self,
+        caller_def_id: ProcedureDefId,
+        called_def_id: ProcedureDefId,
+        call_substs: ty::subst::SubstsRef<'tcx>,
+    ) -> (ProcedureDefId, ty::subst::SubstsRef<'tcx>) {
+        let tcx = self.env().tcx();
+        if tcx.trait_of_item(called_def_id).is_none() {
+            return (called_def_id, call_substs);
+        }
+        let substs = tcx.mk_substs(call_substs.iter().map(|arg| match arg.unpack() {
+            ty::subst::GenericArgKind::Type(ty) => self.resolve_typaram(ty).into(),
+            _ => arg,
+        }));
+        // The type parameters of another procedure cannot be resolved in `caller_def_id`.
+        if substs.needs_subst() {
+            return (called_def_id, call_substs);
+        }
+        self.env()
+            .resolve_trait_method_call(caller_def_id, called_def_id, substs)
+            .filter(|&(impl_def_id, _)| self.is_pure(impl_def_id))
+            .unwrap_or((called_def_id, call_substs))
+    }
+
     /// Encodes a value in a field if the base expression is a reference or
     /// a primitive types.
     /// For composed data structures, the base expression is returned.
@@ -834,7 +981,7 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
             def_id
         );
         if !self.procedures.borrow().contains_key(&def_id) {
-            self.closures_collector.borrow_mut().collect(self.env, def_id.expect_local());
+            self.closures_collector.borrow_mut().collect(self.env, def_id);
             let procedure = self.env.get_procedure(def_id);
             let proc_encoder = ProcedureEncoder::new(self, &procedure)?;
             let method = match proc_encoder.encode() {
@@ -1282,6 +1429,14 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
         if !self.pure_functions.borrow().contains_key(&key) {
             trace!("not encoded: {:?}", key);
             let wrapper_def_id = self.get_wrapper_def_id(proc_def_id);
+            if !self.env.tcx().is_mir_available(wrapper_def_id) {
+                let function = self.encode_abstract_pure_function(proc_def_id)
+                    .run_if_err(cleanup)?;
+                self.log_vir_program_before_viper(function.to_string());
+                self.pure_functions.borrow_mut().insert(key, function);
+                cleanup();
+                return Ok(());
+            }
             let procedure = self.env.get_procedure(wrapper_def_id);
             let pure_function_encoder =
                 PureFunctionEncoder::new(self, proc_def_id, procedure.get_mir(), false);
@@ -1316,6 +1471,76 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
         Ok(())
     }
 
+    /// Encode a pure trait method without a body as an abstract function, whose result is only
+    /// constrained by the invariants of the trait. The permissions to the arguments in its
+    /// precondition make the result depend on the values that they point to.
+    fn encode_abstract_pure_function(
+        &self,
+        proc_def_id: ProcedureDefId,
+    ) -> SpannedEncodingResult<vir::Function> {
+        let tcx = self.env.tcx();
+        let span = tcx.def_span(proc_def_id);
+        let has_contract = self.get_procedure_specs(proc_def_id)
+            .map_or(false, |spec| !spec.pres.is_empty() || !spec.posts.is_empty());
+        if has_contract {
+            return Err(SpannedEncodingError::unsupported(
+                format!(
+                    "specifications of the pure function {} without a body are not supported",
+                    self.env.get_item_name(proc_def_id)
+                ),
+                span,
+            ));
+        }
+        let fn_sig = tcx.fn_sig(proc_def_id).skip_binder();
+        let subst_strings = self.type_substitution_strings().with_span(span)?;
+
+        let mut formal_args = vec![];
+        let mut precondition = vec![];
+        for (index, &arg_ty) in fn_sig.inputs().iter().enumerate() {
+            let var_type = self.encode_value_or_ref_type(self.resolve_typaram(arg_ty))
+                .with_span(span)?
+                .patch(&subst_strings);
+            let formal_arg = vir::LocalVar::new(format!("_{}", index + 1), var_type);
+            let fraction = if let ty::TyKind::Ref(_, _, hir::Mutability::Not) = arg_ty.kind() {
+                vir::PermAmount::Read
+            } else {
+                vir::PermAmount::Write
+            };
+            precondition.extend(vir::Expr::pred_permission(formal_arg.clone().into(), fraction));
+            formal_args.push(formal_arg);
+        }
+
+        let return_ty = self.resolve_typaram(fn_sig.output());
+        let return_type = self.encode_value_type(return_ty).with_span(span)?;
+        let pure_fn_return_variable = vir::LocalVar::new("__result", return_type.clone());
+        let mut postcondition = vec![];
+        if config::check_overflows() {
+            postcondition.extend(
+                self.encode_type_bounds(&vir::Expr::local(pure_fn_return_variable), return_ty)
+            );
+        } else if config::encode_unsigned_num_constraint() {
+            if let ty::TyKind::Uint(_) = return_ty.kind() {
+                postcondition.push(vir::Expr::le_cmp(0.into(), pure_fn_return_variable.into()));
+            }
+        }
+        let res_value_range_pos = self.error_manager().register(
+            span,
+            ErrorCtxt::PureFunctionPostconditionValueRangeOfResult,
+        );
+
+        Ok(vir::Function {
+            name: self.encode_item_name(proc_def_id),
+            formal_args,
+            return_type,
+            pres: precondition,
+            posts: postcondition
+                .into_iter()
+                .map(|post| post.set_default_pos(res_value_range_pos))
+                .collect(),
+            body: None,
+        })
+    }
+
     fn patch_pure_post_with_mirror_call(&self, function: vir::Function)
         -> EncodingResult<vir::Function>
     {
@@ -1444,13 +1669,20 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
         proc_def_id: ProcedureDefId,
     ) -> SpannedEncodingResult<(String, vir::Type)> {
         let wrapper_def_id = self.get_wrapper_def_id(proc_def_id);
-        let procedure = self.env.get_procedure(wrapper_def_id);
-
         assert!(
             self.is_pure(proc_def_id),
             "procedure is not marked as pure: {:?}",
             proc_def_id
         );
+        if !self.env.tcx().is_mir_available(wrapper_def_id) {
+            // e.g. a trait method without a body whose implementation is not statically known
+            let return_ty = self.env.tcx().fn_sig(proc_def_id).skip_binder().output();
+            let return_type = self.encode_value_type(self.resolve_typaram(return_ty))
+                .with_span(self.env.tcx().def_span(proc_def_id))?;
+            self.queue_pure_function_encoding(proc_def_id);
+            return Ok((self.encode_item_name(proc_def_id), return_type));
+        }
+        let procedure = self.env.get_procedure(wrapper_def_id);
 
         let pure_function_encoder =
             PureFunctionEncoder::new(self, proc_def_id, procedure.get_mir(), false);
@@ -1542,6 +1774,7 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
                 "Encoding: {} from {:?} ({})",
                 proc_name, proc_span, proc_def_path
             );
+            let first_error_index = self.encoding_errors.borrow().len();
             let is_pure_function = self.is_pure(proc_def_id);
             if is_pure_function {
                 self.encode_pure_function_def(proc_def_id, substs);
@@ -1559,6 +1792,13 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
                     }
                 }
             }
+            let new_errors = self.encoding_errors.borrow()[first_error_index..].to_vec();
+            if !new_errors.is_empty() {
+                self.procedure_encoding_errors.borrow_mut()
+                    .entry(proc_def_id)
+                    .or_default()
+                    .extend(new_errors);
+            }
         }
     }
 
@@ -4,11 +4,12 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use prusti_common::config;
 use prusti_common::vir::Position;
 use std::collections::HashMap;
 use rustc_span::source_map::SourceMap;
 use rustc_span::MultiSpan;
-use viper::VerificationError;
+use viper::{Counterexample, VerificationError};
 use prusti_interface::PrustiError;
 use log::debug;
 
@@ -27,6 +28,41 @@ pub enum PanicCause {
     Unreachable,
     /// Caused by an unimplemented!()
     Unimplemented,
+    /// Caused by a todo!()
+    Todo,
+    /// Caused by an `unwrap()` or `expect(..)` on an `Option` or a `Result`
+    Unwrap,
+    /// Caused by an index out of the bounds of an array or slice
+    IndexOutOfBounds,
+    /// Caused by a division or a remainder by zero
+    DivisionByZero,
+    /// Caused by an arithmetic overflow
+    Overflow,
+}
+
+impl PanicCause {
+    /// The category of the panic, used to disable its check with `DISABLED_PANIC_CHECKS`.
+    pub fn category(&self) -> &'static str {
+        match self {
+            PanicCause::Generic => "other",
+            PanicCause::Panic => "panic",
+            PanicCause::Assert => "assert",
+            PanicCause::DebugAssert => "debug_assert",
+            PanicCause::Unreachable => "unreachable",
+            PanicCause::Unimplemented => "unimplemented",
+            PanicCause::Todo => "todo",
+            PanicCause::Unwrap => "unwrap",
+            PanicCause::IndexOutOfBounds => "index_out_of_bounds",
+            PanicCause::DivisionByZero => "division_by_zero",
+            PanicCause::Overflow => "overflow",
+        }
+    }
+
+    /// Whether the absence of this kind of panic should be checked.
+    pub fn is_checked(&self) -> bool {
+        config::check_panics() && !config::disabled_panic_checks().iter()
+            .any(|category| category == self.category())
+    }
 }
 
 /// In case of verification error, this enum will contain additional information
@@ -41,6 +77,12 @@ pub enum ErrorCtxt {
     AssertMethodPostcondition,
     /// A Viper `assert expr` that encodes the call of a Rust procedure with precondition `expr`
     AssertMethodPostconditionTypeInvariants,
+    /// A Viper `assert expr` that encodes the invariant `expr` of a trait at the end of a method
+    /// Arguments: the name of the trait, the span of the invariant
+    AssertTraitInvariantOnExit(String, MultiSpan),
+    /// A Viper `assert expr` that encodes the invariant `expr` of a trait before a method call
+    /// Arguments: the name of the trait, the span of the invariant
+    AssertTraitInvariantOnCall(String, MultiSpan),
     /// A Viper `exhale expr` that encodes the end of a Rust procedure with postcondition `expr`
     ExhaleMethodPostcondition,
     /// A Viper `exhale expr` that exhales the permissions of a loop invariant `expr`
@@ -50,8 +92,8 @@ pub enum ErrorCtxt {
     AssertLoopInvariantOnEntry,
     AssertLoopInvariantAfterIteration,
     /// A Viper `assert false` that encodes the failure (panic) of an `assert` Rust terminator
-    /// Arguments: the message of the Rust assertion
-    AssertTerminator(String),
+    /// Arguments: the cause of the panic and the message of the Rust assertion
+    AssertTerminator(PanicCause, String),
     /// A Viper `assert false` that encodes an `abort` Rust terminator
     AbortTerminator,
     /// A Viper `assert false` that encodes an `unreachable` Rust terminator
@@ -70,8 +112,8 @@ pub enum ErrorCtxt {
     PureFunctionPostconditionValueRangeOfResult,
     /// A Viper function with `false` precondition that encodes the failure (panic) of an
     /// `assert` Rust terminator in a Rust pure function.
-    /// Arguments: the message of the Rust assertion
-    PureFunctionAssertTerminator(String),
+    /// Arguments: the cause of the panic and the message of the Rust assertion
+    PureFunctionAssertTerminator(PanicCause, String),
     /// A generic expression
     GenericExpression,
     /// A generic statement
@@ -96,12 +138,76 @@ pub enum ErrorCtxt {
     Unsupported(String),
 }
 
+impl ErrorCtxt {
+    /// The kind of the context, which is used to classify errors in machine-readable reports.
+    /// Panics are classified by their category, as used in `DISABLED_PANIC_CHECKS`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ErrorCtxt::Panic(cause)
+            | ErrorCtxt::AssertTerminator(cause, _)
+            | ErrorCtxt::PureFunctionAssertTerminator(cause, _)
+            | ErrorCtxt::PanicInPureFunction(cause) => cause.category(),
+            ErrorCtxt::ExhaleMethodPrecondition => "exhale_method_precondition",
+            ErrorCtxt::AssertMethodPostcondition => "assert_method_postcondition",
+            ErrorCtxt::AssertMethodPostconditionTypeInvariants => {
+                "assert_method_postcondition_type_invariants"
+            }
+            ErrorCtxt::AssertTraitInvariantOnExit(..) => "assert_trait_invariant_on_exit",
+            ErrorCtxt::AssertTraitInvariantOnCall(..) => "assert_trait_invariant_on_call",
+            ErrorCtxt::ExhaleMethodPostcondition => "exhale_method_postcondition",
+            ErrorCtxt::ExhaleLoopInvariantOnEntry => "exhale_loop_invariant_on_entry",
+            ErrorCtxt::ExhaleLoopInvariantAfterIteration => {
+                "exhale_loop_invariant_after_iteration"
+            }
+            ErrorCtxt::AssertLoopInvariantOnEntry => "assert_loop_invariant_on_entry",
+            ErrorCtxt::AssertLoopInvariantAfterIteration => "assert_loop_invariant_after_iteration",
+            ErrorCtxt::AbortTerminator => "abort_terminator",
+            ErrorCtxt::UnreachableTerminator => "unreachable_terminator",
+            ErrorCtxt::Unexpected => "unexpected",
+            ErrorCtxt::PureFunctionDefinition => "pure_function_definition",
+            ErrorCtxt::PureFunctionCall => "pure_function_call",
+            ErrorCtxt::StubPureFunctionCall => "stub_pure_function_call",
+            ErrorCtxt::PureFunctionPostconditionValueRangeOfResult => {
+                "pure_function_postcondition_value_range_of_result"
+            }
+            ErrorCtxt::GenericExpression => "generic_expression",
+            ErrorCtxt::GenericStatement => "generic_statement",
+            ErrorCtxt::PackageMagicWandForPostcondition => "package_magic_wand_for_postcondition",
+            ErrorCtxt::ApplyMagicWandOnExpiry => "apply_magic_wand_on_expiry",
+            ErrorCtxt::DivergingCallInPureFunction => "diverging_call_in_pure_function",
+            ErrorCtxt::AssertMethodPreconditionWeakening(_) => {
+                "assert_method_precondition_weakening"
+            }
+            ErrorCtxt::AssertMethodPostconditionStrengthening(_) => {
+                "assert_method_postcondition_strengthening"
+            }
+            ErrorCtxt::TypeCast => "type_cast",
+            ErrorCtxt::Unsupported(_) => "unsupported",
+        }
+    }
+}
+
+/// A block of an encoded method that has several successors, used to explain which branches have
+/// been taken on the way to a verification error.
+#[derive(Clone, Debug)]
+pub struct BranchPoint {
+    /// The Viper variable that is true if the block has been executed.
+    pub executed_flag: String,
+    /// For each successor, the Viper variable that is true if the successor has been executed,
+    /// and a description of the branch, e.g. "took the `else` branch at line 12".
+    pub branches: Vec<(String, String)>,
+}
+
 /// The error manager
 #[derive(Clone)]
 pub struct ErrorManager<'tcx> {
     codemap: &'tcx SourceMap,
     source_span: HashMap<u64, MultiSpan>,
     error_contexts: HashMap<u64, ErrorCtxt>,
+    /// For each encoded method, the names of the Rust variables of its Viper variables.
+    variable_names: HashMap<String, HashMap<String, String>>,
+    /// For each encoded method, its blocks that have several successors.
+    branch_points: HashMap<String, Vec<BranchPoint>>,
     next_pos_id: u64,
 }
 
@@ -112,6 +218,8 @@ impl<'tcx> ErrorManager<'tcx>
             codemap,
             source_span: HashMap::new(),
             error_contexts: HashMap::new(),
+            variable_names: HashMap::new(),
+            branch_points: HashMap::new(),
             next_pos_id: 1,
         }
     }
@@ -155,7 +263,121 @@ impl<'tcx> ErrorManager<'tcx>
         self.error_contexts.insert(pos.id(), error_ctxt);
     }
 
-    pub fn translate_verification_error(&self, ver_error: &VerificationError) -> PrustiError {
+    /// Register the names of the Rust variables that are encoded as the given Viper variables of
+    /// a method, so that they can be used to report counterexamples.
+    pub fn register_variable_names(
+        &mut self,
+        method_name: String,
+        variable_names: HashMap<String, String>,
+    ) {
+        self.variable_names.insert(method_name, variable_names);
+    }
+
+    /// Register the blocks of a method that have several successors, so that they can be used
+    /// to explain verification errors.
+    pub fn register_branch_points(&mut self, method_name: String, branch_points: Vec<BranchPoint>) {
+        self.branch_points.insert(method_name, branch_points);
+    }
+
+    /// Translate a verification error of the Viper method `method_name` (if any) to a Prusti
+    /// error, reporting the counterexample of the error and the branches that lead to it as notes.
+    pub fn translate_verification_error(
+        &self,
+        ver_error: &VerificationError,
+        method_name: Option<&str>,
+    ) -> PrustiError {
+        let mut prusti_error = self.translate_verification_error_message(ver_error);
+        if let Some((counterexample, method_name)) = ver_error.counterexample.as_ref().zip(method_name) {
+            if config::produce_counterexample() {
+                if let Some(note) = self.translate_counterexample(counterexample, method_name) {
+                    prusti_error = prusti_error.add_note(note, None);
+                }
+            }
+            if config::explain_verification_errors() {
+                if let Some(note) = self.explain_execution_path(counterexample, method_name) {
+                    prusti_error = prusti_error.add_note(note, None);
+                }
+            }
+        }
+        prusti_error
+    }
+
+    /// The kind of the context in which the position of a verification error was registered, or
+    /// `unregistered` if there is none.
+    pub fn translate_error_kind(&self, ver_error: &VerificationError) -> &'static str {
+        ver_error.pos_id.as_ref()
+            .and_then(|pos_id| pos_id.parse::<u64>().ok())
+            .and_then(|pos_id| self.error_contexts.get(&pos_id))
+            .map(|error_ctxt| error_ctxt.kind())
+            .unwrap_or("unregistered")
+    }
+
+    /// Express the values of a counterexample in terms of the Rust variables of a method, e.g.
+    /// `counterexample: x = 3, v.len = 0`.
+    fn translate_counterexample(
+        &self,
+        counterexample: &Counterexample,
+        method_name: &str,
+    ) -> Option<String> {
+        let variable_names = self.variable_names.get(method_name)?;
+        let values: Vec<String> = counterexample.entries.iter()
+            .filter_map(|(path, value)| {
+                let mut components = path.split('.');
+                let mut rust_path = variable_names.get(components.next()?)?.clone();
+                for field in components {
+                    if let Some(field_name) = field.strip_prefix("f$") {
+                        rust_path.push('.');
+                        rust_path.push_str(field_name.strip_prefix("tuple_").unwrap_or(field_name));
+                    } else if let Some(variant) = field.strip_prefix("enum_") {
+                        rust_path = format!("({} as {})", rust_path, variant);
+                    } else if !field.starts_with("val_") {
+                        // Fields like `val_int` and `val_ref` only wrap values and references.
+                        rust_path.push('.');
+                        rust_path.push_str(field);
+                    }
+                }
+                Some(format!("{} = {}", rust_path, value))
+            })
+            .collect();
+        if values.is_empty() {
+            None
+        } else {
+            Some(format!("counterexample: {}", values.join(", ")))
+        }
+    }
+
+    /// List the branches of a method that have been taken on the way to a verification error,
+    /// according to the execution flags of its blocks in the counterexample of the error, e.g.
+    /// `explanation: took the `else` branch at line 12, entered an iteration of the loop at line 15`.
+    fn explain_execution_path(
+        &self,
+        counterexample: &Counterexample,
+        method_name: &str,
+    ) -> Option<String> {
+        let branch_points = self.branch_points.get(method_name)?;
+        let has_been_executed = |flag: &str| {
+            counterexample.entries.iter().any(|(path, value)| path == flag && value == "true")
+        };
+        let decisions: Vec<&str> = branch_points.iter()
+            .filter(|branch_point| has_been_executed(&branch_point.executed_flag))
+            .filter_map(|branch_point| {
+                let mut taken = branch_point.branches.iter()
+                    .filter(|(flag, _)| has_been_executed(flag));
+                match (taken.next(), taken.next()) {
+                    (Some((_, description)), None) => Some(description.as_str()),
+                    // Several branches can be taken in different iterations of a loop.
+                    _ => None,
+                }
+            })
+            .collect();
+        if decisions.is_empty() {
+            None
+        } else {
+            Some(format!("explanation: {}", decisions.join(", ")))
+        }
+    }
+
+    fn translate_verification_error_message(&self, ver_error: &VerificationError) -> PrustiError {
         debug!("Verification error: {:?}", ver_error);
         let opt_pos_id: Option<u64> = match ver_error.pos_id {
             Some(ref viper_pos_id) => {
@@ -271,7 +493,63 @@ impl<'tcx> ErrorManager<'tcx>
                     .set_failing_assertion(opt_cause_span)
             }
 
-            ("assert.failed:assertion.false", ErrorCtxt::AssertTerminator(ref message)) => {
+            ("assert.failed:assertion.false", ErrorCtxt::Panic(PanicCause::Todo)) => {
+                PrustiError::verification("todo!(..) statement might be reachable", error_span)
+                    .set_failing_assertion(opt_cause_span)
+                    .set_help(
+                        "To ignore todo!(..) statements during development, add `todo` to the \
+                        configuration parameter DISABLED_PANIC_CHECKS."
+                    )
+            }
+
+            ("assert.failed:assertion.false", ErrorCtxt::Panic(PanicCause::Unwrap)) => {
+                PrustiError::verification(
+                    "unwrap() or expect(..) might be called on a `None` or `Err` value",
+                    error_span
+                ).set_failing_assertion(opt_cause_span)
+                    .set_help(
+                        "Check that the value is a `Some` or an `Ok` before unwrapping it, \
+                        for example with a match or a precondition."
+                    )
+            }
+
+            (
+                "assert.failed:assertion.false",
+                ErrorCtxt::AssertTerminator(PanicCause::IndexOutOfBounds, _),
+            ) => {
+                PrustiError::verification("the index might be out of bounds", error_span)
+                    .set_failing_assertion(opt_cause_span)
+                    .set_help(
+                        "Check that the index is smaller than the length before indexing, \
+                        for example with a precondition."
+                    )
+            }
+
+            (
+                "assert.failed:assertion.false",
+                ErrorCtxt::AssertTerminator(PanicCause::DivisionByZero, ref message),
+            ) => {
+                PrustiError::verification(format!("assertion might fail with \"{}\"", message), error_span)
+                    .set_failing_assertion(opt_cause_span)
+                    .set_help(
+                        "Check that the divisor is not zero, for example with a precondition."
+                    )
+            }
+
+            (
+                "assert.failed:assertion.false",
+                ErrorCtxt::AssertTerminator(PanicCause::Overflow, ref message),
+            ) => {
+                PrustiError::verification(format!("assertion might fail with \"{}\"", message), error_span)
+                    .set_failing_assertion(opt_cause_span)
+                    .set_help(
+                        "Bound the operands, for example with a precondition, so that the result \
+                        fits into its type. To ignore overflows, add `overflow` to the \
+                        configuration parameter DISABLED_PANIC_CHECKS."
+                    )
+            }
+
+            ("assert.failed:assertion.false", ErrorCtxt::AssertTerminator(_, ref message)) => {
                 PrustiError::verification(format!("assertion might fail with \"{}\"", message), error_span)
                     .set_failing_assertion(opt_cause_span)
             }
@@ -426,7 +704,31 @@ impl<'tcx> ErrorManager<'tcx>
 
             (
                 "application.precondition:assertion.false",
-                ErrorCtxt::PureFunctionAssertTerminator(ref message),
+                ErrorCtxt::PanicInPureFunction(PanicCause::Todo),
+            ) => {
+                PrustiError::verification(
+                    "todo!(..) statement in pure function might be reachable",
+                    error_span
+                ).push_primary_span(opt_cause_span)
+            }
+
+            (
+                "application.precondition:assertion.false",
+                ErrorCtxt::PureFunctionAssertTerminator(PanicCause::IndexOutOfBounds, _),
+            ) => {
+                PrustiError::verification(
+                    "the index in pure function might be out of bounds",
+                    error_span
+                ).set_failing_assertion(opt_cause_span)
+                    .set_help(
+                        "Check that the index is smaller than the length before indexing, \
+                        for example with a precondition."
+                    )
+            }
+
+            (
+                "application.precondition:assertion.false",
+                ErrorCtxt::PureFunctionAssertTerminator(_, ref message),
             ) => {
                 PrustiError::verification(
                     format!("assertion might fail with \"{}\"", message),
@@ -461,6 +763,35 @@ impl<'tcx> ErrorManager<'tcx>
                 ).set_failing_assertion(opt_cause_span)
             },
 
+            (
+                "assert.failed:assertion.false",
+                ErrorCtxt::AssertTraitInvariantOnExit(ref trait_name, ref invariant_span),
+            ) => {
+                PrustiError::verification(
+                    format!(
+                        "the invariant of trait `{}` might not hold at the end of the method.",
+                        trait_name
+                    ),
+                    error_span
+                ).set_failing_assertion(opt_cause_span)
+                    .add_note("the invariant of the trait is declared here", Some(invariant_span.clone()))
+                    .set_help("Every function that takes a reference to an implementation must preserve them.")
+            },
+
+            (
+                "assert.failed:assertion.false",
+                ErrorCtxt::AssertTraitInvariantOnCall(ref trait_name, ref invariant_span),
+            ) => {
+                PrustiError::verification(
+                    format!(
+                        "the invariant of trait `{}` might not hold before the method call.",
+                        trait_name
+                    ),
+                    error_span
+                ).set_failing_assertion(opt_cause_span)
+                    .add_note("the invariant of the trait is declared here", Some(invariant_span.clone()))
+            },
+
             ("fold.failed:assertion.false", ErrorCtxt::PackageMagicWandForPostcondition) |
             ("fold.failed:assertion.false", ErrorCtxt::AssertMethodPostconditionTypeInvariants) => {
                 PrustiError::verification(
@@ -12,9 +12,16 @@ use prusti_interface::environment::{BasicBlockIndex, PermissionForest, Procedure
 use prusti_interface::utils;
 use rustc_middle::{mir, ty};
 use log::{trace, debug};
+use std::collections::HashSet;
 
 pub enum LoopEncoderError {
+    /// The loop invariant is in a conditional branch of the loop (loop head).
     LoopInvariantInBranch(BasicBlockIndex),
+    /// The loop invariant can be skipped by a `continue` (loop head, source of the `continue`).
+    LoopInvariantAfterContinue(BasicBlockIndex, BasicBlockIndex),
+    /// The loop invariants are separated by other statements (loop head, blocks after which
+    /// each loop invariant is type-checked).
+    AmbiguousLoopInvariant(BasicBlockIndex, Vec<BasicBlockIndex>),
 }
 
 pub struct LoopEncoder<'p, 'tcx: 'p> {
@@ -138,7 +145,6 @@ impl<'p, 'tcx: 'p> LoopEncoder<'p, 'tcx> {
         trace!("get_loop_special_blocks: {:?}", loop_head);
         let loop_info = self.loops();
         debug_assert!(loop_info.is_loop_head(loop_head));
-        let loop_depth = loop_info.get_loop_head_depth(loop_head);
 
         let loop_body: Vec<BasicBlockIndex> = loop_info
             .get_loop_body(loop_head)
@@ -147,27 +153,144 @@ impl<'p, 'tcx: 'p> LoopEncoder<'p, 'tcx> {
             .cloned()
             .collect();
 
-        let loop_exit_blocks = loop_info.get_loop_exit_blocks(loop_head);
-        let before_invariant_block: BasicBlockIndex = loop_body
+        // The blocks that lead to the type-checking of a `body_invariant!(..)` of this loop.
+        // Invariants of nested loops and other specifications (e.g. of closures) are skipped.
+        let invariant_blocks: Vec<BasicBlockIndex> = loop_body
             .iter()
-            .find(|&&bb| {
-                loop_info.get_loop_depth(bb) == loop_depth
+            .filter(|&&bb| {
+                loop_info.get_loop_head(bb) == Some(loop_head)
                     && self.mir()[bb].terminator().successors().any(|&succ_bb| {
                         self.procedure.is_reachable_block(succ_bb)
-                            && self.procedure.is_spec_block(succ_bb)
+                            && self.leads_to_loop_invariant(succ_bb)
                     })
             })
             .cloned()
-            .unwrap_or_else(|| loop_exit_blocks.get(0).cloned().unwrap_or(loop_head));
+            .collect();
+        debug!("invariant blocks of loop {:?}: {:?}", loop_head, invariant_blocks);
+
+        let before_invariant_block = if let Some(&first_invariant_block) = invariant_blocks.first() {
+            if !self.are_adjacent_invariant_blocks(&invariant_blocks) {
+                return Err(LoopEncoderError::AmbiguousLoopInvariant(loop_head, invariant_blocks));
+            }
+            first_invariant_block
+        } else {
+            // Without a loop invariant, pick the loop guard (if any) or the loop head.
+            loop_info
+                .get_loop_exit_blocks(loop_head)
+                .get(0)
+                .cloned()
+                .filter(|&bb| !loop_info.is_conditional_branch(loop_head, bb))
+                .unwrap_or(loop_head)
+        };
 
         if loop_info.is_conditional_branch(loop_head, before_invariant_block) {
             debug!(
                 "{:?} is conditional branch in loop {:?}",
                 before_invariant_block, loop_head
             );
-            return Err(LoopEncoderError::LoopInvariantInBranch(loop_head));
+            // If the invariant can reach a back edge that skips it, the invariant is in a branch.
+            // Otherwise, the back edges that skip it are `continue` statements before it.
+            let reachable_blocks = self.get_forward_reachable_blocks(before_invariant_block);
+            let skipping_back_edge_sources: Vec<_> = loop_info
+                .get_back_edge_sources(loop_head)
+                .into_iter()
+                .filter(|&bb| !loop_info.is_dominated_by(bb, before_invariant_block))
+                .collect();
+            let is_in_branch = skipping_back_edge_sources
+                .iter()
+                .any(|bb| reachable_blocks.contains(bb));
+            return match skipping_back_edge_sources.first() {
+                Some(&continue_block) if !is_in_branch => {
+                    Err(LoopEncoderError::LoopInvariantAfterContinue(loop_head, continue_block))
+                }
+                _ => Err(LoopEncoderError::LoopInvariantInBranch(loop_head)),
+            };
         }
 
         Ok(before_invariant_block)
     }
+
+    /// Does the block lead, through specification blocks only, to the type-checking of a
+    /// loop invariant?
+    fn leads_to_loop_invariant(&self, bbi: BasicBlockIndex) -> bool {
+        let mut visited = HashSet::new();
+        let mut to_visit = vec![bbi];
+        while let Some(curr_bb) = to_visit.pop() {
+            if !self.procedure.is_spec_block(curr_bb) || !visited.insert(curr_bb) {
+                continue;
+            }
+            if self.procedure.is_loop_invariant_block(curr_bb) {
+                return true;
+            }
+            to_visit.extend(self.mir()[curr_bb].terminator().successors().cloned());
+        }
+        false
+    }
+
+    /// Get the blocks reachable from the given one without following back edges.
+    fn get_forward_reachable_blocks(&self, bbi: BasicBlockIndex) -> HashSet<BasicBlockIndex> {
+        let loop_info = self.loops();
+        let mut reachable = HashSet::new();
+        let mut to_visit = vec![bbi];
+        while let Some(curr_bb) = to_visit.pop() {
+            if !reachable.insert(curr_bb) {
+                continue;
+            }
+            for &succ_bb in self.mir()[curr_bb].terminator().successors() {
+                if !loop_info.back_edges.contains(&(curr_bb, succ_bb)) {
+                    to_visit.push(succ_bb);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Check that consecutive loop invariants are separated only by statements that do not
+    /// affect the state of the program (e.g. `StorageDead`), so that they can all be checked at
+    /// the position of the first one.
+    fn are_adjacent_invariant_blocks(&self, invariant_blocks: &[BasicBlockIndex]) -> bool {
+        invariant_blocks.windows(2).all(|pair| {
+            let (from_bb, to_bb) = (pair[0], pair[1]);
+            let mut curr_bb = from_bb;
+            loop {
+                let successors: Vec<_> = self.mir()[curr_bb]
+                    .terminator()
+                    .successors()
+                    .filter(|&&bb| self.procedure.is_reachable_block(bb))
+                    .filter(|&&bb| !self.procedure.is_spec_block(bb))
+                    .cloned()
+                    .collect();
+                if successors.len() != 1 {
+                    return false;
+                }
+                curr_bb = successors[0];
+                if !self.has_only_trivial_statements(curr_bb) {
+                    return false;
+                }
+                if curr_bb == to_bb {
+                    return true;
+                }
+                match self.mir()[curr_bb].terminator().kind {
+                    mir::TerminatorKind::Goto { .. }
+                    | mir::TerminatorKind::FalseEdge { .. }
+                    | mir::TerminatorKind::FalseUnwind { .. } => {}
+                    _ => return false,
+                }
+            }
+        })
+    }
+
+    /// Check that the statements of the block do not affect the state of the program, apart from
+    /// the assignment of constants used to desugar specifications.
+    fn has_only_trivial_statements(&self, bbi: BasicBlockIndex) -> bool {
+        self.mir()[bbi].statements.iter().all(|stmt| match stmt.kind {
+            mir::StatementKind::StorageLive(_)
+            | mir::StatementKind::StorageDead(_)
+            | mir::StatementKind::FakeRead(..)
+            | mir::StatementKind::AscribeUserType(..)
+            | mir::StatementKind::Nop => true,
+            mir::StatementKind::Assign(box (_, mir::Rvalue::Use(mir::Operand::Constant(_)))) => true,
+            _ => false,
+        })
+    }
 }
@@ -753,6 +753,7 @@ impl<'p, 'v: 'p, 'tcx: 'v> MirEncoder<'p, 'v, 'tcx> {
         match &macro_names_str[..] {
             ["core::macros::panic", "std::unimplemented", ..] => PanicCause::Unimplemented,
             ["core::macros::panic", "std::unreachable", ..] => PanicCause::Unreachable,
+            ["core::macros::panic", "std::todo", ..] => PanicCause::Todo,
             ["std::assert", "std::debug_assert", ..] => PanicCause::DebugAssert,
             ["std::assert", ..] => PanicCause::Assert,
             ["std::panic", "std::assert", "std::debug_assert", ..] => PanicCause::DebugAssert,
@@ -761,4 +762,21 @@ impl<'p, 'v: 'p, 'tcx: 'v> MirEncoder<'p, 'v, 'tcx> {
             _ => PanicCause::Generic,
         }
     }
+
+    /// Return the cause and the message of an `Assert` terminator
+    pub fn encode_assert_cause(&self, msg: &mir::AssertMessage<'tcx>) -> (PanicCause, String) {
+        match msg {
+            // The description of a bounds check is not available without its operands
+            mir::AssertKind::BoundsCheck { .. } => {
+                (PanicCause::IndexOutOfBounds, "index out of bounds".to_string())
+            }
+            mir::AssertKind::Overflow(..) | mir::AssertKind::OverflowNeg(..) => {
+                (PanicCause::Overflow, msg.description().to_string())
+            }
+            mir::AssertKind::DivisionByZero(..) | mir::AssertKind::RemainderByZero(..) => {
+                (PanicCause::DivisionByZero, msg.description().to_string())
+            }
+            _ => (PanicCause::Generic, msg.description().to_string()),
+        }
+    }
 }
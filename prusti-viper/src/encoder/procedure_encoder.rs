@@ -7,7 +7,7 @@
 use crate::encoder::borrows::ProcedureContract;
 use crate::encoder::builtin_encoder::BuiltinMethodKind;
 use crate::encoder::errors::{
-    SpannedEncodingError, ErrorCtxt, PanicCause, EncodingError, WithSpan, RunIfErr,
+    SpannedEncodingError, ErrorCtxt, PanicCause, EncodingError, WithSpan, RunIfErr, BranchPoint,
     EncodingResult, SpannedEncodingResult
 };
 use crate::encoder::foldunfold;
@@ -76,7 +76,6 @@ pub struct ProcedureEncoder<'p, 'v: 'p, 'tcx: 'v> {
     loop_encoder: LoopEncoder<'p, 'tcx>,
     auxiliary_local_vars: HashMap<String, vir::Type>,
     mir_encoder: MirEncoder<'p, 'v, 'tcx>,
-    check_panics: bool,
     check_foldunfold_state: bool,
     polonius_info: Option<PoloniusInfo<'p, 'tcx>>,
     procedure_contract: Option<ProcedureContract<'tcx>>,
@@ -142,7 +141,6 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
             loop_encoder: LoopEncoder::new(procedure, tcx),
             auxiliary_local_vars: HashMap::new(),
             mir_encoder: mir_encoder,
-            check_panics: config::check_panics(),
             check_foldunfold_state: config::check_foldunfold_state(),
             polonius_info: None,
             procedure_contract: None,
@@ -337,6 +335,25 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
                 .add_formal_return(&name, vir::Type::TypedRef(type_name))
         }
 
+        // Remember the Rust names of the variables, to report counterexamples
+        let mut variable_names: HashMap<String, String> = self.mir.var_debug_info.iter()
+            .filter_map(|var_debug_info| match var_debug_info.value {
+                mir::VarDebugInfoContents::Place(place) if place.projection.is_empty() => Some((
+                    self.mir_encoder.encode_local_var_name(place.local),
+                    var_debug_info.name.to_ident_string(),
+                )),
+                _ => None,
+            })
+            .collect();
+        variable_names.insert(
+            self.mir_encoder.encode_local_var_name(mir::RETURN_PLACE),
+            "result".to_string(),
+        );
+        self.encoder.error_manager().register_variable_names(
+            self.cfg_method.name(),
+            variable_names,
+        );
+
         // Preprocess loops
         for bbi in self.procedure.get_reachable_nonspec_cfg_blocks() {
             if self.loop_encoder.loops().is_loop_head(bbi) {
@@ -347,6 +364,25 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
                             self.get_loop_span(loop_head),
                         ));
                     }
+                    Err(LoopEncoderError::LoopInvariantAfterContinue(loop_head, _)) => {
+                        return Err(SpannedEncodingError::incorrect(
+                            "the loop invariant cannot be placed after a `continue` of the \
+                            same loop, because the `continue` would skip it",
+                            self.get_loop_span(loop_head),
+                        ));
+                    }
+                    Err(LoopEncoderError::AmbiguousLoopInvariant(_, invariant_blocks)) => {
+                        return Err(SpannedEncodingError::incorrect(
+                            "the position of the loop invariant is ambiguous, because the \
+                            `body_invariant!(..)` of the loop are separated by other statements",
+                            MultiSpan::from_spans(
+                                invariant_blocks
+                                    .iter()
+                                    .map(|&bb| self.mir_encoder.get_span_of_basic_block(bb))
+                                    .collect()
+                            ),
+                        ));
+                    }
                     Ok(loop_inv_bbi) => {
                         self.cached_loop_invariant_block.insert(bbi, loop_inv_bbi);
                     }
@@ -399,6 +435,15 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
                 .insert(bbi, executed_flag_var);
         }
 
+        // Remember the branches of the blocks, to explain verification errors
+        if config::explain_verification_errors() {
+            let branch_points = self.collect_branch_points();
+            self.encoder.error_manager().register_branch_points(
+                self.cfg_method.name(),
+                branch_points,
+            );
+        }
+
         // Encode all blocks
         let (opt_body_head, unresolved_edges) = self.encode_blocks_group(
             "",
@@ -956,6 +1001,73 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
         Ok((curr_block, unresolved_edges))
     }
 
+    /// Describe the branches of the blocks that end with a `SwitchInt` terminator, using the
+    /// execution flags of the blocks to know which branch has been taken.
+    fn collect_branch_points(&self) -> Vec<BranchPoint> {
+        let codemap = self.encoder.env().codemap();
+        let line_of = |span: Span| codemap.lookup_char_pos(span.source_callsite().lo()).line;
+        let loops = self.loop_encoder.loops();
+        let mut branch_points = vec![];
+        for bbi in self.procedure.get_reachable_nonspec_cfg_blocks() {
+            let terminator = self.mir[bbi].terminator();
+            let (switch_ty, targets) = match terminator.kind {
+                TerminatorKind::SwitchInt { switch_ty, ref targets, .. } => (switch_ty, targets),
+                _ => continue,
+            };
+            let line = line_of(terminator.source_info.span);
+            let all_targets: Vec<(Option<u128>, mir::BasicBlock)> = targets.iter()
+                .map(|(value, target)| (Some(value), target))
+                .chain(std::iter::once((None, targets.otherwise())))
+                .collect();
+            // The condition of a loop has a branch that exits the innermost enclosing loop.
+            let opt_exited_loop_head = loops.get_loop_head(bbi).filter(|_| {
+                all_targets.iter().any(|&(_, target)| loops.is_out_edge(bbi, target))
+            });
+            let mut branches: Vec<(mir::BasicBlock, String)> = vec![];
+            for (opt_value, target) in all_targets {
+                if branches.iter().any(|(other_target, _)| *other_target == target) {
+                    continue;
+                }
+                let description = if let Some(loop_head) = opt_exited_loop_head {
+                    let loop_line = line_of(self.mir_encoder.get_span_of_basic_block(loop_head));
+                    if loops.is_out_edge(bbi, target) {
+                        format!("exited the loop at line {}", loop_line)
+                    } else {
+                        format!("entered an iteration of the loop at line {}", loop_line)
+                    }
+                } else if switch_ty.is_bool() {
+                    // The `false` value of a condition is encoded as zero.
+                    if opt_value == Some(0) {
+                        format!("took the `else` branch at line {}", line)
+                    } else {
+                        format!("took the `then` branch at line {}", line)
+                    }
+                } else {
+                    format!(
+                        "took the branch at line {} of the `match` at line {}",
+                        line_of(self.mir_encoder.get_span_of_basic_block(target)),
+                        line,
+                    )
+                };
+                branches.push((target, description));
+            }
+            // Branches to specification blocks have no execution flag.
+            let branches: Vec<(String, String)> = branches.into_iter()
+                .filter_map(|(target, description)| {
+                    self.cfg_block_has_been_executed.get(&target)
+                        .map(|flag| (flag.name.clone(), description))
+                })
+                .collect();
+            if branches.len() > 1 {
+                branch_points.push(BranchPoint {
+                    executed_flag: self.cfg_block_has_been_executed[&bbi].name.clone(),
+                    branches,
+                });
+            }
+        }
+        branch_points
+    }
+
     /// Store a flag that becomes true the first time the block is executed
     fn encode_execution_flag(
         &mut self,
@@ -1880,18 +1992,17 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
                 ..
             } => {
                 if let ty::TyKind::FnDef(def_id, substs) = ty.kind() {
-                    let self_ty = {
-                        // If we are calling a trait method on a struct, self_ty
-                        // is the struct.
-                        let generics = self.encoder.env().tcx().generics_of(*def_id);
-                        if generics.has_self {
-                            Some(substs.type_at(0))
-                        } else {
-                            None
-                        }
+                    // Like in specifications, call the pure method of the implementation if it
+                    // is statically known, so that both encode the same function.
+                    let (def_id, substs) = if self.encoder.is_pure(*def_id) {
+                        self.encoder.resolve_pure_trait_method_call(
+                            self.proc_def_id,
+                            *def_id,
+                            substs,
+                        )
+                    } else {
+                        (*def_id, *substs)
                     };
-
-                    let def_id = *def_id;
                     let full_func_proc_name: &str =
                         &self.encoder.env().tcx().def_path_str(def_id);
                         // &self.encoder.env().tcx().absolute_item_path_str(def_id);
@@ -1935,6 +2046,7 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
                             let panic_cause = self.mir_encoder.encode_panic_cause(
                                 term.source_info
                             );
+                            let is_checked = panic_cause.is_checked();
                             let pos = self
                                 .encoder
                                 .error_manager()
@@ -1943,7 +2055,7 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
                                     ErrorCtxt::Panic(panic_cause)
                                 );
 
-                            if self.check_panics {
+                            if is_checked {
                                 stmts.push(vir::Stmt::comment(format!(
                                     "Rust panic - {}",
                                     panic_message
@@ -2045,7 +2157,7 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
                                         args,
                                         destination,
                                         *cl_def_id,
-                                        self_ty,
+                                        None,
                                     )?);
                                 }
 
@@ -2085,7 +2197,7 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
                                         args,
                                         destination,
                                         def_id,
-                                        self_ty,
+                                        Some(substs),
                                     ).run_if_err(|| cleanup(&self))?
                                 );
                             }
@@ -2141,17 +2253,18 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
                 };
 
                 // Check or assume the assertion
+                let (panic_cause, message) = self.mir_encoder.encode_assert_cause(msg);
                 stmts.push(vir::Stmt::comment(format!(
                     "Rust assertion: {}",
-                    msg.description()
+                    message
                 )));
-                if self.check_panics {
+                if panic_cause.is_checked() {
                     stmts.push(vir::Stmt::Assert(
                         viper_guard,
                         vir::FoldingBehaviour::Stmt,
                         self.encoder.error_manager().register(
                             term.source_info.span,
-                            ErrorCtxt::AssertTerminator(msg.description().to_string()),
+                            ErrorCtxt::AssertTerminator(panic_cause, message),
                         ),
                     ));
                 } else {
@@ -2276,7 +2389,7 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
         mir_args: &[mir::Operand<'tcx>],
         destination: &Option<(mir::Place<'tcx>, BasicBlockIndex)>,
         called_def_id: ProcedureDefId,
-        self_ty: Option<&'tcx ty::TyS<'tcx>>,
+        call_substs: Option<ty::subst::SubstsRef<'tcx>>,
     ) -> SpannedEncodingResult<Vec<vir::Stmt>> {
         let full_func_proc_name = &self
             .encoder
@@ -2365,6 +2478,12 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
         let mut type_invs: HashMap<String, vir::Function> = HashMap::new();
         let mut constant_args = vec![];
 
+        // `unwrap` and `expect` panic unless their receiver is a `Some` or an `Ok`, even if they
+        // have no (extern) specification.
+        let unwrap_condition = operands.first().and_then(|&(_, receiver, receiver_ty, _)| {
+            self.encode_unwrap_condition(called_def_id, receiver, receiver_ty)
+        });
+
         for (mir_arg, arg, arg_ty, encoded_operand) in operands {
             arguments.push(arg.clone());
 
@@ -2474,7 +2593,8 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
 
         let procedure_contract = {
             self.encoder.get_procedure_contract_for_call(
-                self_ty,
+                self.proc_def_id,
+                call_substs,
                 called_def_id,
                 &arguments,
                 target_local,
@@ -2503,6 +2623,26 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
             .encoder
             .error_manager()
             .register(call_site_span, ErrorCtxt::ExhaleMethodPrecondition);
+        if let Some(unwrap_condition) = unwrap_condition {
+            let panic_cause = PanicCause::Unwrap;
+            if panic_cause.is_checked() {
+                let unwrap_pos = self
+                    .encoder
+                    .error_manager()
+                    .register(call_site_span, ErrorCtxt::Panic(panic_cause));
+                stmts.push(vir::Stmt::Assert(
+                    replace_fake_exprs(unwrap_condition),
+                    vir::FoldingBehaviour::Stmt,
+                    unwrap_pos,
+                ));
+            } else {
+                stmts.push(vir::Stmt::comment("This panic will not be checked"));
+                stmts.push(vir::Stmt::Inhale(
+                    replace_fake_exprs(unwrap_condition),
+                    vir::FoldingBehaviour::Stmt,
+                ));
+            }
+        }
         stmts.push(vir::Stmt::Assert(
             replace_fake_exprs(pre_func_spec),
             vir::FoldingBehaviour::Stmt, // TODO: Should be Expr.
@@ -2513,6 +2653,19 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
             vir::FoldingBehaviour::Stmt,
             pos,
         ));
+        for (trait_name, trait_inv_spans, trait_inv) in
+            self.encode_trait_invariants_of_args(&procedure_contract, None)?
+        {
+            let trait_inv_pos = self.encoder.error_manager().register(
+                call_site_span,
+                ErrorCtxt::AssertTraitInvariantOnCall(trait_name, trait_inv_spans),
+            );
+            stmts.push(vir::Stmt::Assert(
+                replace_fake_exprs(trait_inv),
+                vir::FoldingBehaviour::Expr,
+                trait_inv_pos,
+            ));
+        }
         let pre_perm_spec = replace_fake_exprs(pre_type_spec.clone());
         assert!(!pos.is_default());
         stmts.push(vir::Stmt::Exhale(
@@ -2610,6 +2763,14 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
             replace_fake_exprs(post_func_spec),
             vir::FoldingBehaviour::Expr,
         ));
+        for (_, _, trait_inv) in
+            self.encode_trait_invariants_of_args(&procedure_contract, Some(&pre_label))?
+        {
+            stmts.push(vir::Stmt::Inhale(
+                replace_fake_exprs(trait_inv),
+                vir::FoldingBehaviour::Expr,
+            ));
+        }
 
         // Exhale the permissions that were moved into magic wands.
         assert!(!pos.is_default());
@@ -3056,6 +3217,13 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
             start_cfg_block,
             vir::Stmt::Inhale(func_spec, vir::FoldingBehaviour::Expr),
         );
+        let contract = self.procedure_contract().clone();
+        for (_, _, trait_inv) in self.encode_trait_invariants_of_args(&contract, None)? {
+            self.cfg_method.add_stmt(
+                start_cfg_block,
+                vir::Stmt::Inhale(trait_inv, vir::FoldingBehaviour::Expr),
+            );
+        }
         self.cfg_method.add_stmt(
             start_cfg_block,
             vir::Stmt::Label(PRECONDITION_LABEL.to_string()),
@@ -3234,6 +3402,68 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
         Ok(assertion.remove_redundant_old())
     }
 
+    /// Encode the trait invariants that constrain the arguments of the procedure, each
+    /// paired with the name of the trait that declares it and the spans of the invariant.
+    ///
+    /// `Self` is replaced in each invariant by the type of the argument that it constrains, so
+    /// that the calls of trait methods in the invariant are resolved to the methods of the
+    /// implementation whenever it is statically known.
+    ///
+    /// `pre_label` is `Some` when the invariants are encoded in the post-state.
+    fn encode_trait_invariants_of_args(
+        &self,
+        contract: &ProcedureContract<'tcx>,
+        pre_label: Option<&str>,
+    ) -> SpannedEncodingResult<Vec<(String, MultiSpan, vir::Expr)>> {
+        let tcx = self.encoder.env().tcx();
+        let encoded_args: Vec<vir::Expr> = contract
+            .args
+            .iter()
+            .map(|local| self.encode_prusti_local(*local).into())
+            .collect();
+        let mut trait_invs = vec![];
+        let trait_invariants = self.encoder.get_trait_invariants_of_args(contract.def_id);
+        for (index, trait_def_id, self_ty, typed_assertion) in trait_invariants {
+            {
+                // FIXME: this is a hack to support generics. See issue #187.
+                let mut tymap_stack = self.encoder.typaram_repl.borrow_mut();
+                let mut tymap = HashMap::new();
+                tymap.insert(tcx.types.self_param, self_ty);
+                tymap_stack.push(tymap);
+            }
+            let cleanup = |this: &ProcedureEncoder| {
+                // FIXME: this is a hack to support generics. See issue #187.
+                let mut tymap_stack = this.encoder.typaram_repl.borrow_mut();
+                tymap_stack.pop();
+            };
+            let mut assertion = self.encoder.encode_assertion(
+                &typed_assertion,
+                &self.mir,
+                pre_label,
+                &encoded_args[index..=index],
+                None,
+                false,
+                None,
+                ErrorCtxt::GenericExpression,
+            ).run_if_err(|| cleanup(self))?;
+            cleanup(self);
+            if let Some(pre_label) = pre_label {
+                assertion = self.wrap_arguments_into_old(
+                    assertion,
+                    pre_label,
+                    contract,
+                    &encoded_args
+                )?;
+            }
+            let trait_name = tcx.def_path_str(trait_def_id);
+            let spans = MultiSpan::from_spans(
+                typed::Spanned::get_spans(&typed_assertion, &self.mir, tcx)
+            );
+            trait_invs.push((trait_name, spans, assertion));
+        }
+        Ok(trait_invs)
+    }
+
     /// Encode the postcondition with three expressions:
     /// - one for the type encoding
     /// - one for the type invariants
@@ -3808,6 +4038,27 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
             vir::Stmt::Assert(patched_func_spec, vir::FoldingBehaviour::Expr, func_pos),
         );
 
+        // Assert trait invariants
+        self.cfg_method.add_stmt(
+            return_cfg_block,
+            vir::Stmt::comment("Assert trait invariants"),
+        );
+        let trait_invs = self.encode_trait_invariants_of_args(
+            &contract,
+            Some(PRECONDITION_LABEL),
+        )?;
+        for (trait_name, trait_inv_spans, trait_inv) in trait_invs {
+            let trait_inv_pos = self.encoder.error_manager().register(
+                self.mir.span,
+                ErrorCtxt::AssertTraitInvariantOnExit(trait_name, trait_inv_spans),
+            );
+            let patched_trait_inv = self.replace_old_places_with_ghost_vars(None, trait_inv);
+            self.cfg_method.add_stmt(
+                return_cfg_block,
+                vir::Stmt::Assert(patched_trait_inv, vir::FoldingBehaviour::Expr, trait_inv_pos),
+            );
+        }
+
         // Assert type invariants
         self.cfg_method.add_stmt(
             return_cfg_block,
@@ -4116,7 +4367,10 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
                     _,
                     mir::Rvalue::Aggregate(box mir::AggregateKind::Closure(cl_def_id, _), _),
                 )) = stmt.kind {
-                    specs.extend(self.encoder.get_loop_specs(cl_def_id).unwrap().invariant);
+                    // Skip other specifications, such as the ones of closures.
+                    if let Some(loop_specs) = self.encoder.get_loop_specs(cl_def_id) {
+                        specs.extend(loop_specs.invariant);
+                    }
                 }
             }
         }
@@ -4276,6 +4530,40 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
     }
 
     // TODO: What is this?
+    /// If the called method is the `unwrap` or `expect` method of an `Option` or a `Result`,
+    /// encode the condition under which it does not panic: its receiver is a `Some` or an `Ok`.
+    fn encode_unwrap_condition(
+        &self,
+        called_def_id: ProcedureDefId,
+        receiver: Local,
+        receiver_ty: ty::Ty<'tcx>,
+    ) -> Option<vir::Expr> {
+        let tcx = self.encoder.env().tcx();
+        let method_name = tcx.opt_item_name(called_def_id)?.as_str();
+        if &*method_name != "unwrap" && &*method_name != "expect" {
+            return None;
+        }
+        let adt_def = match receiver_ty.kind() {
+            ty::TyKind::Adt(adt_def, _) => adt_def,
+            _ => return None,
+        };
+        let variant_name = match tcx.def_path_str(adt_def.did).as_str() {
+            "std::option::Option" => "Some",
+            "std::result::Result" => "Ok",
+            _ => return None,
+        };
+        let (variant_index, _) = adt_def.variants.iter_enumerated()
+            .find(|(_, variant)| &*variant.ident.as_str() == variant_name)?;
+        let discriminant = self.encoder.encode_discriminant_func_app(
+            vir::Expr::local(self.encode_prusti_local(receiver)),
+            adt_def,
+        );
+        let discr_value: vir::Expr = adt_def.discriminant_for_variant(tcx, variant_index)
+            .val
+            .into();
+        Some(vir::Expr::eq_cmp(discriminant, discr_value))
+    }
+
     fn encode_prusti_local(&self, local: Local) -> vir::LocalVar {
         let var_name = self.locals.get_name(local);
         let type_name = self
@@ -401,6 +401,8 @@ impl<'p, 'v: 'p, 'tcx: 'v> PureFunctionEncoder<'p, 'v, 'tcx> {
 pub(super) struct PureFunctionBackwardInterpreter<'p, 'v: 'p, 'tcx: 'v> {
     encoder: &'p Encoder<'v, 'tcx>,
     mir: &'p mir::Body<'tcx>,
+    /// The procedure (or specification item) whose MIR is interpreted.
+    def_id: DefId,
     mir_encoder: MirEncoder<'p, 'v, 'tcx>,
     /// True if the encoder is currently encoding an assertion and not a pure function body. This
     /// flag is used to distinguish when assert terminators should be translated into `false` and
@@ -422,6 +424,7 @@ impl<'p, 'v: 'p, 'tcx: 'v> PureFunctionBackwardInterpreter<'p, 'v, 'tcx> {
         PureFunctionBackwardInterpreter {
             encoder,
             mir,
+            def_id,
             mir_encoder: MirEncoder::new(encoder, mir, def_id),
             is_encoding_assertion,
         }
@@ -630,7 +633,13 @@ impl<'p, 'v: 'p, 'tcx: 'v> BackwardMirInterpreter<'tcx>
                 ..
             } => {
                 if let ty::TyKind::FnDef(def_id, substs) = ty.kind() {
-                    let def_id = *def_id;
+                    // Call the method of the implementation if it is statically known, e.g.
+                    // because `Self` of a trait invariant is the type of an implementation.
+                    let (def_id, substs) = self.encoder.resolve_pure_trait_method_call(
+                        self.def_id,
+                        *def_id,
+                        substs,
+                    );
                     let full_func_proc_name: &str =
                         &self.encoder.env().tcx().def_path_str(def_id);
                         // &self.encoder.env().tcx().absolute_item_path_str(def_id);
@@ -787,7 +796,7 @@ impl<'p, 'v: 'p, 'tcx: 'v> BackwardMirInterpreter<'tcx>
                         // FIXME: Refactor the common code with the procedure encoder.
 
                         // Encoding of a non-terminating function call
-                        let error_ctxt = match full_func_proc_name {
+                        let (error_ctxt, is_checked) = match full_func_proc_name {
                             "std::rt::begin_panic"
                             | "core::panicking::panic"
                             | "core::panicking::panic_fmt" => {
@@ -798,17 +807,25 @@ impl<'p, 'v: 'p, 'tcx: 'v> BackwardMirInterpreter<'tcx>
                                 let panic_cause = self.mir_encoder.encode_panic_cause(
                                     term.source_info
                                 );
-                                ErrorCtxt::PanicInPureFunction(panic_cause)
+                                let is_checked = panic_cause.is_checked();
+                                (ErrorCtxt::PanicInPureFunction(panic_cause), is_checked)
                             }
 
-                            _ => ErrorCtxt::DivergingCallInPureFunction,
+                            _ => (ErrorCtxt::DivergingCallInPureFunction, true),
                         };
                         let pos = self
                             .encoder
                             .error_manager()
                             .register(term.source_info.span, error_ctxt);
+                        // A panic that is not checked is assumed to be unreachable, so the
+                        // result of the function is left undefined instead.
+                        let encoded_expr = if is_checked {
+                            unreachable_expr(pos)
+                        } else {
+                            undef_expr(pos)
+                        };
                         MultiExprBackwardInterpreterState::new_single(
-                            unreachable_expr(pos).with_span(term.source_info.span)
+                            encoded_expr.with_span(term.source_info.span)
                                 .run_if_err(cleanup)?
                         )
                     };
@@ -841,9 +858,14 @@ impl<'p, 'v: 'p, 'tcx: 'v> BackwardMirInterpreter<'tcx>
                     vir::Expr::not(cond_val)
                 };
 
+                let (panic_cause, message) = self.mir_encoder.encode_assert_cause(msg);
+                if !panic_cause.is_checked() {
+                    // The assertion is assumed to hold.
+                    return Ok(states[target].clone());
+                }
                 let pos = self.encoder.error_manager().register(
                     term.source_info.span,
-                    ErrorCtxt::PureFunctionAssertTerminator(msg.description().to_string()),
+                    ErrorCtxt::PureFunctionAssertTerminator(panic_cause, message),
                 );
 
                 MultiExprBackwardInterpreterState::new(
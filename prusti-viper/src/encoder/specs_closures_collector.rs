@@ -8,13 +8,13 @@ use std::collections::{HashMap, HashSet};
 use log::{debug, trace};
 use rustc_hir as hir;
 use rustc_middle::mir;
-use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_hir::def_id::DefId;
 use rustc_middle::ty;
 use prusti_interface::environment::Environment;
 
 /// Structure to collect closure instantiations annotated with `prusti::spec_only`.
 pub struct SpecsClosuresCollector<'tcx> {
-    visited: HashSet<LocalDefId>,
+    visited: HashSet<DefId>,
     /// For each instantiation of each closure: DefId, location, operands and types of operands.
     instantiations: HashMap<
         DefId,
@@ -35,20 +35,28 @@ impl<'tcx> SpecsClosuresCollector<'tcx> {
         }
     }
 
-    /// Collect instantiations of `prusti::spec_only` closures from all `prusti::spec_only` items.
-    pub fn collect_from_all_spec_items(&mut self, env: &Environment<'tcx>) {
+    /// Collect instantiations of `prusti::spec_only` closures from all `prusti::spec_only` items,
+    /// including the given items imported from other crates.
+    pub fn collect_from_all_spec_items(
+        &mut self,
+        env: &Environment<'tcx>,
+        imported_spec_items: &[DefId],
+    ) {
         debug!("Collecting closure instantiations...");
         let tcx = env.tcx();
         let crate_num = hir::def_id::LOCAL_CRATE;
         for &def_id in tcx.mir_keys(crate_num).iter() {
             if env.has_prusti_attribute(def_id.to_def_id(), "spec_only") {
-                self.collect(env, def_id);
+                self.collect(env, def_id.to_def_id());
             }
         }
+        for &def_id in imported_spec_items {
+            self.collect(env, def_id);
+        }
     }
 
     /// Collect instantiations of `prusti::spec_only` closures from a given procedure.
-    pub fn collect(&mut self, env: &Environment<'tcx>, def_id: LocalDefId) {
+    pub fn collect(&mut self, env: &Environment<'tcx>, def_id: DefId) {
         debug!("Collecting closure instantiations in {:?}", def_id);
         // Avoid visiting the same procedure multiple times
         if (self.visited.contains(&def_id)) {
@@ -58,7 +66,7 @@ impl<'tcx> SpecsClosuresCollector<'tcx> {
             self.visited.insert(def_id);
         }
         let tcx = env.tcx();
-        let mir = env.local_mir(def_id);
+        let mir = env.mir_body(def_id);
         for (bb_index, bb_data) in mir.basic_blocks().iter_enumerated() {
             for (stmt_index, stmt) in bb_data.statements.iter().enumerate() {
                 if let mir::StatementKind::Assign(
@@ -81,7 +89,7 @@ impl<'tcx> SpecsClosuresCollector<'tcx> {
                     let instantiations =
                         self.instantiations.entry(cl_def_id).or_insert(vec![]);
                     instantiations.push((
-                        def_id.to_def_id(),
+                        def_id,
                         mir::Location {
                             block: bb_index,
                             statement_index: stmt_index,
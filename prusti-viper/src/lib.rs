@@ -27,5 +27,8 @@ extern crate rustc_attr;
 extern crate lazy_static;
 
 pub mod encoder;
+mod progress;
 mod utils;
+pub mod validators;
+pub mod verification_report;
 pub mod verifier;
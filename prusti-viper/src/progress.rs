@@ -0,0 +1,80 @@
+// © 2021, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use prusti_common::report::user;
+use prusti_server::{VerificationOutcome, VerificationProgress};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Prints the progress of the verification requests of a crate: a message when the verification
+/// of a procedure finishes and, on a terminal, a line with the procedures that are being verified.
+pub struct ProgressPrinter {
+    /// The name of the procedure of each request, by request name.
+    procedure_names: HashMap<String, String>,
+    total: usize,
+    state: Mutex<ProgressState>,
+}
+
+#[derive(Default)]
+struct ProgressState {
+    finished: usize,
+    running: Vec<String>,
+}
+
+impl ProgressPrinter {
+    pub fn new(procedure_names: HashMap<String, String>, total: usize) -> Self {
+        ProgressPrinter {
+            procedure_names,
+            total,
+            state: Mutex::new(ProgressState::default()),
+        }
+    }
+
+    fn procedure_name(&self, program_name: String) -> String {
+        self.procedure_names.get(&program_name).cloned().unwrap_or(program_name)
+    }
+
+    pub fn report(&self, progress: VerificationProgress) {
+        let mut state = self.state.lock().unwrap();
+        match progress {
+            VerificationProgress::Started { program_name } => {
+                state.running.push(self.procedure_name(program_name));
+            }
+            VerificationProgress::Finished { program_name, outcome, duration } => {
+                let procedure_name = self.procedure_name(program_name);
+                state.running.retain(|name| *name != procedure_name);
+                state.finished += 1;
+                let result = match outcome {
+                    VerificationOutcome::Verified => "verified".to_string(),
+                    VerificationOutcome::Failed(1) => "failed with 1 error".to_string(),
+                    VerificationOutcome::Failed(count) => format!("failed with {} errors", count),
+                    VerificationOutcome::TimedOut => "timed out".to_string(),
+                    VerificationOutcome::Panicked => "crashed the verifier".to_string(),
+                    VerificationOutcome::Cancelled => "was cancelled".to_string(),
+                };
+                user::clear_progress();
+                user::message(format!(
+                    "[{}/{}] {} {} ({:.2} s)",
+                    state.finished,
+                    self.total,
+                    procedure_name,
+                    result,
+                    duration.as_secs_f64(),
+                ));
+            }
+        }
+        if state.running.is_empty() {
+            user::clear_progress();
+        } else {
+            user::progress(format!(
+                "[{}/{}] Verifying {}...",
+                state.finished,
+                self.total,
+                state.running.join(", "),
+            ));
+        }
+    }
+}
@@ -0,0 +1,204 @@
+// © 2021, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A MIR-level analysis that finds the features of a procedure that the encoding does not
+//! (fully) support, before the encoding starts.
+
+mod support_status;
+
+pub use self::support_status::*;
+
+use prusti_interface::data::ProcedureDefId;
+use prusti_interface::environment::{Environment, Procedure};
+use rustc_middle::mir;
+use rustc_middle::ty::{self, Ty};
+use rustc_span::Span;
+
+pub struct Validator<'v, 'tcx: 'v> {
+    env: &'v Environment<'tcx>,
+}
+
+impl<'v, 'tcx: 'v> Validator<'v, 'tcx> {
+    pub fn new(env: &'v Environment<'tcx>) -> Self {
+        Validator { env }
+    }
+
+    /// Find the features of an impure procedure that are not fully supported.
+    pub fn procedure_support_status(&self, proc_def_id: ProcedureDefId) -> SupportStatus {
+        let procedure = self.env.get_procedure(proc_def_id);
+        let mut support = SupportStatus::new();
+        self.check_body(&procedure, &mut support);
+        support
+    }
+
+    /// Find the features of a pure function that are not fully supported.
+    pub fn pure_function_support_status(&self, proc_def_id: ProcedureDefId) -> SupportStatus {
+        let procedure = self.env.get_procedure(proc_def_id);
+        let mir = procedure.get_mir();
+        let mut support = SupportStatus::new();
+        self.check_body(&procedure, &mut support);
+
+        if procedure.loop_info().count_loop_heads() > 0 {
+            support.unsupported("loops in pure functions", procedure.get_span());
+        }
+        let return_ty = mir.return_ty();
+        if !self.env.type_is_copy(return_ty) {
+            let is_snapshot_type = match return_ty.kind() {
+                ty::TyKind::Adt(..) | ty::TyKind::Tuple(..) => true,
+                _ => false,
+            };
+            if is_snapshot_type {
+                support.unsupported(
+                    "return types of pure functions that do not implement Copy",
+                    procedure.get_span(),
+                );
+            }
+        }
+        for bbi in procedure.get_reachable_nonspec_cfg_blocks() {
+            for stmt in &mir[bbi].statements {
+                if let mir::StatementKind::Assign(box (_, ref rvalue)) = stmt.kind {
+                    if let mir::Rvalue::Ref(_, mir::BorrowKind::Mut { .. }, _) = *rvalue {
+                        support.unsupported(
+                            "mutable borrows in pure functions",
+                            stmt.source_info.span,
+                        );
+                    }
+                }
+            }
+        }
+        support
+    }
+
+    fn check_body(&self, procedure: &Procedure<'_, 'tcx>, support: &mut SupportStatus) {
+        let mir = procedure.get_mir();
+        // The types of the other locals are checked where the locals are assigned, so that the
+        // temporaries that are only used to build panic messages are ignored.
+        for local in Some(mir::RETURN_PLACE).into_iter().chain(mir.args_iter()) {
+            let local_decl = &mir.local_decls[local];
+            self.check_ty(local_decl.ty, local_decl.source_info.span, support);
+        }
+        for bbi in procedure.get_reachable_nonspec_cfg_blocks() {
+            let bb_data = &mir[bbi];
+            if bb_data.is_cleanup {
+                continue;
+            }
+            // Like the encoding, ignore the statements of blocks that end with a panic.
+            if !procedure.is_panic_block(bbi) {
+                for stmt in &bb_data.statements {
+                    self.check_statement(procedure, bbi, stmt, support);
+                }
+            }
+            let terminator = bb_data.terminator();
+            let span = terminator.source_info.span;
+            match terminator.kind {
+                mir::TerminatorKind::Yield { .. } |
+                mir::TerminatorKind::GeneratorDrop => {
+                    support.unsupported("generators", span);
+                }
+                mir::TerminatorKind::InlineAsm { .. } => {
+                    support.unsupported("inline assembly", span);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check_ty(&self, ty: Ty<'tcx>, span: Span, support: &mut SupportStatus) {
+        for arg in ty.walk() {
+            if let ty::subst::GenericArgKind::Type(ty) = arg.unpack() {
+                match ty.kind() {
+                    ty::TyKind::RawPtr(..) => support.unsupported("raw pointers", span),
+                    ty::TyKind::Float(..) => support.unsupported("floating-point numbers", span),
+                    ty::TyKind::FnPtr(..) => support.unsupported("function pointers", span),
+                    ty::TyKind::Dynamic(..) => support.unsupported("trait objects", span),
+                    ty::TyKind::Generator(..) => support.unsupported("generators", span),
+                    ty::TyKind::Foreign(..) => support.unsupported("foreign types", span),
+                    ty::TyKind::Array(..) |
+                    ty::TyKind::Slice(..) => {
+                        support.partially("arrays and slices", span)
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn check_statement(
+        &self,
+        procedure: &Procedure<'_, 'tcx>,
+        bbi: mir::BasicBlock,
+        stmt: &mir::Statement<'tcx>,
+        support: &mut SupportStatus,
+    ) {
+        let span = stmt.source_info.span;
+        let mir = procedure.get_mir();
+        let tcx = self.env.tcx();
+        let rvalue = match stmt.kind {
+            mir::StatementKind::Assign(box (ref place, ref rvalue)) => {
+                self.check_ty(place.ty(mir, tcx).ty, span, support);
+                rvalue
+            }
+            mir::StatementKind::LlvmInlineAsm(..) => {
+                support.unsupported("inline assembly", span);
+                return;
+            }
+            _ => return,
+        };
+        match *rvalue {
+            mir::Rvalue::Repeat(..) => support.unsupported("construction of arrays", span),
+            mir::Rvalue::ThreadLocalRef(..) => support.unsupported("thread-local statics", span),
+            mir::Rvalue::AddressOf(..) => support.unsupported("raw pointers", span),
+            mir::Rvalue::Len(..) => support.unsupported("obtaining the length of an array", span),
+            mir::Rvalue::Cast(mir::CastKind::Pointer(..), ..) => {
+                support.unsupported("pointer casts", span)
+            }
+            mir::Rvalue::Cast(mir::CastKind::Misc, ref operand, dst_ty) => {
+                let is_numeric = |ty: Ty<'tcx>| match ty.kind() {
+                    ty::TyKind::Bool |
+                    ty::TyKind::Char |
+                    ty::TyKind::Int(_) |
+                    ty::TyKind::Uint(_) => true,
+                    _ => false,
+                };
+                if !is_numeric(operand.ty(mir, tcx)) || !is_numeric(dst_ty) {
+                    support.unsupported("casts between non-integer types", span);
+                }
+            }
+            mir::Rvalue::BinaryOp(op, ref left, _) |
+            mir::Rvalue::CheckedBinaryOp(op, ref left, _) => {
+                let is_bool = left.ty(mir, tcx).kind() == &ty::TyKind::Bool;
+                match op {
+                    mir::BinOp::BitAnd |
+                    mir::BinOp::BitOr |
+                    mir::BinOp::BitXor if !is_bool => {
+                        support.unsupported("bitwise operations on non-boolean types", span)
+                    }
+                    mir::BinOp::Shl | mir::BinOp::Shr => {
+                        support.unsupported("shift operations", span)
+                    }
+                    mir::BinOp::Offset => support.unsupported("pointer arithmetic", span),
+                    _ => {}
+                }
+            }
+            mir::Rvalue::Aggregate(box mir::AggregateKind::Array(..), _) => {
+                support.unsupported("construction of arrays", span)
+            }
+            mir::Rvalue::Aggregate(box mir::AggregateKind::Generator(..), _) => {
+                support.unsupported("generators", span)
+            }
+            mir::Rvalue::Ref(_, mir::BorrowKind::Mut { .. }, _) => {
+                // The encoding of loans created in loops is limited (see `ProcedureEncoder`).
+                let loop_depth = procedure.loop_info().get_loop_depth(bbi);
+                if loop_depth > 1 {
+                    support.unsupported("creation of loans in nested loops", span);
+                } else if loop_depth == 1 {
+                    support.partially("creation of loans in loops", span);
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,161 @@
+// © 2021, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use prusti_interface::data::ProcedureDefId;
+use prusti_interface::environment::Environment;
+use prusti_interface::PrustiError;
+use rustc_span::Span;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+/// How well a feature is supported by Prusti.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SupportKind {
+    /// The feature can be encoded in some cases (e.g. depending on the rest of the procedure).
+    PartiallySupported,
+    /// The feature cannot be encoded.
+    Unsupported,
+}
+
+/// A use of a feature that is not fully supported by Prusti.
+#[derive(Debug, Clone)]
+pub struct UnsupportedFeature {
+    pub kind: SupportKind,
+    pub reason: String,
+    pub span: Span,
+}
+
+/// The features of a procedure that are not fully supported by Prusti.
+#[derive(Debug, Clone, Default)]
+pub struct SupportStatus {
+    features: Vec<UnsupportedFeature>,
+}
+
+impl SupportStatus {
+    pub fn new() -> Self {
+        SupportStatus::default()
+    }
+
+    pub fn partially<S: ToString>(&mut self, reason: S, span: Span) {
+        self.push(SupportKind::PartiallySupported, reason, span);
+    }
+
+    pub fn unsupported<S: ToString>(&mut self, reason: S, span: Span) {
+        self.push(SupportKind::Unsupported, reason, span);
+    }
+
+    fn push<S: ToString>(&mut self, kind: SupportKind, reason: S, span: Span) {
+        let reason = reason.to_string();
+        // Report the same feature at the same position only once.
+        if !self.features.iter().any(|f| f.kind == kind && f.reason == reason && f.span == span) {
+            self.features.push(UnsupportedFeature { kind, reason, span });
+        }
+    }
+
+    /// All the features that are not fully supported, in the order in which they were found.
+    pub fn features(&self) -> &[UnsupportedFeature] {
+        &self.features
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    pub fn is_partially_supported(&self) -> bool {
+        !self.is_supported() && !self.is_unsupported()
+    }
+
+    pub fn is_unsupported(&self) -> bool {
+        self.features.iter().any(|f| f.kind == SupportKind::Unsupported)
+    }
+
+    /// The overall support kind of the procedure, or `None` if it is fully supported.
+    pub fn kind(&self) -> Option<SupportKind> {
+        if self.is_unsupported() {
+            Some(SupportKind::Unsupported)
+        } else if self.is_partially_supported() {
+            Some(SupportKind::PartiallySupported)
+        } else {
+            None
+        }
+    }
+
+    /// Report each feature that is not fully supported as a warning. They are not reported as
+    /// errors, because the encoding reports the features that it cannot handle anyway.
+    pub fn report_support_status(&self, env: &Environment, is_pure_function: bool) {
+        let function_kind = if is_pure_function { "pure function" } else { "function" };
+        for feature in &self.features {
+            let support = match feature.kind {
+                SupportKind::PartiallySupported => "a partially supported",
+                SupportKind::Unsupported => "an unsupported",
+            };
+            let mut warning = PrustiError::unsupported(
+                format!("this {} uses {} feature: {}", function_kind, support, feature.reason),
+                feature.span.into(),
+            );
+            warning.set_warning();
+            warning.emit(env);
+        }
+    }
+}
+
+/// The support status of a procedure, as written to the support status report.
+#[derive(Serialize)]
+struct ProcedureSupportReport {
+    procedure: String,
+    span: String,
+    pure: bool,
+    skipped: bool,
+    /// `supported`, `partially_supported` or `unsupported`.
+    status: String,
+    features: Vec<FeatureReport>,
+}
+
+#[derive(Serialize)]
+struct FeatureReport {
+    kind: SupportKind,
+    reason: String,
+    span: String,
+}
+
+/// Write the support status of the given procedures to a JSON file. Each procedure is given
+/// together with whether it is a pure function and whether it was skipped.
+pub fn write_support_status_report<P: AsRef<Path>>(
+    env: &Environment,
+    procedures: &[(ProcedureDefId, bool, bool, SupportStatus)],
+    path: P,
+) -> io::Result<()> {
+    let codemap = env.codemap();
+    let report: Vec<ProcedureSupportReport> = procedures.iter()
+        .map(|(proc_def_id, is_pure_function, is_skipped, support_status)| {
+            let status = match support_status.kind() {
+                Some(SupportKind::PartiallySupported) => "partially_supported",
+                Some(SupportKind::Unsupported) => "unsupported",
+                None => "supported",
+            };
+            ProcedureSupportReport {
+                procedure: env.get_item_def_path(*proc_def_id),
+                span: codemap.span_to_string(env.get_item_span(*proc_def_id)),
+                pure: *is_pure_function,
+                skipped: *is_skipped,
+                status: status.to_string(),
+                features: support_status.features().iter()
+                    .map(|feature| FeatureReport {
+                        kind: feature.kind,
+                        reason: feature.reason.clone(),
+                        span: codemap.span_to_string(feature.span),
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(writer, &report)
+        .map_err(|error| io::Error::new(io::ErrorKind::Other, error))
+}
@@ -0,0 +1,332 @@
+// © 2021, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A machine-readable report of the verification of a crate, which can be written as JSON or
+//! in the SARIF format used by code scanning tools.
+//!
+//! When several crates are verified (e.g. by `cargo prusti`), the reports of all of them are
+//! collected in the same file: each crate replaces its own entry and keeps those of the others.
+
+use fs2::FileExt;
+use prusti_interface::PrustiError;
+use rustc_span::source_map::SourceMap;
+use rustc_span::Span;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::fs::OpenOptions;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// The outcome of the verification of a procedure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcedureStatus {
+    /// The procedure has been verified successfully.
+    Verified,
+    /// The verification of the procedure reported errors, or the verifier failed.
+    Failed,
+    /// The procedure could not be encoded.
+    Unsupported,
+    /// The procedure was not verified, e.g. because it is trusted or not fully supported.
+    Skipped,
+    /// The verification of the procedure did not finish within its timeout.
+    TimedOut,
+}
+
+/// A source code range, with 1-based lines and columns.
+#[derive(Debug, Clone, Serialize)]
+pub struct Location {
+    pub file: String,
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Location {
+    pub fn from_span(codemap: &SourceMap, span: Span) -> Option<Self> {
+        if span.is_dummy() {
+            return None;
+        }
+        let span = span.source_callsite();
+        let start = codemap.lookup_char_pos(span.lo());
+        let end = codemap.lookup_char_pos(span.hi());
+        Some(Location {
+            file: start.file.name.to_string(),
+            start_line: start.line,
+            start_column: start.col.0 + 1,
+            end_line: end.line,
+            end_column: end.col.0 + 1,
+        })
+    }
+}
+
+/// An error or warning reported for a procedure.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportedError {
+    /// The context of the error, e.g. `assert_method_postcondition` or `encoding`.
+    pub kind: String,
+    pub message: String,
+    pub is_error: bool,
+    pub location: Option<Location>,
+}
+
+impl ReportedError {
+    pub fn new<S: ToString>(kind: S, error: &PrustiError, codemap: &SourceMap) -> Self {
+        ReportedError {
+            kind: kind.to_string(),
+            message: error.message().to_string(),
+            is_error: error.is_error(),
+            location: error.span()
+                .primary_span()
+                .and_then(|span| Location::from_span(codemap, span)),
+        }
+    }
+}
+
+/// The verification of a procedure.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcedureReport {
+    pub name: String,
+    pub location: Option<Location>,
+    pub pure: bool,
+    pub status: ProcedureStatus,
+    /// Whether the result has been taken from the verification cache.
+    pub cached: bool,
+    /// The time that it took to verify the procedure, in seconds.
+    pub verification_time: Option<f64>,
+    pub errors: Vec<ReportedError>,
+}
+
+/// The time that a phase of the verification took.
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseTiming {
+    pub phase: String,
+    pub seconds: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VerificationReport {
+    /// The name of the verified crate.
+    pub crate_name: String,
+    pub procedures: Vec<ProcedureReport>,
+    /// The errors that do not belong to a single procedure.
+    pub other_errors: Vec<ReportedError>,
+    pub timings: Vec<PhaseTiming>,
+}
+
+impl VerificationReport {
+    pub fn new<S: ToString>(crate_name: S) -> Self {
+        VerificationReport {
+            crate_name: crate_name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn add_timing<S: ToString>(&mut self, phase: S, duration: Duration) {
+        self.timings.push(PhaseTiming {
+            phase: phase.to_string(),
+            seconds: duration.as_secs_f64(),
+        });
+    }
+
+    /// Add the report to the `crates` of the JSON report at `path`.
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let report = serde_json::to_value(self).map_err(to_io_error)?;
+        update_report_file(path.as_ref(), |log| {
+            replace_entry(log, "crates", report, |crate_report| {
+                crate_report["crate_name"] == self.crate_name.as_str()
+            })
+        })
+    }
+
+    /// Add the errors and warnings as a run to the SARIF 2.1.0 log at `path`, using the kind of
+    /// each error as the identifier of its rule.
+    pub fn write_sarif<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let run = self.sarif_run();
+        update_report_file(path.as_ref(), |log| {
+            log["$schema"] = json!("https://json.schemastore.org/sarif-2.1.0.json");
+            log["version"] = json!("2.1.0");
+            replace_entry(log, "runs", run, |run| {
+                run["automationDetails"]["id"] == self.crate_name.as_str()
+            })
+        })
+    }
+
+    fn sarif_run(&self) -> Value {
+        let errors = self.procedures.iter()
+            .flat_map(|procedure| procedure.errors.iter())
+            .chain(self.other_errors.iter());
+        let mut rules = BTreeSet::new();
+        let mut results = vec![];
+        for error in errors {
+            rules.insert(error.kind.clone());
+            let locations: Vec<_> = error.location.iter()
+                .map(|location| json!({
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": location.file },
+                        "region": {
+                            "startLine": location.start_line,
+                            "startColumn": location.start_column,
+                            "endLine": location.end_line,
+                            "endColumn": location.end_column,
+                        },
+                    },
+                }))
+                .collect();
+            results.push(json!({
+                "ruleId": error.kind,
+                "level": if error.is_error { "error" } else { "warning" },
+                "message": { "text": error.message },
+                "locations": locations,
+            }));
+        }
+        let rules: Vec<_> = rules.into_iter().map(|rule| json!({ "id": rule })).collect();
+        json!({
+            "tool": {
+                "driver": {
+                    "name": "Prusti",
+                    "informationUri": "https://github.com/viperproject/prusti-dev",
+                    "rules": rules,
+                },
+            },
+            "automationDetails": { "id": self.crate_name },
+            "results": results,
+        })
+    }
+}
+
+fn to_io_error(error: serde_json::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, error)
+}
+
+/// Update the JSON document at `path`, which is created if it does not exist yet. The file is
+/// locked meanwhile, because the crates of a package can be verified concurrently.
+fn update_report_file<F: FnOnce(&mut Value)>(path: &Path, update: F) -> io::Result<()> {
+    let mut file = OpenOptions::new().read(true).write(true).create(true).open(path)?;
+    file.lock_exclusive()?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    let mut log = if content.trim().is_empty() {
+        json!({})
+    } else {
+        serde_json::from_str(&content).map_err(to_io_error)?
+    };
+    if !log.is_object() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the existing report is not a JSON object",
+        ));
+    }
+    update(&mut log);
+    let content = serde_json::to_string_pretty(&log).map_err(to_io_error)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.set_len(0)?;
+    file.write_all(content.as_bytes())?;
+    file.unlock()
+}
+
+/// Replace the entries of the array `log[key]` for which `is_replaced` holds by `entry`.
+fn replace_entry<F: Fn(&Value) -> bool>(log: &mut Value, key: &str, entry: Value, is_replaced: F) {
+    let mut entries = match log[key].take() {
+        Value::Array(entries) => entries,
+        _ => vec![],
+    };
+    entries.retain(|other| !is_replaced(other));
+    entries.push(entry);
+    log[key] = Value::Array(entries);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn report(crate_name: &str, status: ProcedureStatus, error_kind: &str) -> VerificationReport {
+        let mut report = VerificationReport::new(crate_name);
+        report.procedures.push(ProcedureReport {
+            name: format!("{}::main", crate_name),
+            location: None,
+            pure: false,
+            status,
+            cached: false,
+            verification_time: Some(0.5),
+            errors: vec![ReportedError {
+                kind: error_kind.to_string(),
+                message: "postcondition might not hold.".to_string(),
+                is_error: true,
+                location: Some(Location {
+                    file: "src/main.rs".to_string(),
+                    start_line: 3,
+                    start_column: 11,
+                    end_line: 3,
+                    end_column: 22,
+                }),
+            }],
+        });
+        report
+    }
+
+    fn report_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir()
+            .join(format!("prusti-report-{}-{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn read(path: &Path) -> Value {
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn json_report_keeps_each_crate() {
+        let path = report_path("report.json");
+        report("first", ProcedureStatus::Failed, "assert_method_postcondition")
+            .write_json(&path).unwrap();
+        report("second", ProcedureStatus::Verified, "timeout").write_json(&path).unwrap();
+        report("first", ProcedureStatus::TimedOut, "timeout").write_json(&path).unwrap();
+
+        let log = read(&path);
+        let crates = log["crates"].as_array().unwrap();
+        assert_eq!(crates.len(), 2);
+        assert_eq!(crates[0]["crate_name"], "second");
+        assert_eq!(crates[0]["procedures"][0]["status"], "verified");
+        assert_eq!(crates[1]["crate_name"], "first");
+        assert_eq!(crates[1]["procedures"][0]["status"], "timed_out");
+        assert_eq!(crates[1]["procedures"][0]["errors"][0]["kind"], "timeout");
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn sarif_report_has_a_run_per_crate() {
+        let path = report_path("report.sarif");
+        report("first", ProcedureStatus::Failed, "assert_method_postcondition")
+            .write_sarif(&path).unwrap();
+        report("second", ProcedureStatus::Failed, "encoding").write_sarif(&path).unwrap();
+
+        let log = read(&path);
+        assert_eq!(log["version"], "2.1.0");
+        let runs = log["runs"].as_array().unwrap();
+        assert_eq!(runs.len(), 2);
+        let run = &runs[0];
+        assert_eq!(run["automationDetails"]["id"], "first");
+        assert_eq!(run["tool"]["driver"]["name"], "Prusti");
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "assert_method_postcondition");
+        let result = &run["results"][0];
+        assert_eq!(result["ruleId"], "assert_method_postcondition");
+        assert_eq!(result["level"], "error");
+        assert_eq!(result["message"]["text"], "postcondition might not hold.");
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/main.rs");
+        assert_eq!(location["region"]["startLine"], 3);
+        assert_eq!(location["region"]["endColumn"], 22);
+        assert_eq!(runs[1]["results"][0]["ruleId"], "encoding");
+        let _ = fs::remove_file(&path);
+    }
+}
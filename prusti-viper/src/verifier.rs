@@ -6,131 +6,38 @@
 
 use prusti_common::vir::{self, optimizations, ToViper, ToViperDecl};
 use prusti_common::{
-    config, report::log, verification_context::VerifierBuilder, verification_service::*, Stopwatch,
+    config, report::log, verification_service::*, Stopwatch,
 };
 use crate::encoder::Encoder;
-// use prusti_filter::validators::Validator;
+use crate::progress::ProgressPrinter;
+use crate::validators::{write_support_status_report, Validator};
+use crate::verification_report::{
+    Location, ProcedureReport, ProcedureStatus, ReportedError, VerificationReport,
+};
+use prusti_interface::data::ProcedureDefId;
 use prusti_interface::data::VerificationResult;
 use prusti_interface::data::VerificationTask;
 use prusti_interface::environment::Environment;
 use prusti_interface::PrustiError;
 // use prusti_interface::specifications::TypedSpecificationMap;
-use std::time::Instant;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use viper::{self, VerificationBackend, Viper};
 use std::path::PathBuf;
 use std::fs::{create_dir_all, canonicalize};
 use std::ffi::OsString;
 use prusti_interface::specs::typed;
-use ::log::{info, debug, error};
-use prusti_server::{PrustiServerConnection, ServerSideService, VerifierRunner};
+use ::log::{info, debug, error, warn};
+use prusti_server::{
+    create_backend, describe_result, verify_concurrently, verify_with_backends,
+    BackendMode, CacheKey, Cancellation, Disagreement, PersistentCache, PrustiServerConnection,
+    RemoteVerificationResult, ServerSideService, VerificationOutcome, VerificationProgress,
+    VerifierError,
+};
+use rustc_hir::def_id::LOCAL_CRATE;
 use rustc_span::DUMMY_SP;
 
-// /// A verifier builder is an object that lives entire program's
-// /// lifetime, has no mutable state, and is responsible for constructing
-// /// verification context instances. The user of this interface is supposed
-// /// to create a new verifier for each crate he or she wants to verify.
-// /// The main motivation for having a builder is to be able to cache the JVM
-// /// initialization.
-// pub struct VerifierBuilder {
-//     viper: Viper,
-// }
-
-// impl VerifierBuilder {
-//     pub fn new() -> Self {
-//         VerifierBuilder {
-//             viper: Viper::new_with_args(
-//                 config::extra_jvm_args(),
-//                 VerificationBackend::from_str(&config::viper_backend())
-//             ),
-//         }
-//     }
-
-//     pub fn new_verification_context(&self) -> VerificationContext {
-//         let verification_ctx = self.viper.new_verification_context();
-//         VerificationContext::new(verification_ctx)
-//     }
-// }
-
-// impl Default for VerifierBuilder {
-//     fn default() -> Self {
-//         VerifierBuilder::new()
-//     }
-// }
-
-// /// A verification context is an object that lives entire verification's lifetime.
-// /// Its main purpose is to build verifiers.
-// /// The main motivation for having a verification context is to be able to detach the current
-// /// thread from the JVM when the verification context goes out of scope.
-// pub struct VerificationContext<'v> {
-//     verification_ctx: viper::VerificationContext<'v>,
-// }
-
-// impl<'v, 'r, 'a, 'tcx> VerificationContext<'v>
-//     where
-//         'r: 'v,
-//         'a: 'r,
-//         'tcx: 'a,
-// {
-//     fn new(verification_ctx: viper::VerificationContext<'v>) -> Self {
-//         VerificationContext { verification_ctx }
-//     }
-
-//     pub fn new_verifier(
-//         &'v self,
-//         env: &'v Environment<'tcx>,
-//         spec: &'v typed::SpecificationMap<'tcx>,
-//     ) -> Verifier<'v, 'tcx> {
-//         let backend = VerificationBackend::from_str(&config::viper_backend());
-
-//         let mut verifier_args: Vec<String> = vec![];
-//         let log_path: PathBuf = PathBuf::from(config::log_dir()).join("viper_tmp");
-//         create_dir_all(&log_path).unwrap();
-//         let report_path: PathBuf = log_path.join("report.csv");
-//         let log_dir_str = log_path.to_str().unwrap();
-//         if let VerificationBackend::Silicon = backend {
-//             if config::use_more_complete_exhale() {
-//                 verifier_args.push("--enableMoreCompleteExhale".to_string()); // Buggy :(
-//             }
-//             verifier_args.extend(vec![
-//                 "--assertTimeout".to_string(),
-//                 config::assert_timeout().to_string(),
-//                 "--tempDirectory".to_string(),
-//                 log_dir_str.to_string(),
-//                 //"--logLevel".to_string(), "WARN".to_string(),
-//             ]);
-//         } else {
-//             verifier_args.extend(vec![
-//                 "--disableAllocEncoding".to_string(),
-//                 "--boogieOpt".to_string(),
-//                 format!("/logPrefix {}", log_dir_str),
-//             ]);
-//         }
-//         if config::dump_debug_info() {
-//             if let VerificationBackend::Silicon = backend {
-//                 verifier_args.extend(vec![
-//                     "--printMethodCFGs".to_string(),
-//                     "--logLevel".to_string(),
-//                     "INFO".to_string(),
-//                     //"--printTranslatedProgram".to_string(),
-//                 ]);
-//             } else {
-//                 verifier_args.extend::<Vec<_>>(vec![
-//                     //"--print".to_string(), "./log/boogie_program/program.bpl".to_string(),
-//                 ]);
-//             }
-//         }
-//         verifier_args.extend(config::extra_verifier_args());
-//         Verifier::new(
-//             self.verification_ctx.new_ast_utils(),
-//             self.verification_ctx.new_ast_factory(),
-//             self.verification_ctx
-//                 .new_verifier_with_args(backend, verifier_args, Some(report_path)),
-//             env,
-//             spec,
-//         )
-//     }
-// }
-
 /// A verifier is an object for verifying a single crate, potentially
 /// many times.
 pub struct Verifier<'v, 'tcx>
@@ -139,6 +46,10 @@ where
 {
     env: &'v Environment<'tcx>,
     encoder: Encoder<'v, 'tcx>,
+    /// The number of procedures whose result was found in the verification cache.
+    cache_hits: usize,
+    /// The number of procedures that had to be verified despite the cache being enabled.
+    cache_misses: usize,
 }
 
 impl<'v, 'tcx> Verifier<'v, 'tcx> {
@@ -149,9 +60,16 @@ impl<'v, 'tcx> Verifier<'v, 'tcx> {
         Verifier {
             env,
             encoder: Encoder::new(env, def_spec),
+            cache_hits: 0,
+            cache_misses: 0,
         }
     }
 
+    /// The number of verification cache hits and misses of the previous verifications.
+    pub fn cache_statistics(&self) -> (usize, usize) {
+        (self.cache_hits, self.cache_misses)
+    }
+
     pub fn verify(&mut self, task: &VerificationTask) -> VerificationResult {
         info!(
             "Received {} functions to be verified:",
@@ -170,87 +88,230 @@ impl<'v, 'tcx> Verifier<'v, 'tcx> {
             info!(" - {} from {:?} ({})", proc_name, proc_span, proc_def_path);
         }
 
-        // // Check support status, and queue encoding
-        // let validator = Validator::new(self.env.tcx());
-
-        // let report_support_status = config::report_support_status();
-        // let skip_unsupported_features = config::skip_unsupported_features();
-        // let error_on_partially_supported = config::error_on_partially_supported();
-        // let mut skipped_functions_count = 0;
-
-        // for &proc_id in &task.procedures {
-        //     let proc_name = self.env.get_absolute_item_name(proc_id);
-        //     let proc_span = self.env.get_item_span(proc_id);
-        //     let is_pure_function = self.env.has_prusti_attribute(proc_id, "pure");
-
-        //     let support_status = if is_pure_function {
-        //         validator.pure_function_support_status(proc_id)
-        //     } else {
-        //         validator.procedure_support_status(proc_id)
-        //     };
-
-        //     if report_support_status {
-        //         support_status.report_support_status(
-        //             &self.env,
-        //             is_pure_function,
-        //             // true ==> raise compiler errors for partially supported functions
-        //             error_on_partially_supported,
-        //             // true ==> raise compiler errors for unsupported functions
-        //             error_on_partially_supported || !skip_unsupported_features,
-        //         );
-        //     }
-
-        //     if !support_status.is_supported() && skip_unsupported_features {
-        //         warn!(
-        //             "Skip verification of {}, as it is not fully supported.",
-        //             proc_name
-        //         );
-        //         self.env.span_warn_with_help_and_note(
-        //             proc_span,
-        //             &format!(
-        //                 "this function will be ignored because it is not fully supported by \
-        //                 Prusti: {}",
-        //                 proc_name
-        //             ),
-        //             &Some(if report_support_status {
-        //                 "Disable the SKIP_UNSUPPORTED_FEATURES configuration flag to verify \
-        //                     this function anyway."
-        //                     .to_string()
-        //             } else {
-        //                 "Enable the REPORT_SUPPORT_STATUS configuration flag for more details \
-        //                     on why the function is not fully supported, or disable \
-        //                     SKIP_UNSUPPORTED_FEATURES to verify this function anyway."
-        //                     .to_string()
-        //             }),
-        //             &None,
-        //         );
-        //         skipped_functions_count += 1;
-        //     } else {
-        //         self.encoder.queue_procedure_encoding(proc_id);
-        //     }
-        // }
-        // info!(
-        //     "Out of {} functions, {} are not fully supported and have been skipped.",
-        //     task.procedures.len(),
-        //     skipped_functions_count,
-        // );
-
-        for &proc_id in task.procedures.iter().rev() {
-            // FIXME: Use the loop above.
-            self.encoder.queue_procedure_encoding(proc_id);
-        }
+        let skipped_procedures = self.queue_procedure_encodings(task);
         self.encoder.process_encoding_queue();
 
         let encoding_errors_count = self.encoder.count_encoding_errors();
         let mut program = self.encoder.get_viper_program();
 
+        let crate_name = self.env.tcx().crate_name(LOCAL_CRATE);
+        let mut report = VerificationReport::new(crate_name);
         if config::simplify_encoding() {
-            stopwatch.start_next("optimizing Viper program");
+            report.add_timing("encoding", stopwatch.start_next("optimizing Viper program"));
             let source_file_name = self.encoder.env().source_file_name();
             program = program.optimized(&source_file_name);
+            report.add_timing("optimization", stopwatch.start_next("verifying Viper programs"));
+        } else {
+            report.add_timing("encoding", stopwatch.start_next("verifying Viper programs"));
+        }
+        let encoded_procedures = self.encoder.get_encoded_procedures();
+        let requests = self.build_verification_requests(program, &encoded_procedures);
+        let request_names: Vec<String> = requests
+            .iter()
+            .map(|request| request.program_name.clone())
+            .collect();
+        let method_names: Vec<Option<String>> = requests
+            .iter()
+            .map(|request| request.program.methods.first().map(|method| method.name()))
+            .collect();
+
+        // Look up the results of unchanged procedures in the cache.
+        let mut cache = self.load_cache();
+        let mut verification_results: Vec<Option<RemoteVerificationResult>> = vec![];
+        let mut cache_keys = vec![];
+        let mut uncached_requests = vec![];
+        for request in requests {
+            let key = CacheKey::of(&request);
+            let cached_result = cache
+                .as_ref()
+                .and_then(|cache| cache.get(key.hash))
+                .map(|result| key.restore_result(result));
+            if cached_result.is_some() {
+                debug!("Using the cached result of {}", request.program_name);
+                self.cache_hits += 1;
+            } else {
+                uncached_requests.push(request);
+                if cache.is_some() {
+                    self.cache_misses += 1;
+                }
+            }
+            verification_results.push(cached_result.map(Ok));
+            cache_keys.push(key);
+        }
+
+        info!("Verifying {} Viper programs", uncached_requests.len());
+        let procedure_names: HashMap<String, String> = request_names.iter()
+            .zip(&method_names)
+            .filter_map(|(request_name, method_name)| {
+                let proc_id = encoded_procedures.get(method_name.as_ref()?)?;
+                Some((request_name.clone(), self.env.get_absolute_item_name(*proc_id)))
+            })
+            .collect();
+        let verified_requests = match self.verify_requests(uncached_requests, procedure_names) {
+            Ok(verified_requests) => verified_requests,
+            Err(error) => {
+                error.emit(self.env);
+                return VerificationResult::Failure;
+            }
+        };
+
+        let mut new_results = verified_requests.results.into_iter();
+        let server_cached_requests = verified_requests.server_cached_requests;
+        // The verification time of results cached locally or by the server is `None`.
+        let verification_results: Vec<(RemoteVerificationResult, Option<Duration>)> =
+            verification_results
+                .into_iter()
+                .zip(&cache_keys)
+                .zip(&request_names)
+                .map(|((cached_result, key), request_name)| match cached_result {
+                    Some(result) => (result, None),
+                    None => {
+                        let (result, verification_time) = new_results.next().unwrap();
+                        if let (Some(cache), Ok(result)) = (&mut cache, &result) {
+                            cache.insert(key.hash, &key.normalize_result(result));
+                        }
+                        if server_cached_requests.contains(request_name) {
+                            (result, None)
+                        } else {
+                            (result, Some(verification_time))
+                        }
+                    }
+                })
+                .collect();
+        if let Some(cache) = &cache {
+            if let Err(error) = cache.save() {
+                PrustiError::internal(
+                    format!("failed to store the verification cache: {}", error),
+                    DUMMY_SP.into()
+                ).emit(self.env);
+            }
+        }
+
+        report.add_timing("verification", stopwatch.finish());
+
+        for disagreement in verified_requests.disagreements {
+            let method_name = request_names.iter()
+                .position(|request_name| *request_name == disagreement.program_name)
+                .and_then(|index| method_names[index].clone());
+            self.report_disagreement(disagreement, method_name.as_deref(), &encoded_procedures);
+        }
+
+        let results = request_names.into_iter().zip(method_names).zip(verification_results);
+        let reported_results = self.report_verification_results(results, &encoded_procedures);
+
+        if config::json_report().is_some() || config::sarif_report().is_some() {
+            self.fill_verification_report(
+                &mut report,
+                task,
+                &skipped_procedures,
+                &reported_results.method_outcomes,
+                reported_results.reported_errors,
+            );
+            self.write_verification_report(&report);
         }
 
-        stopwatch.start_next("verifying Viper program");
+        if encoding_errors_count == 0
+            && !reported_results.has_verification_errors
+            && !reported_results.failed
+        {
+            VerificationResult::Success
+        } else {
+            VerificationResult::Failure
+        }
+    }
+
+    /// Queue the encoding of the procedures of the task, checking their support status first if
+    /// it has to be reported or if unsupported procedures have to be skipped. Returns the
+    /// procedures that have been skipped.
+    fn queue_procedure_encodings(&mut self, task: &VerificationTask) -> HashSet<ProcedureDefId> {
+        let report_support_status = config::report_support_status();
+        let skip_unsupported_features = config::skip_unsupported_features();
+        let support_status_report = config::support_status_report();
+        let check_support_status = report_support_status
+            || skip_unsupported_features
+            || support_status_report.is_some();
+        let validator = Validator::new(self.env);
+        let mut support_statuses = vec![];
+        let mut skipped_functions_count = 0;
+
+        for &proc_id in task.procedures.iter().rev() {
+            if !check_support_status {
+                self.encoder.queue_procedure_encoding(proc_id);
+                continue;
+            }
+            let proc_name = self.env.get_absolute_item_name(proc_id);
+            let proc_span = self.env.get_item_span(proc_id);
+            let is_pure_function = self.env.has_prusti_attribute(proc_id, "pure");
+
+            let support_status = if is_pure_function {
+                validator.pure_function_support_status(proc_id)
+            } else {
+                validator.procedure_support_status(proc_id)
+            };
+
+            if report_support_status {
+                support_status.report_support_status(self.env, is_pure_function);
+            }
+
+            let is_skipped = !support_status.is_supported() && skip_unsupported_features;
+            if is_skipped {
+                warn!(
+                    "Skip verification of {}, as it is not fully supported.",
+                    proc_name
+                );
+                let help = if report_support_status {
+                    "Disable the SKIP_UNSUPPORTED_FEATURES configuration flag to verify \
+                        this function anyway."
+                } else {
+                    "Enable the REPORT_SUPPORT_STATUS configuration flag for more details \
+                        on why the function is not fully supported, or disable \
+                        SKIP_UNSUPPORTED_FEATURES to verify this function anyway."
+                };
+                let mut warning = PrustiError::unsupported(
+                    format!(
+                        "this function will be ignored because it is not fully supported by \
+                        Prusti: {}",
+                        proc_name
+                    ),
+                    proc_span.into(),
+                ).set_help(help);
+                warning.set_warning();
+                warning.emit(self.env);
+                skipped_functions_count += 1;
+            } else {
+                self.encoder.queue_procedure_encoding(proc_id);
+            }
+            support_statuses.push((proc_id, is_pure_function, is_skipped, support_status));
+        }
+        info!(
+            "Out of {} functions, {} are not fully supported and have been skipped.",
+            task.procedures.len(),
+            skipped_functions_count,
+        );
+
+        let skipped_procedures = support_statuses.iter()
+            .filter(|(_, _, is_skipped, _)| *is_skipped)
+            .map(|&(proc_id, _, _, _)| proc_id)
+            .collect();
+        if let Some(path) = support_status_report {
+            // Report the procedures in the order in which they were received.
+            support_statuses.reverse();
+            if let Err(error) = write_support_status_report(self.env, &support_statuses, &path) {
+                PrustiError::internal(
+                    format!("failed to write the support status report to {}: {}", path, error),
+                    DUMMY_SP.into(),
+                ).emit(self.env);
+            }
+        }
+        skipped_procedures
+    }
+
+    /// Split the program into one verification request per method.
+    fn build_verification_requests(
+        &self,
+        program: vir::Program,
+        encoded_procedures: &HashMap<String, ProcedureDefId>,
+    ) -> Vec<VerificationRequest> {
         let source_path = self.env.source_path();
         let program_name = source_path
             .file_name()
@@ -258,72 +319,507 @@ impl<'v, 'tcx> Verifier<'v, 'tcx> {
             .to_str()
             .unwrap()
             .to_owned();
-        let verification_result: viper::VerificationResult = if let Some(server_address) =
-            config::server_address()
-        {
-            let server_address = if server_address == "MOCK" {
-                ServerSideService::spawn_off_thread().to_string()
-            } else {
-                server_address
-            };
-            info!("Connecting to Prusti server at {}", server_address);
-            let service = PrustiServerConnection::new(&server_address).unwrap_or_else(|error| {
-                panic!(
-                    "Could not parse server address ({}) due to {:?}",
-                    server_address, error
-                )
-            });
+        program
+            .split_by_method()
+            .into_iter()
+            .map(|(method_name, program)| {
+                let timeout = match encoded_procedures.get(&method_name) {
+                    Some(&proc_id) => self.get_procedure_timeout(proc_id),
+                    None => config::procedure_timeout(),
+                };
+                VerificationRequest {
+                    program,
+                    program_name: format!("{}_{}", program_name, method_name),
+                    backend_config: Default::default(),
+                    timeout,
+                }
+            })
+            .collect()
+    }
 
-            let request = VerificationRequest {
-                program,
-                program_name,
-                backend_config: Default::default(),
-            };
-            service.verify(request)
+    /// Load the verification cache, if it is enabled. Unless `CACHE_PATH` is set, the cache is
+    /// stored next to the output of the crate.
+    fn load_cache(&self) -> Option<PersistentCache> {
+        if !config::enable_cache() {
+            return None;
+        }
+        let cache_path = config::cache_path().map(PathBuf::from).unwrap_or_else(|| {
+            let output_filenames = self.env.tcx().output_filenames(LOCAL_CRATE);
+            output_filenames.out_directory.join("prusti-verification-cache.bin")
+        });
+        Some(PersistentCache::load(cache_path))
+    }
+
+    /// Verify the requests with the server, if there is one, or otherwise locally, printing
+    /// their progress. The results are in the order of the requests. Fails if the local
+    /// verification backend cannot be created.
+    fn verify_requests(
+        &self,
+        requests: Vec<VerificationRequest>,
+        procedure_names: HashMap<String, String>,
+    ) -> Result<VerifiedRequests, PrustiError> {
+        if requests.is_empty() {
+            return Ok(VerifiedRequests::default());
+        }
+        let progress_printer = Arc::new(ProgressPrinter::new(procedure_names, requests.len()));
+        let on_progress = move |progress: VerificationProgress| progress_printer.report(progress);
+        // The requests whose result was found in the cache of the server.
+        let server_cached_requests = Arc::new(Mutex::new(HashSet::new()));
+        // The requests on whose result Silicon and Carbon disagree, in cross-check mode.
+        let disagreements = Arc::new(Mutex::new(vec![]));
+        let backend_mode = BackendMode::from_config()
+            .map_err(|error| PrustiError::internal(error, DUMMY_SP.into()))?;
+        // Viper does not support parallel verifiers in one JVM, so only the requests to a
+        // server, which runs them in separate processes or one after the other, are sent
+        // concurrently.
+        let mut max_concurrency = 1;
+        // Only the server can stop a cancelled verification, by killing its worker process, so
+        // locally the backends of the other modes verify a request one after the other.
+        let mut backends_concurrently = false;
+        let verify: Arc<VerifyRequest> = if let Some(service) = self.connect_to_server() {
+            backends_concurrently = true;
+            max_concurrency = config::num_parallel_verifiers().unwrap_or_else(num_cpus::get);
+            let server_cached_requests = server_cached_requests.clone();
+            Arc::new(move |request: VerificationRequest,
+                           on_progress: &dyn Fn(VerificationProgress),
+                           cancellation: &Cancellation| {
+                let program_name = request.program_name.clone();
+                let start_time = Instant::now();
+                match service.verify_cancellable(request, on_progress, cancellation) {
+                    Ok(response) => {
+                        if response.cached {
+                            server_cached_requests.lock().unwrap().insert(program_name);
+                        }
+                        response.result
+                    }
+                    Err(error) => {
+                        error!("Verification request to server failed: {}", error);
+                        let result = Err(VerifierError::Panicked);
+                        on_progress(VerificationProgress::Finished {
+                            program_name,
+                            outcome: VerificationOutcome::of(&result),
+                            duration: start_time.elapsed(),
+                        });
+                        result
+                    }
+                }
+            })
         } else {
-            let mut stopwatch = Stopwatch::start("prusti-viper", "JVM startup");
-            let verifier_builder = VerifierBuilder::new();
-            stopwatch.start_next("running verifier");
-            VerifierRunner::with_default_configured_runner(&verifier_builder, |runner| {
-                runner.verify(program, program_name.as_str())
+            // Each request is verified by both Silicon and Carbon in the other modes.
+            let cache_size = if backend_mode == BackendMode::Single { 1 } else { 2 };
+            let backend = create_backend(&config::verification_backend(), cache_size)
+                .map_err(|error| PrustiError::internal(
+                    format!("could not create the verification backend: {}", error),
+                    DUMMY_SP.into(),
+                ))?;
+            Arc::new(move |request: VerificationRequest,
+                           on_progress: &dyn Fn(VerificationProgress),
+                           cancellation: &Cancellation| {
+                backend.verify(request, on_progress, cancellation)
             })
         };
+        let results = {
+            let disagreements = disagreements.clone();
+            verify_concurrently(
+                requests,
+                max_concurrency,
+                move |request, on_progress| {
+                    if backend_mode == BackendMode::Single {
+                        return verify(request, on_progress, &Cancellation::default());
+                    }
+                    let (result, disagreement) = verify_with_backends(
+                        backend_mode,
+                        request,
+                        &verify,
+                        on_progress,
+                        backends_concurrently,
+                    );
+                    disagreements.lock().unwrap().extend(disagreement);
+                    result
+                },
+                on_progress,
+            )
+        };
+        let server_cached_requests = server_cached_requests.lock().unwrap().clone();
+        let disagreements = std::mem::take(&mut *disagreements.lock().unwrap());
+        Ok(VerifiedRequests { results, server_cached_requests, disagreements })
+    }
 
-        stopwatch.finish();
-
-        let verification_errors = match verification_result {
-            viper::VerificationResult::Success() => vec![],
-            viper::VerificationResult::Failure(errors) => errors,
-            viper::VerificationResult::ConsistencyErrors(errors) => {
-                debug_assert!(!errors.is_empty());
-                errors.iter().for_each(|e| {
-                    PrustiError::internal(
-                        format!("consistency error: {}", e), DUMMY_SP.into()
-                    ).emit(self.env)
+    /// Emit the errors of the verification results, given together with the name of their
+    /// request and of the method that they verify.
+    fn report_verification_results<I>(
+        &self,
+        results: I,
+        encoded_procedures: &HashMap<String, ProcedureDefId>,
+    ) -> ReportedResults
+    where
+        I: Iterator<Item = (
+            (String, Option<String>),
+            (RemoteVerificationResult, Option<Duration>),
+        )>,
+    {
+        // A function can be part of several programs, so its errors can be reported many times.
+        let mut verification_errors: Vec<(Option<String>, viper::VerificationError)> = vec![];
+        let mut reported_results = ReportedResults::default();
+        for ((request_name, method_name), (verification_result, verification_time)) in results {
+            // The errors of the request that are not verification errors, with their kind.
+            let mut request_errors = vec![];
+            let mut timed_out = false;
+            match verification_result {
+                Ok(viper::VerificationResult::Success()) => {}
+                Ok(viper::VerificationResult::Failure(errors)) => {
+                    for error in errors {
+                        let is_duplicate = verification_errors.iter().any(|(_, other)| {
+                            other.full_id == error.full_id
+                                && other.pos_id == error.pos_id
+                                && other.reason_pos_id == error.reason_pos_id
+                        });
+                        if !is_duplicate {
+                            verification_errors.push((method_name.clone(), error));
+                        }
+                    }
+                }
+                Ok(viper::VerificationResult::ConsistencyErrors(errors)) => {
+                    debug_assert!(!errors.is_empty());
+                    request_errors.extend(errors.iter().map(|e| {
+                        ("internal", PrustiError::internal(
+                            format!("consistency error in {}: {}", request_name, e),
+                            DUMMY_SP.into()
+                        ))
+                    }));
+                }
+                Ok(viper::VerificationResult::JavaException(exception)) => {
+                    error!("Java exception: {}", exception.get_stack_trace());
+                    request_errors.push(("internal", PrustiError::internal(
+                        format!("{}", exception), DUMMY_SP.into()
+                    )));
+                }
+                // The server reports the timeouts of the backend as `VerifierError::TimedOut`.
+                Ok(viper::VerificationResult::TimedOut) => {
+                    request_errors.push(("timeout", PrustiError::internal(
+                        format!("the backend timed out while verifying {}", request_name),
+                        DUMMY_SP.into()
+                    )));
+                    timed_out = true;
+                }
+                Err(error) => {
+                    timed_out = matches!(error, VerifierError::TimedOut(_));
+                    request_errors.push(self.translate_verifier_error(
+                        error,
+                        &request_name,
+                        method_name.as_deref(),
+                        encoded_procedures,
+                    ));
+                }
+            }
+            if !request_errors.is_empty() {
+                reported_results.failed = true;
+            }
+            if let Some(method_name) = &method_name {
+                reported_results.method_outcomes.insert(method_name.clone(), MethodOutcome {
+                    verification_time,
+                    failed: !request_errors.is_empty(),
+                    timed_out,
                 });
-                return VerificationResult::Failure;
             }
-            viper::VerificationResult::JavaException(exception) => {
-                error!("Java exception: {}", exception.get_stack_trace());
-                PrustiError::internal(
-                    format!("{}", exception), DUMMY_SP.into()
-                ).emit(self.env);
-                return VerificationResult::Failure;
+            for (error_kind, request_error) in request_errors {
+                let error_kind = error_kind.to_string();
+                reported_results.reported_errors.push(
+                    (method_name.clone(), error_kind, request_error.clone())
+                );
+                request_error.emit(self.env);
             }
-        };
+        }
 
-        if encoding_errors_count == 0 && verification_errors.is_empty() {
-            VerificationResult::Success
+        reported_results.has_verification_errors = !verification_errors.is_empty();
+        let error_manager = self.encoder.error_manager();
+        for (method_name, verification_error) in verification_errors {
+            debug!("Verification error: {:?}", verification_error);
+            let prusti_error = error_manager.translate_verification_error(
+                &verification_error,
+                method_name.as_deref(),
+            );
+            let error_kind = error_manager.translate_error_kind(&verification_error).to_string();
+            debug!("Prusti error: {:?}", prusti_error);
+            reported_results.reported_errors.push((method_name, error_kind, prusti_error.clone()));
+            prusti_error.emit(self.env);
+        }
+        reported_results
+    }
+
+    /// The error to report when the verifier did not produce a result for a request, together
+    /// with its kind.
+    fn translate_verifier_error(
+        &self,
+        error: VerifierError,
+        request_name: &str,
+        method_name: Option<&str>,
+        encoded_procedures: &HashMap<String, ProcedureDefId>,
+    ) -> (&'static str, PrustiError) {
+        match error {
+            VerifierError::Panicked => ("internal", PrustiError::internal(
+                format!("the verifier panicked while verifying {}", request_name),
+                DUMMY_SP.into()
+            )),
+            VerifierError::Cancelled => ("internal", PrustiError::internal(
+                format!("the verification of {} was cancelled", request_name),
+                DUMMY_SP.into()
+            )),
+            VerifierError::TimedOut(timeout) => {
+                let opt_proc_id = method_name
+                    .and_then(|method_name| encoded_procedures.get(method_name));
+                let (item_name, span) = match opt_proc_id {
+                    Some(&proc_id) => (
+                        self.env.get_absolute_item_name(proc_id),
+                        self.env.get_item_span(proc_id),
+                    ),
+                    None => (request_name.to_string(), DUMMY_SP),
+                };
+                ("timeout", PrustiError::verification(
+                    format!(
+                        "the verification of {} timed out after {} seconds",
+                        item_name, timeout
+                    ),
+                    span.into()
+                ).set_help(
+                    "The timeout can be increased with the `#[timeout(seconds)]` attribute \
+                    or the PROCEDURE_TIMEOUT configuration flag."
+                ))
+            }
+        }
+    }
+
+    /// Connect to the server given by `SERVER_ADDRESS`, if any. If the server is unreachable or
+    /// incompatible, a warning is reported and the programs are verified locally instead.
+    fn connect_to_server(&self) -> Option<PrustiServerConnection> {
+        let server_address = config::server_address()?;
+        let server_address = if server_address == "MOCK" {
+            match ServerSideService::spawn_off_thread() {
+                Ok(address) => address.to_string(),
+                Err(error) => {
+                    let mut warning = PrustiError::internal(
+                        format!(
+                            "could not start the mock Prusti server, verifying locally instead: {}",
+                            error
+                        ),
+                        DUMMY_SP.into(),
+                    );
+                    warning.set_warning();
+                    warning.emit(self.env);
+                    return None;
+                }
+            }
         } else {
-            let error_manager = self.encoder.error_manager();
+            server_address
+        };
+        info!("Connecting to Prusti server at {}", server_address);
+        match PrustiServerConnection::connect(&server_address) {
+            Ok(service) => Some(service),
+            Err(error) => {
+                let mut warning = PrustiError::internal(
+                    format!(
+                        "could not use the Prusti server at {}, verifying locally instead: {}",
+                        server_address, error
+                    ),
+                    DUMMY_SP.into(),
+                );
+                warning.set_warning();
+                warning.emit(self.env);
+                None
+            }
+        }
+    }
 
-            for verification_error in verification_errors {
-                debug!("Verification error: {:?}", verification_error);
-                let prusti_error = error_manager.translate_verification_error(&verification_error);
-                debug!("Prusti error: {:?}", prusti_error);
-                prusti_error.emit(self.env);
+    /// Warn that the backends disagree on the result of a request, listing the errors that each
+    /// of them reported.
+    fn report_disagreement(
+        &self,
+        disagreement: Disagreement,
+        method_name: Option<&str>,
+        encoded_procedures: &HashMap<String, ProcedureDefId>,
+    ) {
+        let opt_proc_id = method_name
+            .and_then(|method_name| encoded_procedures.get(method_name));
+        let (item_name, span) = match opt_proc_id {
+            Some(&proc_id) => (
+                self.env.get_absolute_item_name(proc_id),
+                self.env.get_item_span(proc_id),
+            ),
+            None => (disagreement.program_name.clone(), DUMMY_SP),
+        };
+        let backends: Vec<String> = disagreement.results.iter()
+            .map(|(backend, _)| backend.to_string())
+            .collect();
+        let mut warning = PrustiError::verification(
+            format!(
+                "{} disagree on the verification of {}",
+                backends.join(" and "),
+                item_name
+            ),
+            span.into(),
+        );
+        let error_manager = self.encoder.error_manager();
+        for (backend, result) in disagreement.results {
+            match result {
+                Ok(viper::VerificationResult::Failure(errors)) => {
+                    warning = warning.add_note(
+                        format!("{} reported {} verification errors", backend, errors.len()),
+                        None,
+                    );
+                    for error in &errors {
+                        let prusti_error = error_manager
+                            .translate_verification_error(error, method_name);
+                        warning = warning.add_note(
+                            format!("{}: {}", backend, prusti_error.message()),
+                            Some(prusti_error.span().clone()),
+                        );
+                    }
+                }
+                result => {
+                    warning = warning.add_note(
+                        format!("{}: {}", backend, describe_result(&result)),
+                        None,
+                    );
+                }
+            }
+        }
+        warning.set_warning();
+        warning.emit(self.env);
+    }
+
+    /// The verification timeout of a procedure, in seconds: the one given by its
+    /// `#[timeout(seconds)]` attribute, or otherwise the global `PROCEDURE_TIMEOUT`.
+    fn get_procedure_timeout(&self, proc_id: ProcedureDefId) -> Option<u64> {
+        let seconds = match self.env.get_prusti_attribute(proc_id, "timeout") {
+            Some(seconds) => seconds,
+            None => return config::procedure_timeout(),
+        };
+        match seconds.parse() {
+            Ok(seconds) => Some(seconds),
+            Err(error) => {
+                PrustiError::incorrect(
+                    format!("invalid verification timeout {:?}: {}", seconds, error),
+                    self.env.get_item_span(proc_id).into(),
+                ).emit(self.env);
+                config::procedure_timeout()
+            }
+        }
+    }
+
+    /// Add the status and errors of each procedure to the verification report.
+    fn fill_verification_report(
+        &self,
+        report: &mut VerificationReport,
+        task: &VerificationTask,
+        skipped_procedures: &HashSet<ProcedureDefId>,
+        method_outcomes: &HashMap<String, MethodOutcome>,
+        reported_errors: Vec<(Option<String>, String, PrustiError)>,
+    ) {
+        let codemap = self.env.codemap();
+        let method_names: HashMap<ProcedureDefId, String> = self.encoder
+            .get_encoded_procedures()
+            .into_iter()
+            .map(|(method_name, proc_id)| (proc_id, method_name))
+            .collect();
+        let mut errors_by_method: HashMap<Option<String>, Vec<ReportedError>> = HashMap::new();
+        for (method_name, error_kind, prusti_error) in reported_errors {
+            errors_by_method
+                .entry(method_name)
+                .or_default()
+                .push(ReportedError::new(error_kind, &prusti_error, codemap));
+        }
+
+        for &proc_id in &task.procedures {
+            let is_pure_function = self.encoder.is_pure(proc_id);
+            let method_name = method_names.get(&proc_id);
+            let outcome = method_name.and_then(|method_name| method_outcomes.get(method_name));
+            let mut errors: Vec<ReportedError> = self.encoder
+                .get_encoding_errors(proc_id)
+                .iter()
+                .map(|error| ReportedError::new("encoding", error, codemap))
+                .collect();
+            let has_encoding_errors = !errors.is_empty();
+            if let Some(method_name) = method_name {
+                let method_errors = errors_by_method.remove(&Some(method_name.clone()));
+                errors.extend(method_errors.unwrap_or_default());
+            }
+            let is_skipped = skipped_procedures.contains(&proc_id)
+                || (!is_pure_function && self.encoder.is_trusted(proc_id));
+            let status = if is_skipped {
+                ProcedureStatus::Skipped
+            } else if has_encoding_errors {
+                ProcedureStatus::Unsupported
+            } else if outcome.map_or(false, |outcome| outcome.timed_out) {
+                ProcedureStatus::TimedOut
+            } else if errors.iter().any(|error| error.is_error)
+                || outcome.map_or(false, |outcome| outcome.failed)
+            {
+                ProcedureStatus::Failed
+            } else {
+                ProcedureStatus::Verified
+            };
+            report.procedures.push(ProcedureReport {
+                name: self.env.get_absolute_item_name(proc_id),
+                location: Location::from_span(codemap, self.env.get_item_span(proc_id)),
+                pure: is_pure_function,
+                status,
+                cached: outcome.map_or(false, |outcome| outcome.verification_time.is_none()),
+                verification_time: outcome
+                    .and_then(|outcome| outcome.verification_time)
+                    .map(|verification_time| verification_time.as_secs_f64()),
+                errors,
+            });
+        }
+        report.other_errors = errors_by_method.into_iter().flat_map(|(_, errors)| errors).collect();
+    }
+
+    fn write_verification_report(&self, report: &VerificationReport) {
+        let results = vec![
+            config::json_report().map(|path| (report.write_json(&path), path)),
+            config::sarif_report().map(|path| (report.write_sarif(&path), path)),
+        ];
+        for (result, path) in results.into_iter().flatten() {
+            if let Err(error) = result {
+                PrustiError::internal(
+                    format!("failed to write the verification report to {}: {}", path, error),
+                    DUMMY_SP.into()
+                ).emit(self.env);
             }
-            VerificationResult::Failure
         }
     }
 }
+
+/// The results of the requests that have been verified, in the order of the requests.
+#[derive(Default)]
+struct VerifiedRequests {
+    results: Vec<(RemoteVerificationResult, Duration)>,
+    /// The requests whose result was found in the cache of the server.
+    server_cached_requests: HashSet<String>,
+    /// The requests on whose result Silicon and Carbon disagree, in cross-check mode.
+    disagreements: Vec<Disagreement>,
+}
+
+/// The errors that have been reported for the verification results.
+#[derive(Default)]
+struct ReportedResults {
+    /// The errors reported for each Viper method (if any), together with their kind.
+    reported_errors: Vec<(Option<String>, String, PrustiError)>,
+    method_outcomes: HashMap<String, MethodOutcome>,
+    has_verification_errors: bool,
+    /// Whether the verifier failed on a request, e.g. because it crashed or timed out.
+    failed: bool,
+}
+
+/// The outcome of verifying the Viper program of a method.
+struct MethodOutcome {
+    /// The time that it took to verify the program, or `None` if the result was cached.
+    verification_time: Option<Duration>,
+    /// Whether the verifier failed, e.g. because of consistency errors, a crash or a timeout.
+    failed: bool,
+    /// Whether the verification did not finish within the timeout of the procedure.
+    timed_out: bool,
+}
+
+/// Verifies a request with the backend selected by its configuration, reporting its progress.
+type VerifyRequest = dyn Fn(VerificationRequest, &dyn Fn(VerificationProgress), &Cancellation)
+    -> RemoteVerificationResult + Send + Sync;
@@ -164,6 +164,12 @@ fn main() {
         rustc_args.push("-Zcrate-attr=register_tool(prusti)".to_owned());
         rustc_args.push("--cfg=prusti".to_owned());
 
+        if config::cross_crate_specs() {
+            // The specifications of dependencies are encoded from their MIR in the crate
+            // metadata, which must keep the closures of the specifications.
+            rustc_args.push("-Zmir-opt-level=0".to_owned());
+        }
+
         if config::check_overflows() {
             // Some crates might have a `overflow-checks = false` in their `Cargo.toml` to
             // disable integer overflow checks, but we want to ignore that.
@@ -21,8 +21,16 @@ pub fn verify<'tcx>(
     } else {
         debug!("Prepare verification task...");
         let annotated_procedures = env.get_annotated_procedures();
+        let num_annotated_procedures = annotated_procedures.len();
+        let selected_procedures = env.select_procedures_to_verify(annotated_procedures);
+        if selected_procedures.len() < num_annotated_procedures {
+            user::message(format!(
+                "Skipping {} items that have not been selected for verification",
+                num_annotated_procedures - selected_procedures.len()
+            ));
+        }
         let verification_task = VerificationTask {
-            procedures: annotated_procedures,
+            procedures: selected_procedures,
         };
         debug!("Verification task: {:?}", &verification_task);
 
@@ -48,6 +56,15 @@ pub fn verify<'tcx>(
             let verification_result = verifier.verify(&verification_task);
             debug!("Verifier returned {:?}", verification_result);
 
+            if config::enable_cache() {
+                let (cache_hits, cache_misses) = verifier.cache_statistics();
+                user::message(format!(
+                    "Verification cache: {} hits, {} misses",
+                    cache_hits,
+                    cache_misses
+                ));
+            }
+
             verification_result
         };
 
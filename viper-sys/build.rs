@@ -97,6 +97,7 @@ fn main() {
             // Scala
             java_class!("scala.Some", vec![
                 constructor!(),
+                method!("get"),
             ]),
             java_class!("scala.None$", vec![
                 object_getter!(),
@@ -119,6 +120,9 @@ fn main() {
                 constructor!("()V"),
                 method!("updated", "(Ljava/lang/Object;Ljava/lang/Object;)Lscala/collection/immutable/HashMap;"),
             ]),
+            java_class!("scala.collection.immutable.Map", vec![
+                method!("toSeq"),
+            ]),
             java_class!("scala.collection.immutable.Nil$", vec![
                 object_getter!(),
             ]),
@@ -131,10 +135,26 @@ fn main() {
                 object_getter!(),
                 method!("apply"),
             ]),
+            java_class!("scala.Tuple2", vec![
+                method!("_1"),
+                method!("_2"),
+            ]),
             // Silicon
             java_class!("viper.silicon.Silicon", vec![
                 constructor!("(Lviper/silver/plugin/PluginAwareReporter;Lscala/collection/immutable/Seq;)V"),
             ]),
+            java_class!("viper.silicon.interfaces.SiliconMappedCounterexample", vec![
+                method!("converter"),
+            ]),
+            java_class!("viper.silicon.reporting.Converter", vec![
+                method!("extractedModel"),
+            ]),
+            java_class!("viper.silicon.reporting.ExtractedModel", vec![
+                method!("entries"),
+            ]),
+            java_class!("viper.silicon.reporting.RefEntry", vec![
+                method!("fields"),
+            ]),
             // Carbon
             java_class!("viper.carbon.CarbonVerifier", vec![
                 constructor!("()V"),
@@ -613,6 +633,19 @@ fn main() {
                 method!("fullId"),
                 method!("reason"),
                 method!("readableMessage", "()Ljava/lang/String;"),
+                method!("failureContexts"),
+            ]),
+            java_class!("viper.silver.verifier.FailureContext", vec![
+                method!("counterExample"),
+            ]),
+            java_class!("viper.silver.verifier.Counterexample", vec![
+                method!("model"),
+            ]),
+            java_class!("viper.silver.verifier.Model", vec![
+                method!("entries"),
+            ]),
+            java_class!("viper.silver.verifier.ConstantEntry", vec![
+                method!("value"),
             ]),
             java_class!("viper.silver.verifier.ErrorReason", vec![
                 method!("id"),
@@ -142,6 +142,30 @@ impl<'a> JniUtils<'a> {
         res
     }
 
+    /// Converts a Scala Option to a Rust Option<JObject>
+    pub fn option_to_option(&self, option: JObject<'a>) -> Option<JObject<'a>> {
+        if self.is_instance_of(option, "scala/Some") {
+            Some(self.unwrap_result(scala::Some::with(self.env).call_get(option)))
+        } else {
+            None
+        }
+    }
+
+    /// Convert a Scala Map to a Rust Vec<(JObject, JObject)> of its key-value pairs
+    pub fn map_to_vec(&self, map: JObject<'a>) -> Vec<(JObject<'a>, JObject<'a>)> {
+        let tuple_wrapper = scala::Tuple2::with(self.env);
+        let pairs = self.unwrap_result(
+            scala::collection::immutable::Map::with(self.env).call_toSeq(map)
+        );
+        self.seq_to_vec(pairs)
+            .into_iter()
+            .map(|pair| (
+                self.unwrap_result(tuple_wrapper.call__1(pair)),
+                self.unwrap_result(tuple_wrapper.call__2(pair)),
+            ))
+            .collect()
+    }
+
     /// Checks if an object is a subtype of a Java class
     pub fn is_instance_of(&self, object: JObject, class: &str) -> bool {
         let object_class = self.unwrap_result(self.env.get_object_class(object));
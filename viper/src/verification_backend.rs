@@ -4,7 +4,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use std::fmt;
+use std::{fmt, str::FromStr};
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum VerificationBackend {
@@ -12,15 +12,17 @@ pub enum VerificationBackend {
     Carbon,
 }
 
-impl VerificationBackend {
-    pub fn from_str(backend: &str) -> Self {
+impl FromStr for VerificationBackend {
+    type Err = String;
+
+    fn from_str(backend: &str) -> Result<Self, Self::Err> {
         match backend.to_lowercase().as_str() {
-            "silicon" => VerificationBackend::Silicon,
-            "carbon" => VerificationBackend::Carbon,
-            _ => panic!(
+            "silicon" => Ok(VerificationBackend::Silicon),
+            "carbon" => Ok(VerificationBackend::Carbon),
+            _ => Err(format!(
                 "Invalid verification backend: '{}'. Allowed values are 'Silicon' and 'Carbon'",
                 backend
-            ),
+            )),
         }
     }
 }
@@ -17,6 +17,8 @@ pub enum VerificationResult {
     ConsistencyErrors(Vec<String>),
     /// The verification raised a Java exception.
     JavaException(JavaException),
+    /// The verification did not finish within the timeout given to the backend.
+    TimedOut,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -25,6 +27,7 @@ pub struct VerificationError {
     pub pos_id: Option<String>,
     pub reason_pos_id: Option<String>,
     pub message: String,
+    pub counterexample: Option<Counterexample>,
 }
 
 impl VerificationError {
@@ -33,12 +36,21 @@ impl VerificationError {
         pos_id: Option<String>,
         reason_pos_id: Option<String>,
         message: String,
+        counterexample: Option<Counterexample>,
     ) -> Self {
         VerificationError {
             full_id,
             pos_id,
             reason_pos_id,
             message,
+            counterexample,
         }
     }
 }
+
+/// The values that the verifier found for the variables of a failing program.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Counterexample {
+    /// The value of each variable and field, by path (e.g. `_1.val_int`).
+    pub entries: Vec<(String, String)>,
+}
@@ -14,10 +14,14 @@ use jni_utils::JniUtils;
 use std::marker::PhantomData;
 use std::path::PathBuf;
 use verification_backend::VerificationBackend;
-use verification_result::VerificationError;
+use verification_result::{Counterexample, VerificationError};
 use verification_result::VerificationResult;
+use viper_sys::wrappers::scala;
 use viper_sys::wrappers::viper::*;
 
+/// How many fields deep the values of references are reported in counterexamples.
+const COUNTEREXAMPLE_DEPTH: usize = 8;
+
 pub mod state {
     pub struct Uninitialized;
     pub struct Stopped;
@@ -59,18 +63,30 @@ impl<'a, VerifierState> Verifier<'a, VerifierState> {
             VerificationBackend::Carbon => carbon::CarbonVerifier::with(env).new(),
         });
 
-        let name = jni.to_string(jni.unwrap_result(verifier_wrapper.call_name(verifier_instance)));
-        let build_version =
-            jni.to_string(jni.unwrap_result(verifier_wrapper.call_buildVersion(verifier_instance)));
-        info!("Using backend {} version {}", name, build_version);
-
-        Verifier {
+        let verifier = Verifier {
             env,
             verifier_wrapper,
             verifier_instance,
             jni,
             state: PhantomData,
-        }
+        };
+        info!("Using backend {} version {}", verifier.name(), verifier.build_version());
+        verifier
+    }
+
+    /// The name of the backend, e.g. "silicon".
+    pub fn name(&self) -> String {
+        self.jni.to_string(
+            self.jni
+                .unwrap_result(self.verifier_wrapper.call_name(self.verifier_instance)),
+        )
+    }
+
+    pub fn build_version(&self) -> String {
+        self.jni.to_string(
+            self.jni
+                .unwrap_result(self.verifier_wrapper.call_buildVersion(self.verifier_instance)),
+        )
     }
 }
 
@@ -178,6 +194,14 @@ impl<'a> Verifier<'a, state::Started> {
                     .is_instance_of(viper_error, "viper/silver/verifier/VerificationError");
 
                 if !is_verification_error {
+                    let is_timeout = self
+                        .jni
+                        .is_instance_of(viper_error, "viper/silver/verifier/TimeoutOccurred");
+                    if is_timeout {
+                        debug!("The verification timed out: {}", self.jni.to_string(viper_error));
+                        return VerificationResult::TimedOut;
+                    }
+
                     let is_aborted_exceptionally = self
                         .jni
                         .is_instance_of(viper_error, "viper/silver/verifier/AbortedExceptionally");
@@ -259,11 +283,14 @@ impl<'a> Verifier<'a, state::Started> {
                         None
                     };
 
+                let counterexample = self.get_counterexample(viper_error);
+
                 errors.push(VerificationError::new(
                     error_full_id,
                     pos_id,
                     reason_pos_id,
                     message,
+                    counterexample,
                 ))
             }
 
@@ -272,4 +299,101 @@ impl<'a> Verifier<'a, state::Started> {
             VerificationResult::Success()
         }
     }
+
+    /// Extract the counterexample of a verification error, if the verifier produced one.
+    fn get_counterexample(&self, viper_error: JObject<'a>) -> Option<Counterexample> {
+        let failure_context_wrapper = silver::verifier::FailureContext::with(self.env);
+        let failure_contexts = self.jni.seq_to_vec(self.jni.unwrap_result(
+            silver::verifier::VerificationError::with(self.env).call_failureContexts(viper_error),
+        ));
+        let counterexample = failure_contexts.into_iter().find_map(|failure_context| {
+            self.jni.option_to_option(self.jni.unwrap_result(
+                failure_context_wrapper.call_counterExample(failure_context),
+            ))
+        })?;
+
+        let mut entries = vec![];
+        if self
+            .jni
+            .is_instance_of(counterexample, "viper/silicon/interfaces/SiliconMappedCounterexample")
+        {
+            // Silicon maps the model back to the variables and heap of the program.
+            let converter = self.jni.unwrap_result(
+                silicon::interfaces::SiliconMappedCounterexample::with(self.env)
+                    .call_converter(counterexample),
+            );
+            let extracted_model = self.jni.unwrap_result(
+                silicon::reporting::Converter::with(self.env).call_extractedModel(converter),
+            );
+            let model_entries = self.jni.unwrap_result(
+                silicon::reporting::ExtractedModel::with(self.env).call_entries(extracted_model),
+            );
+            for (name, entry) in self.jni.map_to_vec(model_entries) {
+                self.collect_extracted_entries(
+                    self.jni.get_string(name),
+                    entry,
+                    COUNTEREXAMPLE_DEPTH,
+                    &mut entries,
+                );
+            }
+        } else {
+            // Otherwise, only the values of variables with a constant value can be reported.
+            let model = self.jni.unwrap_result(
+                silver::verifier::Counterexample::with(self.env).call_model(counterexample),
+            );
+            let model_entries = self.jni.unwrap_result(
+                silver::verifier::Model::with(self.env).call_entries(model),
+            );
+            for (name, entry) in self.jni.map_to_vec(model_entries) {
+                if self
+                    .jni
+                    .is_instance_of(entry, "viper/silver/verifier/ConstantEntry")
+                {
+                    let value = self.jni.unwrap_result(
+                        silver::verifier::ConstantEntry::with(self.env).call_value(entry),
+                    );
+                    entries.push((self.jni.get_string(name), self.jni.get_string(value)));
+                }
+            }
+        }
+        entries.sort();
+
+        Some(Counterexample { entries })
+    }
+
+    /// Collect the values of an entry of a mapped Silicon model, following the fields of
+    /// references up to `depth` times.
+    fn collect_extracted_entries(
+        &self,
+        path: String,
+        entry: JObject<'a>,
+        depth: usize,
+        entries: &mut Vec<(String, String)>,
+    ) {
+        if self
+            .jni
+            .is_instance_of(entry, "viper/silicon/reporting/RefEntry")
+        {
+            if depth == 0 {
+                return;
+            }
+            let fields = self.jni.unwrap_result(
+                silicon::reporting::RefEntry::with(self.env).call_fields(entry),
+            );
+            for (field_name, field_entry) in self.jni.map_to_vec(fields) {
+                // The value of a field is paired with the permission amount to it.
+                let field_value = self.jni.unwrap_result(
+                    scala::Tuple2::with(self.env).call__1(field_entry),
+                );
+                self.collect_extracted_entries(
+                    format!("{}.{}", path, self.jni.get_string(field_name)),
+                    field_value,
+                    depth - 1,
+                    entries,
+                );
+            }
+        } else {
+            entries.push((path, self.jni.to_string(entry)));
+        }
+    }
 }